@@ -1,7 +1,7 @@
 use winit::{
     event::{DeviceEvent, ElementState, Event, KeyboardInput, WindowEvent},
     event_loop::{ControlFlow, EventLoop},
-    window::WindowBuilder,
+    window::{CursorGrabMode, WindowBuilder},
 };
 
 fn main() {
@@ -28,11 +28,22 @@ fn main() {
                     ..
                 } => {
                     use winit::event::VirtualKeyCode::*;
-                    match key {
-                        Escape => *control_flow = ControlFlow::Exit,
-                        G => window.set_cursor_grab(!modifiers.shift).unwrap(),
-                        H => window.set_cursor_visible(modifiers.shift),
-                        _ => (),
+                    let result = match key {
+                        Escape => {
+                            *control_flow = ControlFlow::Exit;
+                            Ok(())
+                        }
+                        G if modifiers.shift => window.set_cursor_grab(CursorGrabMode::None),
+                        G => window.set_cursor_grab(CursorGrabMode::Confined),
+                        L => window.set_cursor_grab(CursorGrabMode::Locked),
+                        H => {
+                            window.set_cursor_visible(modifiers.shift);
+                            Ok(())
+                        }
+                        _ => Ok(()),
+                    };
+                    if let Err(err) = result {
+                        println!("error: {}", err);
                     }
                 }
                 _ => (),
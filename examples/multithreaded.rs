@@ -4,7 +4,7 @@ use std::{collections::HashMap, sync::mpsc, thread, time::Duration};
 use winit::{
     event::{ElementState, Event, KeyboardInput, VirtualKeyCode, WindowEvent},
     event_loop::{ControlFlow, EventLoop},
-    window::{CursorIcon, Fullscreen, WindowBuilder},
+    window::{CursorGrabMode, CursorIcon, Fullscreen, WindowBuilder},
 };
 
 const WINDOW_COUNT: usize = 3;
@@ -88,7 +88,13 @@ fn main() {
                                 )),
                                 (false, _) => None,
                             }),
-                            G => window.set_cursor_grab(state).unwrap(),
+                            G => window
+                                .set_cursor_grab(if state {
+                                    CursorGrabMode::Confined
+                                } else {
+                                    CursorGrabMode::None
+                                })
+                                .unwrap(),
                             H => window.set_cursor_visible(!state),
                             I => {
                                 println!("Info:");
@@ -8,6 +8,7 @@ use std::{path::PathBuf, time::Instant};
 
 use crate::{
     dpi::{LogicalPosition, LogicalSize},
+    keyboard::{Key, KeyCode, KeyLocation, KeyboardLayout},
     platform_impl,
     window::WindowId,
 };
@@ -42,6 +43,17 @@ pub enum Event<T> {
 
     /// Emitted when the application has been resumed.
     Resumed,
+
+    /// Emitted when the content of a selection, requested with
+    /// `EventLoopWindowTargetExtUnix::request_selection`, has been transferred.
+    ///
+    /// ## Platform-specific
+    ///
+    /// - Only emitted on Linux, DragonFly, FreeBSD, NetBSD and OpenBSD.
+    SelectionEvent {
+        selection: Selection,
+        event: SelectionEvent,
+    },
 }
 
 impl<T> Event<T> {
@@ -56,6 +68,7 @@ impl<T> Event<T> {
             LoopDestroyed => Ok(LoopDestroyed),
             Suspended => Ok(Suspended),
             Resumed => Ok(Resumed),
+            SelectionEvent { selection, event } => Ok(SelectionEvent { selection, event }),
         }
     }
 }
@@ -119,9 +132,72 @@ pub enum WindowEvent {
     /// hovered.
     HoveredFileCancelled,
 
+    /// Data is being dragged over the window.
+    ///
+    /// This is emitted when the drag enters the window and every time it moves over it. The
+    /// offer lists the MIME types the data is available as, and can be used to request it. The
+    /// reply tells the source whether the data can be dropped at this position.
+    ///
+    /// ## Platform-specific
+    ///
+    /// - Only emitted on Linux, DragonFly, FreeBSD, NetBSD and OpenBSD.
+    DragHovered {
+        offer: DragOffer,
+        position: LogicalPosition,
+        reply: DragReply,
+    },
+
+    /// The dragged data has left the window without being dropped.
+    ///
+    /// ## Platform-specific
+    ///
+    /// - Only emitted on Linux, DragonFly, FreeBSD, NetBSD and OpenBSD.
+    DragLeft,
+
+    /// The dragged data has been dropped on the window.
+    ///
+    /// ## Platform-specific
+    ///
+    /// - Only emitted on Linux, DragonFly, FreeBSD, NetBSD and OpenBSD.
+    DragDropped {
+        offer: DragOffer,
+        position: LogicalPosition,
+    },
+
+    /// The outcome of a call to `DragOffer::request_data`.
+    ///
+    /// ## Platform-specific
+    ///
+    /// - Only emitted on Linux, DragonFly, FreeBSD, NetBSD and OpenBSD.
+    DragData(SelectionEvent),
+
+    /// A drag and drop operation started with `Window::start_drag` has ended.
+    ///
+    /// Contains the action performed by the target the data was dropped on, or `None` if the
+    /// operation was cancelled or the drop rejected.
+    ///
+    /// ## Platform-specific
+    ///
+    /// - Only emitted on Linux, DragonFly, FreeBSD, NetBSD and OpenBSD.
+    DragEnded(Option<DragAction>),
+
     /// The window received a unicode character.
     ReceivedCharacter(char),
 
+    /// The state of the input method of the window changed.
+    ///
+    /// Text composed with an input method is first shown with `Ime::Preedit` events as it's being
+    /// edited, and is then committed with `Ime::Commit`.
+    ///
+    /// ## Platform-specific
+    ///
+    /// - **X11:** The text being composed is only reported by input methods that let the
+    ///   application draw it (the "on-the-spot" style). With other input methods, the committed
+    ///   text is received with `ReceivedCharacter` events.
+    /// - **Wayland:** Requires the compositor to support the text input protocol.
+    /// - **Windows / macOS / iOS / Android / Web:** Unsupported.
+    Ime(Ime),
+
     /// The window gained or lost focus.
     ///
     /// The parameter is true if the window has gained focus, and false if it has lost focus.
@@ -133,6 +209,42 @@ pub enum WindowEvent {
         input: KeyboardInput,
     },
 
+    /// A key was pressed or released.
+    ///
+    /// This is sent alongside `KeyboardInput`, and describes the key both by its location and by
+    /// its meaning under the keyboard layout.
+    ///
+    /// ## Platform-specific
+    ///
+    /// - Only emitted on Linux, DragonFly, FreeBSD, NetBSD and OpenBSD.
+    Key {
+        device_id: DeviceId,
+        event: KeyEvent,
+    },
+
+    /// The state of the keyboard modifiers changed.
+    ///
+    /// This is sent when a modifier is pressed or released while the window has focus, and when
+    /// the window gains focus with modifiers already active. When it loses focus, the modifiers
+    /// are reported as released.
+    ///
+    /// ## Platform-specific
+    ///
+    /// - Only emitted on Linux, DragonFly, FreeBSD, NetBSD and OpenBSD. On X11, requires
+    ///   libxkbcommon-x11.
+    ModifiersChanged(ModifiersState),
+
+    /// The active keyboard layout changed, or the keymap was replaced.
+    ///
+    /// This is sent to the window with the keyboard focus, which is also told the active layout
+    /// when it gains focus if it changed in the meantime.
+    ///
+    /// ## Platform-specific
+    ///
+    /// - Only emitted on Linux, DragonFly, FreeBSD, NetBSD and OpenBSD. On X11, requires
+    ///   libxkbcommon-x11.
+    KeyboardLayoutChanged(KeyboardLayout),
+
     /// The cursor has moved on the window.
     CursorMoved {
         device_id: DeviceId,
@@ -158,6 +270,34 @@ pub enum WindowEvent {
         modifiers: ModifiersState,
     },
 
+    /// A mouse wheel movement or touchpad scroll occurred, reported with both its detents and its
+    /// smooth delta.
+    ///
+    /// Every scroll is also reported as a `MouseWheel` event. When a touchpad or another
+    /// continuous source stops scrolling, a `WheelInput` event with a null delta and the `Ended`
+    /// phase is sent, which is where applications can start kinetic scrolling.
+    ///
+    /// ## Platform-specific
+    ///
+    /// - Only emitted on X11 and Wayland.
+    /// - **X11:** The source is only known for the scrolls reported as buttons, and stop events
+    ///   are never sent. The smooth scrolls of every device, touchpads included, are counted as
+    ///   detents each time they add up to a whole scroll increment of the device.
+    WheelInput {
+        device_id: DeviceId,
+        /// The number of detents the wheel has been turned by in the horizontal and vertical
+        /// directions, with the same sign convention as `MouseScrollDelta`. This is zero for the
+        /// scrolls that don't come from a wheel, except on X11.
+        detents: (i32, i32),
+        /// The smooth scroll delta, with the precision reported by the device. High-resolution
+        /// wheels report fractions of detents here.
+        delta: WheelDelta,
+        /// The kind of device that produced the scroll, if known.
+        source: Option<ScrollSource>,
+        phase: TouchPhase,
+        modifiers: ModifiersState,
+    },
+
     /// An mouse button press has been received.
     MouseInput {
         device_id: DeviceId,
@@ -177,6 +317,41 @@ pub enum WindowEvent {
         stage: i64,
     },
 
+    /// Pinch gesture on a touchpad, usually used to zoom.
+    ///
+    /// At the moment, only supported on X11 and Wayland, where each pinch is also reported with
+    /// `RotationGesture` events of the same phases. On X11, it needs XInput 2.4.
+    PinchGesture {
+        device_id: DeviceId,
+        /// The change of the scale since the last event. The scale starts at 1.0 when the gesture
+        /// begins and is the ratio of the current distance between the fingers to their initial
+        /// distance, so positive values mean the fingers are moving apart.
+        delta: f64,
+        phase: TouchPhase,
+    },
+
+    /// Rotation of the fingers of a pinch gesture on a touchpad.
+    ///
+    /// At the moment, only supported on X11 and Wayland. On X11, it needs XInput 2.4.
+    RotationGesture {
+        device_id: DeviceId,
+        /// The rotation since the last event, in degrees clockwise.
+        delta: f64,
+        phase: TouchPhase,
+    },
+
+    /// Swipe gesture of three or more fingers on a touchpad.
+    ///
+    /// At the moment, only supported on X11 and Wayland. On X11, it needs XInput 2.4.
+    SwipeGesture {
+        device_id: DeviceId,
+        /// The number of fingers, which is the same for the whole gesture.
+        fingers: u32,
+        /// The motion of the center of the fingers since the last event.
+        delta: LogicalPosition,
+        phase: TouchPhase,
+    },
+
     /// Motion on some analog axis. May report data redundant to other, more specific events.
     AxisMotion {
         device_id: DeviceId,
@@ -190,6 +365,29 @@ pub enum WindowEvent {
     /// Touch event has been received
     Touch(Touch),
 
+    /// A tool of a graphics tablet, such as a pen, was used over the window.
+    ///
+    /// ## Platform-specific
+    ///
+    /// - Only emitted on Linux, DragonFly, FreeBSD, NetBSD and OpenBSD.
+    /// - **X11:** XInput2 has no proximity events, so `ProximityIn` is sent when the tool first
+    ///   moves over the window, and `ProximityOut` when it leaves the window.
+    /// - **Wayland:** Requires the compositor to support the tablet protocol.
+    TabletTool {
+        device_id: DeviceId,
+        event: TabletToolEvent,
+    },
+
+    /// The application lost the ownership of a selection, because another application replaced
+    /// its content.
+    ///
+    /// This event is sent to the window that had keyboard focus when the selection was set.
+    ///
+    /// ## Platform-specific
+    ///
+    /// - Only emitted on Linux, DragonFly, FreeBSD, NetBSD and OpenBSD.
+    SelectionLost(Selection),
+
     /// The DPI factor of the window has changed.
     ///
     /// The following user actions can cause DPI changes:
@@ -202,6 +400,119 @@ pub enum WindowEvent {
     HiDpiFactorChanged(f64),
 }
 
+/// Describes a change to the input method state of a window.
+///
+/// Once enabled, an input method composes text with a series of `Preedit` events, ending with an
+/// empty `Preedit`, usually followed by a `Commit` with the text composed.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum Ime {
+    /// The input method was enabled for the window.
+    Enabled,
+
+    /// The text being composed changed.
+    ///
+    /// Contains the text, and the range of bytes of the text that the cursor covers, or `None` if
+    /// the cursor should be hidden. An empty text means that the composition ended and the text
+    /// previously shown should be removed.
+    Preedit(String, Option<(usize, usize)>),
+
+    /// The composed text was committed, and should be inserted.
+    Commit(String),
+
+    /// The input method was disabled for the window, which won't receive any other `Ime` events
+    /// until it's enabled again.
+    Disabled,
+}
+
+/// A system-wide buffer used to exchange data between applications.
+#[derive(Debug, Hash, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum Selection {
+    /// The clipboard, filled by explicit copy and cut actions.
+    Clipboard,
+
+    /// The primary selection, filled by selecting text and usually pasted with the middle mouse
+    /// button.
+    Primary,
+}
+
+/// Describes the outcome of a transfer of data from a selection or a drag and drop operation.
+#[derive(Clone, Debug, PartialEq)]
+pub enum SelectionEvent {
+    /// The data has been received in the requested MIME type.
+    Received { mime_type: String, data: Vec<u8> },
+
+    /// The data could not be retrieved in the requested MIME type.
+    ///
+    /// This happens when the selection is empty, when its owner doesn't offer the requested MIME
+    /// type, or when the transfer was interrupted.
+    Failed { mime_type: String },
+}
+
+/// Data dragged over a window by a drag and drop operation.
+///
+/// Clones of an offer all refer to the same operation. Once the data has been dropped, the
+/// operation is only completed when all of them have been dropped, so the data should be
+/// requested before that.
+#[derive(Clone, Debug, PartialEq)]
+pub struct DragOffer(pub(crate) platform_impl::DragOffer);
+
+impl DragOffer {
+    /// Returns the MIME types the data is offered as.
+    pub fn mime_types(&self) -> &[String] {
+        self.0.mime_types()
+    }
+
+    /// Requests the data in the given MIME type, which should be one of the offered ones.
+    ///
+    /// The data is delivered to the window through `WindowEvent::DragData`.
+    pub fn request_data(&self, mime_type: &str) {
+        self.0.request_data(mime_type.to_owned())
+    }
+}
+
+/// Replies to a `WindowEvent::DragHovered` event, telling the source of the drag whether its data
+/// can be dropped at the hovered position and what would be done with it.
+///
+/// Only the first reply made through a handle or its clones is taken into account. If none has
+/// been made once they've all been dropped, the drop is accepted with `DragAction::Copy`.
+///
+/// The outcome of a drop is reported to the source according to the reply made for the last
+/// position it was hovered at.
+#[derive(Clone, Debug, PartialEq)]
+pub struct DragReply(pub(crate) platform_impl::DragReply);
+
+impl DragReply {
+    /// Accepts the drop at the hovered position, with the given action.
+    pub fn accept(&self, action: DragAction) {
+        self.0.reply(Some(action))
+    }
+
+    /// Rejects the drop at the hovered position.
+    pub fn reject(&self) {
+        self.0.reply(None)
+    }
+}
+
+/// Describes what is done with data dropped on a window.
+#[derive(Debug, Hash, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum DragAction {
+    /// The data is copied, leaving the source unchanged.
+    Copy,
+
+    /// The data is moved, and the source removes it once the drop is done.
+    Move,
+
+    /// A link to the data is created.
+    ///
+    /// ## Platform-specific
+    ///
+    /// - **Wayland:** Unsupported, treated as `Copy`.
+    Link,
+}
+
 /// Identifier of an input device.
 ///
 /// Whenever you receive an event arising from a particular input device, this event contains a `DeviceId` which
@@ -261,12 +572,82 @@ pub enum DeviceEvent {
         button: ButtonId,
         state: ElementState,
     },
+
+    /// Press or release of a button of a gamepad or joystick.
+    ///
+    /// At the moment, only supported on Linux, where the gamepads are read from their evdev
+    /// devices, which `DeviceEvent::Added` and `DeviceEvent::Removed` report being plugged and
+    /// unplugged. A D-pad reported as a hat axis is reported as its buttons.
+    GamepadButton {
+        button: GamepadButton,
+        state: ElementState,
+    },
+
+    /// Motion on an axis of a gamepad or joystick.
+    ///
+    /// The sticks range from -1.0 to 1.0, with the positive values to the right and down, and
+    /// are 0.0 when centered. The analog triggers range from 0.0 when released to 1.0.
+    ///
+    /// At the moment, only supported on Linux, like `GamepadButton`.
+    GamepadAxis {
+        axis: GamepadAxis,
+        value: f64,
+    },
+
     Key(KeyboardInput),
     Text {
         codepoint: char,
     },
 }
 
+/// A button of a gamepad, named after the layout of the common console controllers.
+///
+/// The face buttons are named after their position, the `South` button being the A button of an
+/// Xbox controller and the cross of a PlayStation controller.
+#[derive(Debug, Hash, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum GamepadButton {
+    South,
+    East,
+    North,
+    West,
+    LeftBumper,
+    RightBumper,
+    /// The left trigger, for the gamepads reporting it as a button as well as an axis, or only
+    /// as a button.
+    LeftTrigger,
+    RightTrigger,
+    Select,
+    Start,
+    /// The button in the middle of the gamepad, such as the Xbox or PS button.
+    Mode,
+    /// The click of the left stick.
+    LeftStick,
+    RightStick,
+    DPadUp,
+    DPadDown,
+    DPadLeft,
+    DPadRight,
+    /// A button outside of the standard layout, such as the buttons of a joystick, with its
+    /// platform-specific code.
+    Other(u16),
+}
+
+/// An axis of a gamepad, named after the layout of the common console controllers.
+#[derive(Debug, Hash, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum GamepadAxis {
+    LeftStickX,
+    LeftStickY,
+    RightStickX,
+    RightStickY,
+    LeftTrigger,
+    RightTrigger,
+    /// An axis outside of the standard layout, such as the throttle of a joystick, with its
+    /// platform-specific code. It ranges from -1.0 to 1.0 like the sticks.
+    Other(u16),
+}
+
 /// Describes a keyboard input event.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
@@ -291,6 +672,42 @@ pub struct KeyboardInput {
     /// This is tracked internally to avoid tracking errors arising from modifier key state changes when events from
     /// this device are not being delivered to the application, e.g. due to keyboard focus being elsewhere.
     pub modifiers: ModifiersState,
+
+    /// Whether the key press was caused by the key being held down, rather than being pressed.
+    ///
+    /// This is always `false` for releases.
+    pub is_repeat: bool,
+}
+
+/// Describes a key press or release.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct KeyEvent {
+    /// The key pressed, regardless of the keyboard layout.
+    ///
+    /// Use it when the location of the key matters more than what's printed on it, such as for
+    /// movement controls in a game.
+    pub physical_key: KeyCode,
+
+    /// The meaning of the key under the current keyboard layout and modifiers.
+    ///
+    /// Use it for keyboard shortcuts, and for keys like "page up" that aren't meant to produce
+    /// text.
+    pub logical_key: Key,
+
+    /// The text produced by the key press, if any.
+    ///
+    /// This is always `None` for releases, as well as for presses of dead keys and presses
+    /// handled by an input method, whose text is received with `WindowEvent::Ime` instead.
+    pub text: Option<String>,
+
+    /// Where the key is on the keyboard, for keys found in several places.
+    pub location: KeyLocation,
+
+    pub state: ElementState,
+
+    /// Whether the event was caused by the key being held down, rather than being pressed.
+    pub repeat: bool,
 }
 
 /// Describes touch-screen input state.
@@ -323,10 +740,95 @@ pub struct Touch {
     pub device_id: DeviceId,
     pub phase: TouchPhase,
     pub location: LogicalPosition,
+    /// The pressure of the touch, from 0 to 1, if the device reports it.
+    ///
+    /// ## Platform-specific
+    ///
+    /// Only reported on X11.
+    pub force: Option<f64>,
+    /// The area touched, if the device reports it.
+    ///
+    /// ## Platform-specific
+    ///
+    /// Only reported on X11 and Wayland.
+    pub contact: Option<TouchContact>,
     /// unique identifier of a finger.
     pub id: u64,
 }
 
+/// The area touched by a finger, approximated by an ellipse centered on the location of the touch.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct TouchContact {
+    /// The length of the major axis of the ellipse.
+    pub major: f64,
+    /// The length of the minor axis of the ellipse.
+    pub minor: f64,
+    /// The clockwise angle between the major axis and the y axis, in degrees from -180 to 180.
+    ///
+    /// It's 0 if the device doesn't report it.
+    pub orientation: f64,
+}
+
+/// Describes an event from a tool of a graphics tablet.
+///
+/// While the tool is in range of the tablet, it's reported with `Moved` events, which are sent
+/// between `Down` and `Up` while it touches the tablet.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum TabletToolEvent {
+    /// The tool came in range of the tablet, over the window.
+    ProximityIn(TabletTool),
+    /// The tool went out of range of the tablet, or left the window.
+    ProximityOut,
+    /// The tool started touching the tablet.
+    Down,
+    /// The tool stopped touching the tablet.
+    Up,
+    /// The tool moved, or the state of one of its axes changed.
+    Moved(TabletToolState),
+    /// A button of the tool was pressed or released.
+    ///
+    /// The identifiers of the buttons are platform-specific.
+    Button {
+        button: ButtonId,
+        state: ElementState,
+    },
+}
+
+/// The kind of a tool of a graphics tablet.
+#[derive(Debug, Hash, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum TabletTool {
+    Pen,
+    Eraser,
+    Brush,
+    Pencil,
+    Airbrush,
+    Finger,
+    Mouse,
+    Lens,
+    Unknown,
+}
+
+/// The state of a tool of a graphics tablet.
+///
+/// The axes the tool doesn't have are `None`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct TabletToolState {
+    /// The position of the tool in the window.
+    pub location: LogicalPosition,
+    /// How hard the tool presses on the tablet, from 0 to 1.
+    pub pressure: Option<f64>,
+    /// The distance of the tool from the tablet, from 0 to 1.
+    pub distance: Option<f64>,
+    /// The angles of the tool from the z axis, towards the positive x and y axes, in degrees.
+    pub tilt: Option<(f64, f64)>,
+    /// The clockwise rotation of the tool around its own axis, in degrees from 0 to 360.
+    pub rotation: Option<f64>,
+}
+
 /// Hardware-dependent keyboard scan code.
 pub type ScanCode = u32;
 
@@ -373,6 +875,34 @@ pub enum MouseScrollDelta {
     PixelDelta(LogicalPosition),
 }
 
+/// Describes the smooth scroll delta of a `WheelInput` event.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum WheelDelta {
+    /// Amount in lines or rows to scroll in the horizontal and vertical directions, which can be
+    /// fractional.
+    ///
+    /// Positive values indicate movement forward (away from the user) or rightwards.
+    LineDelta(f64, f64),
+    /// Amount in pixels to scroll in the horizontal and vertical directions.
+    PixelDelta(LogicalPosition),
+}
+
+/// Describes the kind of device that produced a scroll.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum ScrollSource {
+    /// The rotation of a mouse wheel.
+    Wheel,
+    /// The sideways tilt of a mouse wheel.
+    WheelTilt,
+    /// Fingers moving on a touchpad.
+    Finger,
+    /// A device scrolling in a continuous coordinate space without fingers, such as the motion of
+    /// a trackball while a button is held down.
+    Continuous,
+}
+
 /// Symbolic name for a keyboard key.
 #[derive(Debug, Hash, Ord, PartialOrd, PartialEq, Eq, Clone, Copy)]
 #[repr(u32)]
@@ -589,4 +1119,32 @@ pub struct ModifiersState {
     ///
     /// This is the "windows" key on PC and "command" key on Mac.
     pub logo: bool,
+
+    /// The left "shift" key is held.
+    ///
+    /// The left and right keys of each modifier are only told apart on Linux, DragonFly, FreeBSD,
+    /// NetBSD and OpenBSD. They may both be `false` while the modifier is active, for example
+    /// when it's latched or was pressed before the window gained focus.
+    pub lshift: bool,
+    /// The right "shift" key is held.
+    pub rshift: bool,
+    /// The left "control" key is held.
+    pub lctrl: bool,
+    /// The right "control" key is held.
+    pub rctrl: bool,
+    /// The left "alt" key is held.
+    pub lalt: bool,
+    /// The right "alt" key is held.
+    pub ralt: bool,
+    /// The left "logo" key is held.
+    pub llogo: bool,
+    /// The right "logo" key is held.
+    pub rlogo: bool,
+
+    /// Caps Lock is on.
+    ///
+    /// The lock keys are only tracked on Linux, DragonFly, FreeBSD, NetBSD and OpenBSD.
+    pub caps_lock: bool,
+    /// Num Lock is on.
+    pub num_lock: bool,
 }
@@ -1,4 +1,4 @@
-use std::{error::Error, fmt, mem};
+use std::{error::Error, fmt, mem, time::Duration};
 
 #[repr(C)]
 #[derive(Debug)]
@@ -25,6 +25,15 @@ pub enum BadIcon {
         width_x_height: usize,
         pixel_count: usize,
     },
+    /// Produced when the hotspot of a `CustomCursor` lies outside of its image.
+    HotspotOutOfBounds {
+        width: u32,
+        height: u32,
+        hotspot_x: u32,
+        hotspot_y: u32,
+    },
+    /// Produced when `CustomCursor::from_frames` is given no frames.
+    NoFrames,
 }
 
 impl fmt::Display for BadIcon {
@@ -43,6 +52,16 @@ impl fmt::Display for BadIcon {
                 "The specified dimensions ({:?}x{:?}) don't match the number of pixels supplied by the `rgba` argument ({:?}). For those dimensions, the expected pixel count is {:?}.",
                 width, height, pixel_count, width_x_height,
             ),
+            &BadIcon::HotspotOutOfBounds {
+                width,
+                height,
+                hotspot_x,
+                hotspot_y,
+            } => format!(
+                "The specified hotspot ({:?}, {:?}) lies outside of the {:?}x{:?} image.",
+                hotspot_x, hotspot_y, width, height,
+            ),
+            &BadIcon::NoFrames => "An animated cursor needs at least one frame.".to_owned(),
         };
         write!(f, "{}", msg)
     }
@@ -94,3 +113,69 @@ impl Icon {
         }
     }
 }
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+/// A cursor image, or an animation, used with `Window::set_custom_cursor`.
+pub struct CustomCursor {
+    pub(crate) frames: Vec<CursorFrame>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct CursorFrame {
+    pub(crate) image: Icon,
+    pub(crate) hotspot_x: u32,
+    pub(crate) hotspot_y: u32,
+    // How long the frame is shown for, which is irrelevant if it's the only one.
+    pub(crate) delay: Duration,
+}
+
+impl CustomCursor {
+    /// Creates a `CustomCursor` from 32bpp RGBA data, with its hotspot at the given pixel.
+    ///
+    /// The data is validated like with `Icon::from_rgba`, and the hotspot must lie within the
+    /// image. Otherwise, this will return a `BadIcon` error.
+    pub fn from_rgba(
+        rgba: Vec<u8>,
+        width: u32,
+        height: u32,
+        hotspot_x: u32,
+        hotspot_y: u32,
+    ) -> Result<Self, BadIcon> {
+        let image = Icon::from_rgba(rgba, width, height)?;
+        if hotspot_x >= width || hotspot_y >= height {
+            return Err(BadIcon::HotspotOutOfBounds {
+                width,
+                height,
+                hotspot_x,
+                hotspot_y,
+            });
+        }
+        Ok(CustomCursor {
+            frames: vec![CursorFrame {
+                image,
+                hotspot_x,
+                hotspot_y,
+                delay: Duration::from_secs(0),
+            }],
+        })
+    }
+
+    /// Creates an animated cursor, showing each of the given cursors in turn for the duration it
+    /// comes with, and looping.
+    ///
+    /// Animations can't be nested: only the first frame of animated cursors is used. If `frames`
+    /// is empty, this will return a `BadIcon` error.
+    pub fn from_frames(frames: Vec<(CustomCursor, Duration)>) -> Result<Self, BadIcon> {
+        if frames.is_empty() {
+            return Err(BadIcon::NoFrames);
+        }
+        let frames = frames
+            .into_iter()
+            .map(|(cursor, delay)| {
+                let frame = cursor.frames.into_iter().next().unwrap();
+                CursorFrame { delay, ..frame }
+            })
+            .collect();
+        Ok(CustomCursor { frames })
+    }
+}
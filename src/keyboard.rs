@@ -0,0 +1,436 @@
+//! Types describing the keys of a keyboard, as reported by `WindowEvent::Key`.
+//!
+//! Each key event tells both which key was pressed, with a [`KeyCode`] that doesn't depend on the
+//! keyboard layout, and what it means under the current layout, with a [`Key`].
+//!
+//! [`KeyCode`]: ./enum.KeyCode.html
+//! [`Key`]: ./enum.Key.html
+use std::time::Duration;
+
+use crate::event::ScanCode;
+
+/// Identifies a physical key, regardless of the keyboard layout.
+///
+/// The keys are named after the key found at their location on a US keyboard, following the
+/// [`code` values of the UI Events specification](https://www.w3.org/TR/uievents-code/). For
+/// example, the key to the right of `Tab` is always `KeyQ`, even though it produces an "a" with
+/// an AZERTY layout.
+#[derive(Debug, Hash, Ord, PartialOrd, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum KeyCode {
+    /// <kbd>`</kbd> on a US keyboard.
+    Backquote,
+    /// <kbd>\\</kbd> on a US keyboard.
+    Backslash,
+    /// <kbd>[</kbd> on a US keyboard.
+    BracketLeft,
+    /// <kbd>]</kbd> on a US keyboard.
+    BracketRight,
+    /// <kbd>,</kbd> on a US keyboard.
+    Comma,
+    Digit0,
+    Digit1,
+    Digit2,
+    Digit3,
+    Digit4,
+    Digit5,
+    Digit6,
+    Digit7,
+    Digit8,
+    Digit9,
+    /// <kbd>=</kbd> on a US keyboard.
+    Equal,
+    /// The key between the left shift and <kbd>Z</kbd> on ISO keyboards.
+    IntlBackslash,
+    /// The key left of the right shift on Japanese keyboards.
+    IntlRo,
+    /// The key left of backspace on Japanese keyboards.
+    IntlYen,
+    KeyA,
+    KeyB,
+    KeyC,
+    KeyD,
+    KeyE,
+    KeyF,
+    KeyG,
+    KeyH,
+    KeyI,
+    KeyJ,
+    KeyK,
+    KeyL,
+    KeyM,
+    KeyN,
+    KeyO,
+    KeyP,
+    KeyQ,
+    KeyR,
+    KeyS,
+    KeyT,
+    KeyU,
+    KeyV,
+    KeyW,
+    KeyX,
+    KeyY,
+    KeyZ,
+    /// <kbd>-</kbd> on a US keyboard.
+    Minus,
+    /// <kbd>.</kbd> on a US keyboard.
+    Period,
+    /// <kbd>'</kbd> on a US keyboard.
+    Quote,
+    /// <kbd>;</kbd> on a US keyboard.
+    Semicolon,
+    /// <kbd>/</kbd> on a US keyboard.
+    Slash,
+
+    AltLeft,
+    AltRight,
+    Backspace,
+    CapsLock,
+    ContextMenu,
+    ControlLeft,
+    ControlRight,
+    Enter,
+    /// The left Windows, Command or other OS key.
+    SuperLeft,
+    /// The right Windows, Command or other OS key.
+    SuperRight,
+    ShiftLeft,
+    ShiftRight,
+    Space,
+    Tab,
+
+    /// The henkan key of Japanese keyboards.
+    Convert,
+    /// The katakana/hiragana key of Japanese keyboards.
+    KanaMode,
+    /// The Han/Yeong key of Korean keyboards.
+    Lang1,
+    /// The Hanja key of Korean keyboards.
+    Lang2,
+    /// The katakana key of Japanese keyboards.
+    Lang3,
+    /// The hiragana key of Japanese keyboards.
+    Lang4,
+    /// The zenkaku/hankaku key of Japanese keyboards.
+    Lang5,
+    /// The muhenkan key of Japanese keyboards.
+    NonConvert,
+
+    Delete,
+    End,
+    Help,
+    Home,
+    Insert,
+    PageDown,
+    PageUp,
+
+    ArrowDown,
+    ArrowLeft,
+    ArrowRight,
+    ArrowUp,
+
+    NumLock,
+    Numpad0,
+    Numpad1,
+    Numpad2,
+    Numpad3,
+    Numpad4,
+    Numpad5,
+    Numpad6,
+    Numpad7,
+    Numpad8,
+    Numpad9,
+    NumpadAdd,
+    NumpadComma,
+    NumpadDecimal,
+    NumpadDivide,
+    NumpadEnter,
+    NumpadEqual,
+    NumpadMultiply,
+    NumpadParenLeft,
+    NumpadParenRight,
+    NumpadSubtract,
+
+    Escape,
+    F1,
+    F2,
+    F3,
+    F4,
+    F5,
+    F6,
+    F7,
+    F8,
+    F9,
+    F10,
+    F11,
+    F12,
+    F13,
+    F14,
+    F15,
+    F16,
+    F17,
+    F18,
+    F19,
+    F20,
+    F21,
+    F22,
+    F23,
+    F24,
+    PrintScreen,
+    ScrollLock,
+    Pause,
+
+    BrowserBack,
+    BrowserFavorites,
+    BrowserForward,
+    BrowserHome,
+    BrowserRefresh,
+    BrowserSearch,
+    BrowserStop,
+    Eject,
+    /// Usually the "My Computer" key.
+    LaunchApp1,
+    /// Usually the "Calculator" key.
+    LaunchApp2,
+    LaunchMail,
+    MediaPlayPause,
+    MediaSelect,
+    MediaStop,
+    MediaTrackNext,
+    MediaTrackPrevious,
+    Power,
+    Sleep,
+    AudioVolumeDown,
+    AudioVolumeMute,
+    AudioVolumeUp,
+    WakeUp,
+
+    Again,
+    Copy,
+    Cut,
+    Find,
+    Open,
+    Paste,
+    Props,
+    Select,
+    Undo,
+
+    /// A key that couldn't be identified, along with its platform-specific scan code.
+    Unidentified(ScanCode),
+}
+
+/// The meaning of a key under the current keyboard layout.
+#[derive(Debug, Hash, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum Key {
+    /// A key that doesn't produce a character, such as a modifier or a function key.
+    Named(NamedKey),
+
+    /// A key producing a character, along with the character, as modified by the shift and
+    /// AltGr keys of the layout.
+    Character(String),
+
+    /// A dead key, which combines with the next key pressed, along with the character it adds
+    /// to it if known. For example, a dead `^` followed by `e` produces "ê".
+    Dead(Option<char>),
+
+    /// A key without a known meaning.
+    Unidentified,
+}
+
+/// A key that doesn't produce a character.
+///
+/// The keys are named after the [`key` values of the UI Events
+/// specification](https://www.w3.org/TR/uievents-key/).
+#[derive(Debug, Hash, Ord, PartialOrd, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum NamedKey {
+    Alt,
+    /// The key choosing the third level of the layout, usually the right alt key.
+    AltGraph,
+    CapsLock,
+    Control,
+    Fn,
+    NumLock,
+    ScrollLock,
+    Shift,
+    /// The Windows, Command or other OS key.
+    Super,
+    Hyper,
+    Meta,
+
+    Enter,
+    Tab,
+    Space,
+
+    ArrowDown,
+    ArrowLeft,
+    ArrowRight,
+    ArrowUp,
+    End,
+    Home,
+    PageDown,
+    PageUp,
+
+    Backspace,
+    Clear,
+    Copy,
+    Cut,
+    Delete,
+    Insert,
+    Paste,
+    Redo,
+    Undo,
+
+    Cancel,
+    ContextMenu,
+    Escape,
+    Execute,
+    Find,
+    Help,
+    Pause,
+    Select,
+    PrintScreen,
+
+    /// The key starting compose sequences.
+    Compose,
+    Convert,
+    NonConvert,
+    /// The key switching to the second group of the layout.
+    ModeChange,
+    /// The key cycling through the layouts.
+    GroupNext,
+    GroupPrevious,
+    HangulMode,
+    HanjaMode,
+    Hiragana,
+    HiraganaKatakana,
+    KanaMode,
+    KanjiMode,
+    Katakana,
+    Romaji,
+    Zenkaku,
+    Hankaku,
+    ZenkakuHankaku,
+
+    F1,
+    F2,
+    F3,
+    F4,
+    F5,
+    F6,
+    F7,
+    F8,
+    F9,
+    F10,
+    F11,
+    F12,
+    F13,
+    F14,
+    F15,
+    F16,
+    F17,
+    F18,
+    F19,
+    F20,
+    F21,
+    F22,
+    F23,
+    F24,
+
+    AudioVolumeDown,
+    AudioVolumeMute,
+    AudioVolumeUp,
+    MediaPause,
+    MediaPlay,
+    MediaPlayPause,
+    MediaStop,
+    MediaTrackNext,
+    MediaTrackPrevious,
+    BrowserBack,
+    BrowserFavorites,
+    BrowserForward,
+    BrowserHome,
+    BrowserRefresh,
+    BrowserSearch,
+    BrowserStop,
+    LaunchApplication1,
+    LaunchApplication2,
+    LaunchMail,
+    Eject,
+    Power,
+    Standby,
+    WakeUp,
+}
+
+/// Where a key is on the keyboard, for keys found in several places.
+#[derive(Debug, Hash, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum KeyLocation {
+    /// The only location of the key, or the main one.
+    Standard,
+    /// The left one of keys found on both sides of the keyboard, like shift.
+    Left,
+    /// The right one of keys found on both sides of the keyboard, like shift.
+    Right,
+    /// The numeric keypad.
+    Numpad,
+}
+
+/// A layout of the keymap, which XKB calls a group.
+#[derive(Debug, Hash, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct KeyboardLayout {
+    /// The index of the layout in the keymap.
+    pub index: u32,
+    /// The name of the layout, such as "English (US)". It's empty if the keymap doesn't name it.
+    pub name: String,
+}
+
+/// How a held key is repeated.
+#[derive(Debug, Hash, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct KeyRepeatInfo {
+    /// The number of repeats per second.
+    pub rate: u32,
+    /// The time the key is held for before it starts repeating.
+    pub delay: Duration,
+}
+
+impl KeyCode {
+    /// Returns where the key is on the keyboard.
+    pub fn location(self) -> KeyLocation {
+        match self {
+            KeyCode::AltLeft | KeyCode::ControlLeft | KeyCode::ShiftLeft | KeyCode::SuperLeft => {
+                KeyLocation::Left
+            }
+            KeyCode::AltRight
+            | KeyCode::ControlRight
+            | KeyCode::ShiftRight
+            | KeyCode::SuperRight => KeyLocation::Right,
+            KeyCode::NumLock
+            | KeyCode::Numpad0
+            | KeyCode::Numpad1
+            | KeyCode::Numpad2
+            | KeyCode::Numpad3
+            | KeyCode::Numpad4
+            | KeyCode::Numpad5
+            | KeyCode::Numpad6
+            | KeyCode::Numpad7
+            | KeyCode::Numpad8
+            | KeyCode::Numpad9
+            | KeyCode::NumpadAdd
+            | KeyCode::NumpadComma
+            | KeyCode::NumpadDecimal
+            | KeyCode::NumpadDivide
+            | KeyCode::NumpadEnter
+            | KeyCode::NumpadEqual
+            | KeyCode::NumpadMultiply
+            | KeyCode::NumpadParenLeft
+            | KeyCode::NumpadParenRight
+            | KeyCode::NumpadSubtract => KeyLocation::Numpad,
+            _ => KeyLocation::Standard,
+        }
+    }
+}
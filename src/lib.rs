@@ -125,6 +125,15 @@ extern crate derivative;
 #[macro_use]
 #[cfg(any(target_os = "ios", target_os = "windows"))]
 extern crate bitflags;
+#[cfg(any(
+    target_os = "linux",
+    target_os = "dragonfly",
+    target_os = "freebsd",
+    target_os = "netbsd",
+    target_os = "openbsd"
+))]
+#[macro_use]
+extern crate dlib;
 #[cfg(any(target_os = "macos", target_os = "ios"))]
 #[macro_use]
 extern crate objc;
@@ -135,6 +144,7 @@ pub mod error;
 pub mod event;
 pub mod event_loop;
 mod icon;
+pub mod keyboard;
 pub mod monitor;
 mod platform_impl;
 pub mod window;
@@ -1,12 +1,15 @@
 #![cfg(any(target_os = "linux", target_os = "dragonfly", target_os = "freebsd", target_os = "netbsd", target_os = "openbsd"))]
 
-use std::{os::raw, ptr, sync::Arc};
+use std::{os::raw, ptr, sync::Arc, time::Duration};
 
 use smithay_client_toolkit::window::{ButtonState, Theme};
 
 use crate::{
     dpi::LogicalSize,
+    error::ExternalError,
+    event::{DeviceId, ScanCode, Selection},
     event_loop::{EventLoop, EventLoopWindowTarget},
+    keyboard::{Key, KeyCode, KeyRepeatInfo, KeyboardLayout},
     monitor::MonitorHandle,
     window::{Window, WindowBuilder},
 };
@@ -109,6 +112,98 @@ pub trait EventLoopWindowTargetExtUnix {
     ///
     /// The pointer will become invalid when the winit `EventLoop` is destroyed.
     fn wayland_display(&self) -> Option<*mut raw::c_void>;
+
+    /// Takes ownership of `selection`, offering `data` to the other applications.
+    ///
+    /// `data` lists the same content in one or more formats, each identified by its MIME type
+    /// (for example `text/plain;charset=utf-8` for text). The content stays available until
+    /// another application takes ownership of the selection.
+    ///
+    /// ## Platform-specific
+    ///
+    /// - **Wayland:** Fails if none of the windows has keyboard focus.
+    fn set_selection(
+        &self,
+        selection: Selection,
+        data: Vec<(String, Vec<u8>)>,
+    ) -> Result<(), ExternalError>;
+
+    /// Requests the content of `selection` in the format identified by `mime_type`.
+    ///
+    /// The transfer happens asynchronously, its result is delivered as an
+    /// `Event::SelectionEvent`.
+    fn request_selection(&self, selection: Selection, mime_type: &str);
+
+    /// Chooses the theme cursor icons are taken from, by its name, and their nominal size.
+    ///
+    /// When `None`, the theme and size are taken from the `XCURSOR_THEME` and `XCURSOR_SIZE`
+    /// environment variables, then from the settings of the desktop, with a default size of 24.
+    /// The size is that of cursors at a hidpi factor of 1: cursors are scaled along with the
+    /// hidpi factor of the window they're shown over, and reloaded when it changes.
+    ///
+    /// Applies to the cursors already shown as well.
+    ///
+    /// ## Platform-specific
+    ///
+    /// - **X11:** The settings of the desktop are the `Gtk/CursorThemeName` and
+    ///   `Gtk/CursorThemeSize` XSETTINGS, read once when the `EventLoop` is created, then the
+    ///   `Xcursor.theme` resource.
+    /// - **Wayland:** The desktop has no settings to fall back to.
+    fn set_cursor_theme(&self, name: Option<&str>, size: Option<u32>);
+
+    /// Returns the active keyboard layout, whose changes are reported with
+    /// `WindowEvent::KeyboardLayoutChanged`.
+    ///
+    /// Returns `None` if the keymap isn't known yet, or without libxkbcommon. On X11,
+    /// libxkbcommon-x11 is needed as well. On Wayland, this is the layout of the keyboard of the
+    /// first seat.
+    fn keyboard_layout(&self) -> Option<KeyboardLayout>;
+
+    /// Returns the layouts of the keymap, in the order they're cycled through.
+    ///
+    /// Returns an empty list in the same cases `keyboard_layout` returns `None`.
+    fn keyboard_layouts(&self) -> Vec<KeyboardLayout>;
+
+    /// Returns what the key with the given scan code means under the active keyboard layout and
+    /// the modifiers currently held, as if it was pressed now. This is meant for showing
+    /// keyboard shortcuts, such as the key producing a "z" on an AZERTY layout being the
+    /// `KeyCode::KeyW` key.
+    ///
+    /// Scan codes are the evdev codes of `KeyboardInput::scancode`. Returns `None` in the same
+    /// cases `keyboard_layout` does.
+    fn scancode_to_key(&self, scancode: ScanCode) -> Option<Key>;
+
+    /// Returns what the key at the given location means, like `scancode_to_key`.
+    fn keycode_to_key(&self, keycode: KeyCode) -> Option<Key>;
+
+    /// Returns how held keys are repeated, or `None` if they aren't.
+    ///
+    /// ## Platform-specific
+    ///
+    /// - **X11:** This is the setting of the X server, which does the repeat.
+    /// - **Wayland:** This is the setting the compositor sent for the keyboard of the first seat,
+    ///   which winit follows to repeat the keys itself. Until it's known, keys are repeated 25
+    ///   times per second after 600 milliseconds.
+    fn key_repeat_info(&self) -> Option<KeyRepeatInfo>;
+
+    /// Makes a gamepad rumble for `duration`, with its strong (low frequency) and weak (high
+    /// frequency) motors at magnitudes from 0.0 to 1.0. A rumble replaces the previous one, so a
+    /// rumble with both magnitudes at 0.0 stops it.
+    ///
+    /// `device_id` is the device of the `DeviceEvent::GamepadButton` and `GamepadAxis` events.
+    /// Returns `ExternalError::NotSupported` if the device isn't a gamepad with rumble motors
+    /// whose device file can be written to.
+    ///
+    /// ## Platform-specific
+    ///
+    /// - **DragonFly / FreeBSD / NetBSD / OpenBSD:** Unsupported.
+    fn rumble_gamepad(
+        &self,
+        device_id: DeviceId,
+        strong: f64,
+        weak: f64,
+        duration: Duration,
+    ) -> Result<(), ExternalError>;
 }
 
 impl<T> EventLoopWindowTargetExtUnix for EventLoopWindowTarget<T> {
@@ -140,6 +235,61 @@ impl<T> EventLoopWindowTargetExtUnix for EventLoopWindowTarget<T> {
             _ => None,
         }
     }
+
+    #[inline]
+    fn set_selection(
+        &self,
+        selection: Selection,
+        data: Vec<(String, Vec<u8>)>,
+    ) -> Result<(), ExternalError> {
+        self.p.set_selection(selection, data)
+    }
+
+    #[inline]
+    fn request_selection(&self, selection: Selection, mime_type: &str) {
+        self.p.request_selection(selection, mime_type.to_owned())
+    }
+
+    #[inline]
+    fn set_cursor_theme(&self, name: Option<&str>, size: Option<u32>) {
+        self.p.set_cursor_theme(name, size)
+    }
+
+    #[inline]
+    fn keyboard_layout(&self) -> Option<KeyboardLayout> {
+        self.p.keyboard_layout()
+    }
+
+    #[inline]
+    fn keyboard_layouts(&self) -> Vec<KeyboardLayout> {
+        self.p.keyboard_layouts()
+    }
+
+    #[inline]
+    fn scancode_to_key(&self, scancode: ScanCode) -> Option<Key> {
+        self.p.scancode_to_key(scancode)
+    }
+
+    #[inline]
+    fn keycode_to_key(&self, keycode: KeyCode) -> Option<Key> {
+        self.p.keycode_to_key(keycode)
+    }
+
+    #[inline]
+    fn key_repeat_info(&self) -> Option<KeyRepeatInfo> {
+        self.p.key_repeat_info()
+    }
+
+    #[inline]
+    fn rumble_gamepad(
+        &self,
+        device_id: DeviceId,
+        strong: f64,
+        weak: f64,
+        duration: Duration,
+    ) -> Result<(), ExternalError> {
+        self.p.rumble_gamepad(device_id.0, strong, weak, duration)
+    }
 }
 
 /// Additional methods on `EventLoop` that are specific to Unix.
@@ -198,6 +348,10 @@ pub trait WindowExtUnix {
     /// Set window urgency hint (`XUrgencyHint`). Only relevant on X.
     fn set_urgent(&self, is_urgent: bool);
 
+    /// Sets whether the window receives repeated key presses while a key is held, which it does
+    /// by default. Games usually only care about the key being pressed and released.
+    fn set_key_repeat(&self, enabled: bool);
+
     /// This function returns the underlying `xcb_connection_t` of an xlib `Display`.
     ///
     /// Returns `None` if the window doesn't use xlib (if it uses wayland for example).
@@ -281,6 +435,14 @@ impl WindowExtUnix for Window {
         }
     }
 
+    #[inline]
+    fn set_key_repeat(&self, enabled: bool) {
+        match self.window {
+            LinuxWindow::X(ref w) => w.set_key_repeat(enabled),
+            LinuxWindow::Wayland(ref w) => w.set_key_repeat(enabled),
+        }
+    }
+
     #[inline]
     fn wayland_surface(&self) -> Option<*mut raw::c_void> {
         match self.window {
@@ -75,6 +75,8 @@ impl EventLoop {
                                 android_glue::MotionAction::Cancel => TouchPhase::Cancelled,
                             },
                             location,
+                            force: None,
+                            contact: None,
                             id: motion.pointer_id as u64,
                             device_id: DEVICE_ID,
                         }),
@@ -179,6 +181,28 @@ impl DeviceId {
     }
 }
 
+#[derive(Clone, Debug, PartialEq)]
+pub enum DragOffer {}
+
+impl DragOffer {
+    pub fn mime_types(&self) -> &[String] {
+        match *self {}
+    }
+
+    pub fn request_data(&self, _mime_type: String) {
+        match *self {}
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum DragReply {}
+
+impl DragReply {
+    pub fn reply(&self, _action: Option<crate::event::DragAction>) {
+        match *self {}
+    }
+}
+
 pub struct Window {
     native_window: *const c_void,
 }
@@ -344,7 +368,12 @@ impl Window {
     }
 
     #[inline]
-    pub fn set_cursor_grab(&self, _grab: bool) -> Result<(), ExternalError> {
+    pub fn set_custom_cursor(&self, _: ::CustomCursor) {
+        // N/A
+    }
+
+    #[inline]
+    pub fn set_cursor_grab(&self, _mode: ::CursorGrabMode) -> Result<(), ExternalError> {
         Err(ExternalError::NotSupported(NotSupportedError::new()))
     }
 
@@ -393,10 +422,29 @@ impl Window {
     }
 
     #[inline]
-    pub fn set_ime_position(&self, _spot: LogicalPosition) {
+    pub fn set_ime_cursor_area(&self, _position: LogicalPosition, _size: LogicalSize) {
         // N/A
     }
 
+    #[inline]
+    pub fn set_ime_allowed(&self, _allowed: bool) {
+        // N/A
+    }
+
+    #[inline]
+    pub fn set_ime_purpose(&self, _purpose: ::ImePurpose) {
+        // N/A
+    }
+
+    #[inline]
+    pub fn start_drag(
+        &self,
+        _data: Vec<(String, Vec<u8>)>,
+        _icon: Option<::Icon>,
+    ) -> Result<(), ExternalError> {
+        Err(ExternalError::NotSupported(NotSupportedError::new()))
+    }
+
     #[inline]
     pub fn current_monitor(&self) -> RootMonitorHandle {
         RootMonitorHandle {
@@ -43,6 +43,28 @@ impl DeviceId {
     }
 }
 
+#[derive(Clone, Debug, PartialEq)]
+pub enum DragOffer {}
+
+impl DragOffer {
+    pub fn mime_types(&self) -> &[String] {
+        match *self {}
+    }
+
+    pub fn request_data(&self, _mime_type: String) {
+        match *self {}
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum DragReply {}
+
+impl DragReply {
+    pub fn reply(&self, _action: Option<crate::event::DragAction>) {
+        match *self {}
+    }
+}
+
 #[derive(Clone, Default)]
 pub struct PlatformSpecificHeadlessBuilderAttributes;
 
@@ -228,6 +250,7 @@ extern "C" fn mouse_callback(
             ctrl: (*event).ctrlKey == ffi::EM_TRUE,
             alt: (*event).altKey == ffi::EM_TRUE,
             logo: (*event).metaKey == ffi::EM_TRUE,
+            ..Default::default()
         };
 
         match event_type {
@@ -294,6 +317,7 @@ extern "C" fn keyboard_callback(
             ctrl: (*event).ctrlKey == ffi::EM_TRUE,
             alt: (*event).altKey == ffi::EM_TRUE,
             logo: (*event).metaKey == ffi::EM_TRUE,
+            ..Default::default()
         };
 
         match event_type {
@@ -307,6 +331,7 @@ extern "C" fn keyboard_callback(
                             state: ::ElementState::Pressed,
                             virtual_keycode: key_translate_virt((*event).key, (*event).location),
                             modifiers,
+                            is_repeat: (*event).repeat == ffi::EM_TRUE,
                         },
                     },
                 });
@@ -321,6 +346,7 @@ extern "C" fn keyboard_callback(
                             state: ::ElementState::Released,
                             virtual_keycode: key_translate_virt((*event).key, (*event).location),
                             modifiers,
+                            is_repeat: false,
                         },
                     },
                 });
@@ -362,6 +388,8 @@ extern "C" fn touch_callback(
                         phase,
                         id: touch.identifier as u64,
                         location,
+                        force: None,
+                        contact: None,
                     }),
                 });
             }
@@ -602,7 +630,19 @@ impl Window {
     }
 
     #[inline]
-    pub fn set_cursor_grab(&self, grab: bool) -> Result<(), ExternalError> {
+    pub fn set_custom_cursor(&self, _cursor: ::CustomCursor) {
+        // N/A
+    }
+
+    #[inline]
+    pub fn set_cursor_grab(&self, mode: ::CursorGrabMode) -> Result<(), ExternalError> {
+        let grab = match mode {
+            ::CursorGrabMode::None => false,
+            ::CursorGrabMode::Locked => true,
+            ::CursorGrabMode::Confined => {
+                return Err(ExternalError::NotSupported(NotSupportedError::new()))
+            }
+        };
         let mut grabbed_lock = self.window.cursor_grabbed.lock().unwrap();
         if grab == *grabbed_lock {
             return Ok(());
@@ -688,10 +728,29 @@ impl Window {
     }
 
     #[inline]
-    pub fn set_ime_position(&self, _logical_spot: LogicalPosition) {
+    pub fn set_ime_cursor_area(&self, _position: LogicalPosition, _size: LogicalSize) {
         // N/A
     }
 
+    #[inline]
+    pub fn set_ime_allowed(&self, _allowed: bool) {
+        // N/A
+    }
+
+    #[inline]
+    pub fn set_ime_purpose(&self, _purpose: ::ImePurpose) {
+        // N/A
+    }
+
+    #[inline]
+    pub fn start_drag(
+        &self,
+        _data: Vec<(String, Vec<u8>)>,
+        _icon: Option<::Icon>,
+    ) -> Result<(), ExternalError> {
+        Err(ExternalError::NotSupported(NotSupportedError::new()))
+    }
+
     #[inline]
     pub fn current_monitor(&self) -> RootMonitorHandle {
         RootMonitorHandle {
@@ -723,7 +782,7 @@ impl Drop for Window {
         unsafe {
             // Return back to normal cursor state
             self.hide_cursor(false);
-            self.set_cursor_grab(false);
+            self.set_cursor_grab(::CursorGrabMode::None);
 
             // Exit fullscreen if on
             if self.window.is_fullscreen {
@@ -99,6 +99,28 @@ impl DeviceId {
 unsafe impl Send for DeviceId {}
 unsafe impl Sync for DeviceId {}
 
+#[derive(Clone, Debug, PartialEq)]
+pub enum DragOffer {}
+
+impl DragOffer {
+    pub fn mime_types(&self) -> &[String] {
+        match *self {}
+    }
+
+    pub fn request_data(&self, _mime_type: String) {
+        match *self {}
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum DragReply {}
+
+impl DragReply {
+    pub fn reply(&self, _action: Option<crate::event::DragAction>) {
+        match *self {}
+    }
+}
+
 #[derive(Debug)]
 pub enum OsError {}
 
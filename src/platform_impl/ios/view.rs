@@ -235,6 +235,8 @@ unsafe fn get_window_class() -> &'static Class {
                             device_id: RootDeviceId(DeviceId { uiscreen }),
                             id: touch_id,
                             location: (location.x as f64, location.y as f64).into(),
+                            force: None,
+                            contact: None,
                             phase,
                         }),
                     });
@@ -0,0 +1,123 @@
+use std::{
+    fmt, io,
+    path::{Path, PathBuf},
+    str::Utf8Error,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+};
+
+use percent_encoding::percent_decode;
+
+use super::DragOffer;
+use crate::event::DragAction;
+
+/// The MIME type under which dragged files are offered, on both X11 and Wayland.
+pub const URI_LIST_MIME_TYPE: &str = "text/uri-list";
+
+#[derive(Debug)]
+pub enum DndDataParseError {
+    EmptyData,
+    InvalidUtf8(Utf8Error),
+    HostnameSpecified(String),
+    UnexpectedProtocol(String),
+    UnresolvablePath(io::Error),
+}
+
+impl From<Utf8Error> for DndDataParseError {
+    fn from(e: Utf8Error) -> Self {
+        DndDataParseError::InvalidUtf8(e)
+    }
+}
+
+impl From<io::Error> for DndDataParseError {
+    fn from(e: io::Error) -> Self {
+        DndDataParseError::UnresolvablePath(e)
+    }
+}
+
+/// Parses a `text/uri-list` into the local paths it refers to.
+pub fn parse_uri_list(data: &[u8]) -> Result<Vec<PathBuf>, DndDataParseError> {
+    if !data.is_empty() {
+        let mut path_list = Vec::new();
+        let decoded = percent_decode(data).decode_utf8()?.into_owned();
+        for uri in decoded.split("\r\n").filter(|u| !u.is_empty()) {
+            // The format is specified as protocol://host/path
+            // However, it's typically simply protocol:///path
+            let path_str = if uri.starts_with("file://") {
+                let path_str = uri.replace("file://", "");
+                if !path_str.starts_with('/') {
+                    // A hostname is specified
+                    // Supporting this case is beyond the scope of my mental health
+                    return Err(DndDataParseError::HostnameSpecified(path_str));
+                }
+                path_str
+            } else {
+                // Only the file protocol is supported
+                return Err(DndDataParseError::UnexpectedProtocol(uri.to_owned()));
+            };
+
+            let path = Path::new(&path_str).canonicalize()?;
+            path_list.push(path);
+        }
+        Ok(path_list)
+    } else {
+        Err(DndDataParseError::EmptyData)
+    }
+}
+
+/// A reply to a single `DragHovered` event, shared by X11 and Wayland.
+#[derive(Clone)]
+pub struct DragReply {
+    inner: Arc<DragReplyInner>,
+}
+
+struct DragReplyInner {
+    offer: DragOffer,
+    replied: AtomicBool,
+}
+
+impl DragReply {
+    pub fn new(offer: DragOffer) -> DragReply {
+        DragReply {
+            inner: Arc::new(DragReplyInner {
+                offer,
+                replied: AtomicBool::new(false),
+            }),
+        }
+    }
+
+    pub fn reply(&self, action: Option<DragAction>) {
+        self.inner.reply(action);
+    }
+}
+
+impl DragReplyInner {
+    fn reply(&self, action: Option<DragAction>) {
+        if !self.replied.swap(true, Ordering::SeqCst) {
+            self.offer.reply(action);
+        }
+    }
+}
+
+impl fmt::Debug for DragReply {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("DragReply")
+            .field("replied", &self.inner.replied.load(Ordering::SeqCst))
+            .finish()
+    }
+}
+
+impl PartialEq for DragReply {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.inner, &other.inner)
+    }
+}
+
+impl Drop for DragReplyInner {
+    fn drop(&mut self) {
+        // The source waits for a reply before sending further positions, so one is always made.
+        self.reply(Some(DragAction::Copy));
+    }
+}
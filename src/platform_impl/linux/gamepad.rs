@@ -0,0 +1,685 @@
+//! Gamepads and joysticks, read from their evdev devices in `/dev/input` and reported as device
+//! events. Both backends insert them in their calloop event loop.
+
+use std::{
+    cell::RefCell,
+    ffi::{OsStr, OsString},
+    fs::{self, File, OpenOptions},
+    io::{self, Read},
+    mem,
+    os::{
+        raw::c_ulong,
+        unix::{
+            ffi::OsStrExt,
+            fs::OpenOptionsExt,
+            io::{AsRawFd, FromRawFd, RawFd},
+        },
+    },
+    path::Path,
+    ptr,
+    rc::{Rc, Weak},
+    slice,
+    time::Duration,
+};
+
+use calloop::{
+    generic::{EventedFd, Generic},
+    mio::Ready,
+    LoopHandle, Source,
+};
+
+use crate::{
+    error::{ExternalError, NotSupportedError},
+    event::{DeviceEvent, ElementState, GamepadAxis, GamepadButton},
+    platform_impl::platform::OsError,
+};
+
+const INPUT_DIR: &str = "/dev/input";
+
+// The event types and codes of evdev, which libc doesn't define.
+const EV_SYN: u16 = 0x00;
+const EV_KEY: u16 = 0x01;
+const EV_ABS: u16 = 0x03;
+const EV_FF: u16 = 0x15;
+
+const SYN_REPORT: u16 = 0;
+const SYN_DROPPED: u16 = 3;
+
+const BTN_JOYSTICK: u16 = 0x120;
+const BTN_GAMEPAD: u16 = 0x130;
+const BTN_SOUTH: u16 = 0x130;
+const BTN_EAST: u16 = 0x131;
+const BTN_NORTH: u16 = 0x133;
+const BTN_WEST: u16 = 0x134;
+const BTN_TL: u16 = 0x136;
+const BTN_TR: u16 = 0x137;
+const BTN_TL2: u16 = 0x138;
+const BTN_TR2: u16 = 0x139;
+const BTN_SELECT: u16 = 0x13a;
+const BTN_START: u16 = 0x13b;
+const BTN_MODE: u16 = 0x13c;
+const BTN_THUMBL: u16 = 0x13d;
+const BTN_THUMBR: u16 = 0x13e;
+const BTN_DPAD_UP: u16 = 0x220;
+const BTN_DPAD_DOWN: u16 = 0x221;
+const BTN_DPAD_LEFT: u16 = 0x222;
+const BTN_DPAD_RIGHT: u16 = 0x223;
+
+const ABS_X: u16 = 0x00;
+const ABS_Y: u16 = 0x01;
+const ABS_Z: u16 = 0x02;
+const ABS_RX: u16 = 0x03;
+const ABS_RY: u16 = 0x04;
+const ABS_RZ: u16 = 0x05;
+const ABS_HAT0X: u16 = 0x10;
+const ABS_HAT0Y: u16 = 0x11;
+const ABS_HAT2X: u16 = 0x14;
+const ABS_HAT2Y: u16 = 0x15;
+
+const FF_RUMBLE: u16 = 0x50;
+
+// The ioctls of evdev. Most architectures encode them with a 2-bit direction and a 14-bit size,
+// while a few have a 3-bit direction, a 13-bit size and their own direction values.
+#[cfg(not(any(
+    target_arch = "mips",
+    target_arch = "mips64",
+    target_arch = "powerpc",
+    target_arch = "powerpc64",
+    target_arch = "sparc",
+    target_arch = "sparc64"
+)))]
+mod ioc {
+    use std::os::raw::c_ulong;
+
+    pub const WRITE: c_ulong = 1;
+    pub const READ: c_ulong = 2;
+    pub const SIZE_BITS: c_ulong = 14;
+}
+
+#[cfg(any(
+    target_arch = "mips",
+    target_arch = "mips64",
+    target_arch = "powerpc",
+    target_arch = "powerpc64",
+    target_arch = "sparc",
+    target_arch = "sparc64"
+))]
+mod ioc {
+    use std::os::raw::c_ulong;
+
+    pub const WRITE: c_ulong = 4;
+    pub const READ: c_ulong = 2;
+    pub const SIZE_BITS: c_ulong = 13;
+}
+
+fn ioc(dir: c_ulong, nr: c_ulong, size: usize) -> c_ulong {
+    (dir << (16 + ioc::SIZE_BITS)) | ((size as c_ulong) << 16) | (c_ulong::from(b'E') << 8) | nr
+}
+
+fn eviocgbit(ev: u16, len: usize) -> c_ulong {
+    ioc(ioc::READ, 0x20 + c_ulong::from(ev), len)
+}
+
+fn eviocgabs(abs: u16) -> c_ulong {
+    ioc(
+        ioc::READ,
+        0x40 + c_ulong::from(abs),
+        mem::size_of::<libc::input_absinfo>(),
+    )
+}
+
+fn eviocsff() -> c_ulong {
+    ioc(ioc::WRITE, 0x80, mem::size_of::<libc::ff_effect>())
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct DeviceId(u32);
+
+fn button(code: u16) -> GamepadButton {
+    match code {
+        BTN_SOUTH => GamepadButton::South,
+        BTN_EAST => GamepadButton::East,
+        BTN_NORTH => GamepadButton::North,
+        BTN_WEST => GamepadButton::West,
+        BTN_TL => GamepadButton::LeftBumper,
+        BTN_TR => GamepadButton::RightBumper,
+        BTN_TL2 => GamepadButton::LeftTrigger,
+        BTN_TR2 => GamepadButton::RightTrigger,
+        BTN_SELECT => GamepadButton::Select,
+        BTN_START => GamepadButton::Start,
+        BTN_MODE => GamepadButton::Mode,
+        BTN_THUMBL => GamepadButton::LeftStick,
+        BTN_THUMBR => GamepadButton::RightStick,
+        BTN_DPAD_UP => GamepadButton::DPadUp,
+        BTN_DPAD_DOWN => GamepadButton::DPadDown,
+        BTN_DPAD_LEFT => GamepadButton::DPadLeft,
+        BTN_DPAD_RIGHT => GamepadButton::DPadRight,
+        code => GamepadButton::Other(code),
+    }
+}
+
+// The analog triggers are `ABS_Z` and `ABS_RZ` for most drivers, and `ABS_HAT2X` and `ABS_HAT2Y`
+// for the others.
+fn axis(code: u16) -> GamepadAxis {
+    match code {
+        ABS_X => GamepadAxis::LeftStickX,
+        ABS_Y => GamepadAxis::LeftStickY,
+        ABS_RX => GamepadAxis::RightStickX,
+        ABS_RY => GamepadAxis::RightStickY,
+        ABS_Z | ABS_HAT2Y => GamepadAxis::LeftTrigger,
+        ABS_RZ | ABS_HAT2X => GamepadAxis::RightTrigger,
+        code => GamepadAxis::Other(code),
+    }
+}
+
+fn has_bit(bits: &[u8], bit: u16) -> bool {
+    let bit = usize::from(bit);
+    bits[bit / 8] & (1 << (bit % 8)) != 0
+}
+
+fn get_bits(file: &File, ev: u16, bits: &mut [u8]) -> bool {
+    unsafe {
+        libc::ioctl(
+            file.as_raw_fd(),
+            eviocgbit(ev, bits.len()) as _,
+            bits.as_mut_ptr(),
+        ) >= 0
+    }
+}
+
+struct Axis {
+    code: u16,
+    axis: GamepadAxis,
+    info: libc::input_absinfo,
+}
+
+impl Axis {
+    // Scales `value` from the range of the axis to 0.0 to 1.0 for the triggers and -1.0 to 1.0
+    // for the others, the values within the flat of a centered axis being 0.0.
+    fn normalize(&self, value: i32) -> f64 {
+        let min = f64::from(self.info.minimum);
+        let max = f64::from(self.info.maximum);
+        if max <= min {
+            return 0.0;
+        }
+        let value = f64::from(value);
+        match self.axis {
+            GamepadAxis::LeftTrigger | GamepadAxis::RightTrigger => (value - min) / (max - min),
+            _ => {
+                let center = (min + max) / 2.0;
+                if (value - center).abs() <= f64::from(self.info.flat) {
+                    0.0
+                } else {
+                    (value - center) / (max - center)
+                }
+            }
+        }
+    }
+}
+
+struct Gamepad {
+    id: DeviceId,
+    // The name of the device file, such as `event3`.
+    name: OsString,
+    // The device file, owned by the source.
+    fd: RawFd,
+    source: Source<Generic<EventedFd<File>>>,
+    axes: Vec<Axis>,
+    // The directions of the D-pad, for the gamepads reporting it as the `ABS_HAT0X` and
+    // `ABS_HAT0Y` axes instead of buttons.
+    hat: (i32, i32),
+    // Whether the device file could be opened for writing and the device has rumble motors.
+    rumble: bool,
+    // The rumble effect uploaded to the device, if any.
+    rumble_effect: Option<i16>,
+    // Whether the kernel dropped events, which are then skipped until the next report.
+    dropped: bool,
+}
+
+impl Gamepad {
+    fn process(&mut self, event: &libc::input_event, sink: &mut dyn FnMut(DeviceEvent)) {
+        match event.type_ {
+            EV_SYN if event.code == SYN_REPORT => self.dropped = false,
+            EV_SYN if event.code == SYN_DROPPED => self.dropped = true,
+            _ if self.dropped => (),
+            // The value of the keys is 2 for the repeats.
+            EV_KEY if event.value < 2 => sink(DeviceEvent::GamepadButton {
+                button: button(event.code),
+                state: if event.value == 1 {
+                    ElementState::Pressed
+                } else {
+                    ElementState::Released
+                },
+            }),
+            EV_ABS if event.code == ABS_HAT0X => {
+                let previous = mem::replace(&mut self.hat.0, event.value.signum());
+                hat_changed(
+                    previous,
+                    self.hat.0,
+                    GamepadButton::DPadLeft,
+                    GamepadButton::DPadRight,
+                    sink,
+                );
+            }
+            EV_ABS if event.code == ABS_HAT0Y => {
+                let previous = mem::replace(&mut self.hat.1, event.value.signum());
+                hat_changed(
+                    previous,
+                    self.hat.1,
+                    GamepadButton::DPadUp,
+                    GamepadButton::DPadDown,
+                    sink,
+                );
+            }
+            EV_ABS => {
+                if let Some(axis) = self.axes.iter().find(|axis| axis.code == event.code) {
+                    sink(DeviceEvent::GamepadAxis {
+                        axis: axis.axis,
+                        value: axis.normalize(event.value),
+                    });
+                }
+            }
+            _ => (),
+        }
+    }
+
+    fn rumble(&mut self, strong: f64, weak: f64, duration: Duration) -> Result<(), ExternalError> {
+        if !self.rumble {
+            return Err(ExternalError::NotSupported(NotSupportedError::new()));
+        }
+        let full = f64::from(u16::MAX);
+        let magnitude = |value: f64| (value.max(0.0) * full).min(full) as u16;
+        // The length is in milliseconds, up to about a minute.
+        let length = duration.as_millis().min(u128::from(u16::MAX)) as u16;
+        unsafe {
+            let mut effect: libc::ff_effect = mem::zeroed();
+            effect.type_ = FF_RUMBLE;
+            // The kernel allocates the effect the first time, which is then updated.
+            effect.id = self.rumble_effect.unwrap_or(-1);
+            effect.replay.length = length;
+            ptr::write(
+                effect.u.as_mut_ptr() as *mut libc::ff_rumble_effect,
+                libc::ff_rumble_effect {
+                    strong_magnitude: magnitude(strong),
+                    weak_magnitude: magnitude(weak),
+                },
+            );
+            if libc::ioctl(self.fd, eviocsff() as _, &mut effect) < 0 {
+                return Err(ExternalError::Os(os_error!(OsError::EvdevMisc(
+                    "Failed to upload the rumble effect"
+                ))));
+            }
+            self.rumble_effect = Some(effect.id);
+
+            let mut event: libc::input_event = mem::zeroed();
+            event.type_ = EV_FF;
+            event.code = effect.id as u16;
+            event.value = 1;
+            let len = mem::size_of::<libc::input_event>();
+            if libc::write(self.fd, &event as *const _ as *const _, len) != len as isize {
+                return Err(ExternalError::Os(os_error!(OsError::EvdevMisc(
+                    "Failed to play the rumble effect"
+                ))));
+            }
+        }
+        Ok(())
+    }
+}
+
+// Reports the D-pad buttons of a hat axis going from `previous` to `value`.
+fn hat_changed(
+    previous: i32,
+    value: i32,
+    negative: GamepadButton,
+    positive: GamepadButton,
+    sink: &mut dyn FnMut(DeviceEvent),
+) {
+    let button = |value: i32| if value < 0 { negative } else { positive };
+    if previous == value {
+        return;
+    }
+    if previous != 0 {
+        sink(DeviceEvent::GamepadButton {
+            button: button(previous),
+            state: ElementState::Released,
+        });
+    }
+    if value != 0 {
+        sink(DeviceEvent::GamepadButton {
+            button: button(value),
+            state: ElementState::Pressed,
+        });
+    }
+}
+
+// Opens `path` if it's a gamepad or a joystick, returning its file, axes and whether it can
+// rumble.
+fn open_gamepad(path: &Path) -> Option<(File, Vec<Axis>, bool)> {
+    // Rumble needs to write to the device, which may only be readable.
+    let mut options = OpenOptions::new();
+    options.read(true).custom_flags(libc::O_NONBLOCK);
+    let (file, writable) = match options.clone().write(true).open(path) {
+        Ok(file) => (file, true),
+        Err(_) => (options.open(path).ok()?, false),
+    };
+
+    let mut keys = [0u8; libc::KEY_CNT / 8];
+    if !get_bits(&file, EV_KEY, &mut keys)
+        || !(has_bit(&keys, BTN_GAMEPAD) || has_bit(&keys, BTN_JOYSTICK))
+    {
+        return None;
+    }
+
+    let mut abs = [0u8; libc::ABS_CNT / 8];
+    let mut axes = Vec::new();
+    if get_bits(&file, EV_ABS, &mut abs) {
+        for code in 0..libc::ABS_CNT as u16 {
+            if !has_bit(&abs, code) || code == ABS_HAT0X || code == ABS_HAT0Y {
+                continue;
+            }
+            let mut info: libc::input_absinfo = unsafe { mem::zeroed() };
+            if unsafe { libc::ioctl(file.as_raw_fd(), eviocgabs(code) as _, &mut info) } >= 0 {
+                axes.push(Axis {
+                    code,
+                    axis: axis(code),
+                    info,
+                });
+            }
+        }
+    }
+
+    let mut ff = [0u8; libc::FF_CNT / 8];
+    let rumble = writable && get_bits(&file, EV_FF, &mut ff) && has_bit(&ff, FF_RUMBLE);
+
+    Some((file, axes, rumble))
+}
+
+// Reads the pending events of a device, failing once it's unplugged.
+fn read_events(file: &mut File) -> io::Result<Vec<libc::input_event>> {
+    let mut events = Vec::new();
+    let mut buffer: [libc::input_event; 32] = unsafe { mem::zeroed() };
+    loop {
+        let bytes = unsafe {
+            slice::from_raw_parts_mut(buffer.as_mut_ptr() as *mut u8, mem::size_of_val(&buffer))
+        };
+        match file.read(bytes) {
+            Ok(0) => return Err(io::ErrorKind::UnexpectedEof.into()),
+            Ok(len) => {
+                events.extend_from_slice(&buffer[..len / mem::size_of::<libc::input_event>()])
+            }
+            Err(ref err) if err.kind() == io::ErrorKind::WouldBlock => return Ok(events),
+            Err(err) => return Err(err),
+        }
+    }
+}
+
+fn is_event_device(name: &OsStr) -> bool {
+    name.as_bytes().starts_with(b"event")
+}
+
+struct State {
+    handle: LoopHandle<()>,
+    gamepads: Vec<Gamepad>,
+    next_id: u32,
+    sink: Box<dyn FnMut(DeviceId, DeviceEvent)>,
+}
+
+impl State {
+    fn add_gamepad(&mut self, name: &OsStr, state: &Weak<RefCell<State>>) {
+        if self.gamepads.iter().any(|gamepad| gamepad.name == name) {
+            return;
+        }
+        let (file, axes, rumble) = match open_gamepad(&Path::new(INPUT_DIR).join(name)) {
+            Some(gamepad) => gamepad,
+            None => return,
+        };
+        let id = DeviceId(self.next_id);
+        self.next_id += 1;
+
+        let fd = file.as_raw_fd();
+        let mut source = Generic::from_fd_source(file);
+        source.set_interest(Ready::readable());
+        let state = state.clone();
+        let source = self.handle.insert_source(source, move |event, &mut ()| {
+            let state = match state.upgrade() {
+                Some(state) => state,
+                None => return,
+            };
+            let mut state = state.borrow_mut();
+            let result = read_events(&mut event.source.borrow_mut().0);
+            match result {
+                Ok(events) => state.process(id, &events),
+                Err(_) => state.remove_gamepad(|gamepad| gamepad.id == id),
+            }
+        });
+        let source = match source {
+            Ok(source) => source,
+            Err(_) => return,
+        };
+
+        self.gamepads.push(Gamepad {
+            id,
+            name: name.to_owned(),
+            fd,
+            source,
+            axes,
+            hat: (0, 0),
+            rumble,
+            rumble_effect: None,
+            dropped: false,
+        });
+        (self.sink)(id, DeviceEvent::Added);
+    }
+
+    fn remove_gamepad<F>(&mut self, predicate: F)
+    where
+        F: Fn(&Gamepad) -> bool,
+    {
+        if let Some(idx) = self.gamepads.iter().position(predicate) {
+            let gamepad = self.gamepads.remove(idx);
+            gamepad.source.remove();
+            (self.sink)(gamepad.id, DeviceEvent::Removed);
+        }
+    }
+
+    fn process(&mut self, id: DeviceId, events: &[libc::input_event]) {
+        let sink = &mut self.sink;
+        if let Some(gamepad) = self.gamepads.iter_mut().find(|gamepad| gamepad.id == id) {
+            for event in events {
+                gamepad.process(event, &mut |event| sink(id, event));
+            }
+        }
+    }
+}
+
+// Watches `/dev/input` for the devices being plugged and unplugged. The devices may only become
+// readable once udev changes their permissions, after they're created.
+fn watch_hotplug(
+    handle: &LoopHandle<()>,
+    state: Weak<RefCell<State>>,
+) -> Option<Source<Generic<EventedFd<File>>>> {
+    let inotify = unsafe {
+        let fd = libc::inotify_init1(libc::IN_NONBLOCK | libc::IN_CLOEXEC);
+        if fd < 0 {
+            return None;
+        }
+        let path = b"/dev/input\0";
+        let mask = libc::IN_CREATE | libc::IN_ATTRIB | libc::IN_DELETE;
+        if libc::inotify_add_watch(fd, path.as_ptr() as *const _, mask) < 0 {
+            libc::close(fd);
+            return None;
+        }
+        File::from_raw_fd(fd)
+    };
+
+    let mut source = Generic::from_fd_source(inotify);
+    source.set_interest(Ready::readable());
+    handle
+        .insert_source(source, move |event, &mut ()| {
+            let state = match state.upgrade() {
+                Some(state) => state,
+                None => return,
+            };
+            let mut buffer = [0u8; 4096];
+            let len = match event.source.borrow_mut().0.read(&mut buffer) {
+                Ok(len) => len,
+                Err(_) => return,
+            };
+            let weak = Rc::downgrade(&state);
+            let mut state = state.borrow_mut();
+            let header_len = mem::size_of::<libc::inotify_event>();
+            let mut offset = 0;
+            while offset + header_len <= len {
+                let header: libc::inotify_event =
+                    unsafe { ptr::read_unaligned(buffer[offset..].as_ptr() as *const _) };
+                let name_start = offset + header_len;
+                offset = name_start + header.len as usize;
+                let name = &buffer[name_start..offset.min(len)];
+                // The name is padded with nul bytes.
+                let name = OsStr::from_bytes(name.split(|&b| b == 0).next().unwrap_or(name));
+                if !is_event_device(name) {
+                    continue;
+                }
+                if header.mask & libc::IN_DELETE != 0 {
+                    state.remove_gamepad(|gamepad| gamepad.name == name);
+                } else {
+                    state.add_gamepad(name, &weak);
+                }
+            }
+        })
+        .ok()
+}
+
+/// The gamepads and joysticks, whose events are reported through `sink`.
+pub struct Gamepads {
+    state: Rc<RefCell<State>>,
+    _hotplug_source: Option<Source<Generic<EventedFd<File>>>>,
+}
+
+impl Gamepads {
+    pub fn new<F>(handle: LoopHandle<()>, sink: F) -> Gamepads
+    where
+        F: FnMut(DeviceId, DeviceEvent) + 'static,
+    {
+        let hotplug_handle = handle.clone();
+        let state = Rc::new(RefCell::new(State {
+            handle,
+            gamepads: Vec::new(),
+            next_id: 0,
+            sink: Box::new(sink),
+        }));
+        let hotplug_source = watch_hotplug(&hotplug_handle, Rc::downgrade(&state));
+
+        // Sorted so that the gamepads keep their order across runs.
+        let mut names: Vec<_> = fs::read_dir(INPUT_DIR)
+            .map(|entries| {
+                entries
+                    .filter_map(|entry| entry.ok())
+                    .map(|entry| entry.file_name())
+                    .filter(|name| is_event_device(name))
+                    .collect()
+            })
+            .unwrap_or_default();
+        names.sort_by_key(|name| {
+            name.to_str()
+                .and_then(|name| name["event".len()..].parse::<u32>().ok())
+        });
+        let weak = Rc::downgrade(&state);
+        for name in names {
+            state.borrow_mut().add_gamepad(&name, &weak);
+        }
+
+        Gamepads {
+            state,
+            _hotplug_source: hotplug_source,
+        }
+    }
+
+    pub fn rumble(
+        &self,
+        id: DeviceId,
+        strong: f64,
+        weak: f64,
+        duration: Duration,
+    ) -> Result<(), ExternalError> {
+        let mut state = self.state.borrow_mut();
+        match state.gamepads.iter_mut().find(|gamepad| gamepad.id == id) {
+            Some(gamepad) => gamepad.rumble(strong, weak, duration),
+            None => Err(ExternalError::NotSupported(NotSupportedError::new())),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn axis_with_range(code: u16, minimum: i32, maximum: i32, flat: i32) -> Axis {
+        Axis {
+            code,
+            axis: axis(code),
+            info: libc::input_absinfo {
+                value: 0,
+                minimum,
+                maximum,
+                fuzz: 0,
+                flat,
+                resolution: 0,
+            },
+        }
+    }
+
+    #[test]
+    fn normalize() {
+        let stick = axis_with_range(ABS_X, -32768, 32767, 128);
+        assert_eq!(stick.normalize(-32768), -1.0);
+        assert_eq!(stick.normalize(32767), 1.0);
+        // The values within the flat are the center.
+        assert_eq!(stick.normalize(100), 0.0);
+
+        // The flat only applies to centered axes.
+        let trigger = axis_with_range(ABS_Z, 0, 1023, 64);
+        assert_eq!(trigger.normalize(0), 0.0);
+        assert_eq!(trigger.normalize(32), 32.0 / 1023.0);
+        assert_eq!(trigger.normalize(1023), 1.0);
+
+        assert_eq!(axis_with_range(ABS_X, 0, 0, 0).normalize(10), 0.0);
+    }
+
+    fn hat_events(previous: i32, value: i32) -> Vec<DeviceEvent> {
+        let mut events = Vec::new();
+        hat_changed(
+            previous,
+            value,
+            GamepadButton::DPadLeft,
+            GamepadButton::DPadRight,
+            &mut |event| events.push(event),
+        );
+        events
+    }
+
+    fn hat_button(button: GamepadButton, state: ElementState) -> DeviceEvent {
+        DeviceEvent::GamepadButton { button, state }
+    }
+
+    #[test]
+    fn hat_transitions() {
+        assert_eq!(hat_events(1, 1), vec![]);
+        assert_eq!(
+            hat_events(0, -1),
+            vec![hat_button(GamepadButton::DPadLeft, ElementState::Pressed)]
+        );
+        assert_eq!(
+            hat_events(-1, 0),
+            vec![hat_button(GamepadButton::DPadLeft, ElementState::Released)]
+        );
+        // Going straight from one direction to the other releases the first one.
+        assert_eq!(
+            hat_events(-1, 1),
+            vec![
+                hat_button(GamepadButton::DPadLeft, ElementState::Released),
+                hat_button(GamepadButton::DPadRight, ElementState::Pressed),
+            ]
+        );
+    }
+}
@@ -0,0 +1,403 @@
+//! Translation of evdev scan codes and XKB keysyms, which both X11 and Wayland use, to the keys
+//! of `WindowEvent::Key`.
+use smithay_client_toolkit::keyboard::keysyms;
+
+use super::vkey;
+use crate::{
+    event::{ElementState, KeyEvent, VirtualKeyCode},
+    keyboard::{Key, KeyCode, NamedKey},
+};
+
+/// Maps a key to the `VirtualKeyCode` of `KeyboardInput` events, given its evdev scan code and
+/// the keysym it produces.
+pub fn virtual_keycode(scancode: u32, keysym: u32) -> Option<VirtualKeyCode> {
+    // The digits are found by location, as some layouts need shift to produce them.
+    match scancode {
+        1 => Some(VirtualKeyCode::Escape),
+        2 => Some(VirtualKeyCode::Key1),
+        3 => Some(VirtualKeyCode::Key2),
+        4 => Some(VirtualKeyCode::Key3),
+        5 => Some(VirtualKeyCode::Key4),
+        6 => Some(VirtualKeyCode::Key5),
+        7 => Some(VirtualKeyCode::Key6),
+        8 => Some(VirtualKeyCode::Key7),
+        9 => Some(VirtualKeyCode::Key8),
+        10 => Some(VirtualKeyCode::Key9),
+        11 => Some(VirtualKeyCode::Key0),
+        _ => vkey::keysym_to_element(keysym),
+    }
+}
+
+/// Builds the event of a key, given its evdev scan code, the keysym it produces and the text it
+/// produces.
+pub fn key_event(
+    scancode: u32,
+    keysym: u32,
+    text: Option<String>,
+    state: ElementState,
+    repeat: bool,
+) -> KeyEvent {
+    let physical_key = scancode_to_keycode(scancode);
+    let text = match state {
+        ElementState::Pressed => text.filter(|text| !text.is_empty()),
+        ElementState::Released => None,
+    };
+    KeyEvent {
+        physical_key,
+        logical_key: keysym_to_key(keysym, text.as_deref()),
+        text,
+        location: physical_key.location(),
+        state,
+        repeat,
+    }
+}
+
+/// Maps an evdev scan code, as defined in `linux/input-event-codes.h`, to the key at its location.
+pub fn scancode_to_keycode(scancode: u32) -> KeyCode {
+    match scancode {
+        1 => KeyCode::Escape,
+        2 => KeyCode::Digit1,
+        3 => KeyCode::Digit2,
+        4 => KeyCode::Digit3,
+        5 => KeyCode::Digit4,
+        6 => KeyCode::Digit5,
+        7 => KeyCode::Digit6,
+        8 => KeyCode::Digit7,
+        9 => KeyCode::Digit8,
+        10 => KeyCode::Digit9,
+        11 => KeyCode::Digit0,
+        12 => KeyCode::Minus,
+        13 => KeyCode::Equal,
+        14 => KeyCode::Backspace,
+        15 => KeyCode::Tab,
+        16 => KeyCode::KeyQ,
+        17 => KeyCode::KeyW,
+        18 => KeyCode::KeyE,
+        19 => KeyCode::KeyR,
+        20 => KeyCode::KeyT,
+        21 => KeyCode::KeyY,
+        22 => KeyCode::KeyU,
+        23 => KeyCode::KeyI,
+        24 => KeyCode::KeyO,
+        25 => KeyCode::KeyP,
+        26 => KeyCode::BracketLeft,
+        27 => KeyCode::BracketRight,
+        28 => KeyCode::Enter,
+        29 => KeyCode::ControlLeft,
+        30 => KeyCode::KeyA,
+        31 => KeyCode::KeyS,
+        32 => KeyCode::KeyD,
+        33 => KeyCode::KeyF,
+        34 => KeyCode::KeyG,
+        35 => KeyCode::KeyH,
+        36 => KeyCode::KeyJ,
+        37 => KeyCode::KeyK,
+        38 => KeyCode::KeyL,
+        39 => KeyCode::Semicolon,
+        40 => KeyCode::Quote,
+        41 => KeyCode::Backquote,
+        42 => KeyCode::ShiftLeft,
+        43 => KeyCode::Backslash,
+        44 => KeyCode::KeyZ,
+        45 => KeyCode::KeyX,
+        46 => KeyCode::KeyC,
+        47 => KeyCode::KeyV,
+        48 => KeyCode::KeyB,
+        49 => KeyCode::KeyN,
+        50 => KeyCode::KeyM,
+        51 => KeyCode::Comma,
+        52 => KeyCode::Period,
+        53 => KeyCode::Slash,
+        54 => KeyCode::ShiftRight,
+        55 => KeyCode::NumpadMultiply,
+        56 => KeyCode::AltLeft,
+        57 => KeyCode::Space,
+        58 => KeyCode::CapsLock,
+        59 => KeyCode::F1,
+        60 => KeyCode::F2,
+        61 => KeyCode::F3,
+        62 => KeyCode::F4,
+        63 => KeyCode::F5,
+        64 => KeyCode::F6,
+        65 => KeyCode::F7,
+        66 => KeyCode::F8,
+        67 => KeyCode::F9,
+        68 => KeyCode::F10,
+        69 => KeyCode::NumLock,
+        70 => KeyCode::ScrollLock,
+        71 => KeyCode::Numpad7,
+        72 => KeyCode::Numpad8,
+        73 => KeyCode::Numpad9,
+        74 => KeyCode::NumpadSubtract,
+        75 => KeyCode::Numpad4,
+        76 => KeyCode::Numpad5,
+        77 => KeyCode::Numpad6,
+        78 => KeyCode::NumpadAdd,
+        79 => KeyCode::Numpad1,
+        80 => KeyCode::Numpad2,
+        81 => KeyCode::Numpad3,
+        82 => KeyCode::Numpad0,
+        83 => KeyCode::NumpadDecimal,
+        85 => KeyCode::Lang5,
+        86 => KeyCode::IntlBackslash,
+        87 => KeyCode::F11,
+        88 => KeyCode::F12,
+        89 => KeyCode::IntlRo,
+        90 => KeyCode::Lang3,
+        91 => KeyCode::Lang4,
+        92 => KeyCode::Convert,
+        93 => KeyCode::KanaMode,
+        94 => KeyCode::NonConvert,
+        96 => KeyCode::NumpadEnter,
+        97 => KeyCode::ControlRight,
+        98 => KeyCode::NumpadDivide,
+        99 => KeyCode::PrintScreen,
+        100 => KeyCode::AltRight,
+        102 => KeyCode::Home,
+        103 => KeyCode::ArrowUp,
+        104 => KeyCode::PageUp,
+        105 => KeyCode::ArrowLeft,
+        106 => KeyCode::ArrowRight,
+        107 => KeyCode::End,
+        108 => KeyCode::ArrowDown,
+        109 => KeyCode::PageDown,
+        110 => KeyCode::Insert,
+        111 => KeyCode::Delete,
+        113 => KeyCode::AudioVolumeMute,
+        114 => KeyCode::AudioVolumeDown,
+        115 => KeyCode::AudioVolumeUp,
+        116 => KeyCode::Power,
+        117 => KeyCode::NumpadEqual,
+        119 => KeyCode::Pause,
+        121 => KeyCode::NumpadComma,
+        122 => KeyCode::Lang1,
+        123 => KeyCode::Lang2,
+        124 => KeyCode::IntlYen,
+        125 => KeyCode::SuperLeft,
+        126 => KeyCode::SuperRight,
+        127 => KeyCode::ContextMenu,
+        128 => KeyCode::BrowserStop,
+        129 => KeyCode::Again,
+        130 => KeyCode::Props,
+        131 => KeyCode::Undo,
+        132 => KeyCode::Select,
+        133 => KeyCode::Copy,
+        134 => KeyCode::Open,
+        135 => KeyCode::Paste,
+        136 => KeyCode::Find,
+        137 => KeyCode::Cut,
+        138 => KeyCode::Help,
+        140 => KeyCode::LaunchApp2,
+        142 => KeyCode::Sleep,
+        143 => KeyCode::WakeUp,
+        155 => KeyCode::LaunchMail,
+        156 => KeyCode::BrowserFavorites,
+        157 => KeyCode::LaunchApp1,
+        158 => KeyCode::BrowserBack,
+        159 => KeyCode::BrowserForward,
+        161 => KeyCode::Eject,
+        163 => KeyCode::MediaTrackNext,
+        164 => KeyCode::MediaPlayPause,
+        165 => KeyCode::MediaTrackPrevious,
+        166 => KeyCode::MediaStop,
+        172 => KeyCode::BrowserHome,
+        173 => KeyCode::BrowserRefresh,
+        179 => KeyCode::NumpadParenLeft,
+        180 => KeyCode::NumpadParenRight,
+        183 => KeyCode::F13,
+        184 => KeyCode::F14,
+        185 => KeyCode::F15,
+        186 => KeyCode::F16,
+        187 => KeyCode::F17,
+        188 => KeyCode::F18,
+        189 => KeyCode::F19,
+        190 => KeyCode::F20,
+        191 => KeyCode::F21,
+        192 => KeyCode::F22,
+        193 => KeyCode::F23,
+        194 => KeyCode::F24,
+        217 => KeyCode::BrowserSearch,
+        226 => KeyCode::MediaSelect,
+        _ => KeyCode::Unidentified(scancode),
+    }
+}
+
+/// Maps a key to its evdev scan code, the reverse of `scancode_to_keycode`.
+pub fn keycode_to_scancode(keycode: KeyCode) -> Option<u32> {
+    match keycode {
+        KeyCode::Unidentified(scancode) => Some(scancode),
+        // The scan codes of the keys we know all fit in a byte.
+        keycode => (0..256).find(|&scancode| scancode_to_keycode(scancode) == keycode),
+    }
+}
+
+/// Maps a keysym to the meaning of the key, falling back to the text it produced for keysyms
+/// without a character of their own.
+pub fn keysym_to_key(keysym: u32, text: Option<&str>) -> Key {
+    if let Some(key) = keysym_to_named_key(keysym) {
+        return Key::Named(key);
+    }
+    if let Some(dead) = keysym_to_dead_key(keysym) {
+        return Key::Dead(dead);
+    }
+    if let Some(chr) = keysym_to_char(keysym) {
+        return Key::Character(chr.to_string());
+    }
+    // Control characters, produced with the control key, don't tell what the key is.
+    match text {
+        Some(text) if !text.chars().any(char::is_control) => Key::Character(text.to_owned()),
+        _ => Key::Unidentified,
+    }
+}
+
+fn keysym_to_char(keysym: u32) -> Option<char> {
+    match keysym {
+        // Latin-1 keysyms are their own code point.
+        0x20..=0x7e | 0xa0..=0xff => std::char::from_u32(keysym),
+        // Keysyms for the rest of Unicode are the code point with the 0x01000000 bit set.
+        0x0100_0100..=0x0110_ffff => std::char::from_u32(keysym - 0x0100_0000),
+        _ => None,
+    }
+}
+
+fn keysym_to_dead_key(keysym: u32) -> Option<Option<char>> {
+    if !(0xfe50..=0xfe93).contains(&keysym) {
+        return None;
+    }
+    // The dead keys without a spacing character have none.
+    Some(match keysym {
+        keysyms::XKB_KEY_dead_grave => Some('`'),
+        keysyms::XKB_KEY_dead_acute => Some('´'),
+        keysyms::XKB_KEY_dead_circumflex => Some('^'),
+        keysyms::XKB_KEY_dead_tilde => Some('~'),
+        keysyms::XKB_KEY_dead_macron => Some('¯'),
+        keysyms::XKB_KEY_dead_breve => Some('˘'),
+        keysyms::XKB_KEY_dead_abovedot => Some('˙'),
+        keysyms::XKB_KEY_dead_diaeresis => Some('¨'),
+        keysyms::XKB_KEY_dead_abovering => Some('˚'),
+        keysyms::XKB_KEY_dead_doubleacute => Some('˝'),
+        keysyms::XKB_KEY_dead_caron => Some('ˇ'),
+        keysyms::XKB_KEY_dead_cedilla => Some('¸'),
+        keysyms::XKB_KEY_dead_ogonek => Some('˛'),
+        keysyms::XKB_KEY_dead_iota => Some('ͺ'),
+        _ => None,
+    })
+}
+
+fn keysym_to_named_key(keysym: u32) -> Option<NamedKey> {
+    Some(match keysym {
+        keysyms::XKB_KEY_Alt_L | keysyms::XKB_KEY_Alt_R => NamedKey::Alt,
+        keysyms::XKB_KEY_ISO_Level3_Shift => NamedKey::AltGraph,
+        keysyms::XKB_KEY_Caps_Lock | keysyms::XKB_KEY_Shift_Lock => NamedKey::CapsLock,
+        keysyms::XKB_KEY_Control_L | keysyms::XKB_KEY_Control_R => NamedKey::Control,
+        keysyms::XKB_KEY_Num_Lock => NamedKey::NumLock,
+        keysyms::XKB_KEY_Scroll_Lock => NamedKey::ScrollLock,
+        keysyms::XKB_KEY_Shift_L | keysyms::XKB_KEY_Shift_R => NamedKey::Shift,
+        keysyms::XKB_KEY_Super_L | keysyms::XKB_KEY_Super_R => NamedKey::Super,
+        keysyms::XKB_KEY_Hyper_L | keysyms::XKB_KEY_Hyper_R => NamedKey::Hyper,
+        keysyms::XKB_KEY_Meta_L | keysyms::XKB_KEY_Meta_R => NamedKey::Meta,
+
+        keysyms::XKB_KEY_Return | keysyms::XKB_KEY_KP_Enter => NamedKey::Enter,
+        keysyms::XKB_KEY_Tab | keysyms::XKB_KEY_ISO_Left_Tab | keysyms::XKB_KEY_KP_Tab => {
+            NamedKey::Tab
+        }
+        keysyms::XKB_KEY_space | keysyms::XKB_KEY_KP_Space => NamedKey::Space,
+
+        keysyms::XKB_KEY_Down | keysyms::XKB_KEY_KP_Down => NamedKey::ArrowDown,
+        keysyms::XKB_KEY_Left | keysyms::XKB_KEY_KP_Left => NamedKey::ArrowLeft,
+        keysyms::XKB_KEY_Right | keysyms::XKB_KEY_KP_Right => NamedKey::ArrowRight,
+        keysyms::XKB_KEY_Up | keysyms::XKB_KEY_KP_Up => NamedKey::ArrowUp,
+        keysyms::XKB_KEY_End | keysyms::XKB_KEY_KP_End => NamedKey::End,
+        keysyms::XKB_KEY_Home | keysyms::XKB_KEY_KP_Home => NamedKey::Home,
+        keysyms::XKB_KEY_Page_Down | keysyms::XKB_KEY_KP_Page_Down => NamedKey::PageDown,
+        keysyms::XKB_KEY_Page_Up | keysyms::XKB_KEY_KP_Page_Up => NamedKey::PageUp,
+
+        keysyms::XKB_KEY_BackSpace => NamedKey::Backspace,
+        keysyms::XKB_KEY_Clear | keysyms::XKB_KEY_KP_Begin => NamedKey::Clear,
+        keysyms::XKB_KEY_XF86Copy => NamedKey::Copy,
+        keysyms::XKB_KEY_XF86Cut => NamedKey::Cut,
+        keysyms::XKB_KEY_Delete | keysyms::XKB_KEY_KP_Delete => NamedKey::Delete,
+        keysyms::XKB_KEY_Insert | keysyms::XKB_KEY_KP_Insert => NamedKey::Insert,
+        keysyms::XKB_KEY_XF86Paste => NamedKey::Paste,
+        keysyms::XKB_KEY_Redo => NamedKey::Redo,
+        keysyms::XKB_KEY_Undo => NamedKey::Undo,
+
+        keysyms::XKB_KEY_Cancel => NamedKey::Cancel,
+        keysyms::XKB_KEY_Menu => NamedKey::ContextMenu,
+        keysyms::XKB_KEY_Escape => NamedKey::Escape,
+        keysyms::XKB_KEY_Execute => NamedKey::Execute,
+        keysyms::XKB_KEY_Find => NamedKey::Find,
+        keysyms::XKB_KEY_Help => NamedKey::Help,
+        keysyms::XKB_KEY_Pause | keysyms::XKB_KEY_Break => NamedKey::Pause,
+        keysyms::XKB_KEY_Select => NamedKey::Select,
+        keysyms::XKB_KEY_Print => NamedKey::PrintScreen,
+
+        keysyms::XKB_KEY_Multi_key => NamedKey::Compose,
+        keysyms::XKB_KEY_Henkan => NamedKey::Convert,
+        keysyms::XKB_KEY_Muhenkan => NamedKey::NonConvert,
+        keysyms::XKB_KEY_Mode_switch => NamedKey::ModeChange,
+        keysyms::XKB_KEY_ISO_Next_Group => NamedKey::GroupNext,
+        keysyms::XKB_KEY_ISO_Prev_Group => NamedKey::GroupPrevious,
+        keysyms::XKB_KEY_Hangul => NamedKey::HangulMode,
+        keysyms::XKB_KEY_Hangul_Hanja => NamedKey::HanjaMode,
+        keysyms::XKB_KEY_Hiragana => NamedKey::Hiragana,
+        keysyms::XKB_KEY_Hiragana_Katakana => NamedKey::HiraganaKatakana,
+        keysyms::XKB_KEY_Kana_Lock | keysyms::XKB_KEY_Kana_Shift => NamedKey::KanaMode,
+        keysyms::XKB_KEY_Kanji => NamedKey::KanjiMode,
+        keysyms::XKB_KEY_Katakana => NamedKey::Katakana,
+        keysyms::XKB_KEY_Romaji => NamedKey::Romaji,
+        keysyms::XKB_KEY_Zenkaku => NamedKey::Zenkaku,
+        keysyms::XKB_KEY_Hankaku => NamedKey::Hankaku,
+        keysyms::XKB_KEY_Zenkaku_Hankaku => NamedKey::ZenkakuHankaku,
+
+        keysyms::XKB_KEY_F1 => NamedKey::F1,
+        keysyms::XKB_KEY_F2 => NamedKey::F2,
+        keysyms::XKB_KEY_F3 => NamedKey::F3,
+        keysyms::XKB_KEY_F4 => NamedKey::F4,
+        keysyms::XKB_KEY_F5 => NamedKey::F5,
+        keysyms::XKB_KEY_F6 => NamedKey::F6,
+        keysyms::XKB_KEY_F7 => NamedKey::F7,
+        keysyms::XKB_KEY_F8 => NamedKey::F8,
+        keysyms::XKB_KEY_F9 => NamedKey::F9,
+        keysyms::XKB_KEY_F10 => NamedKey::F10,
+        keysyms::XKB_KEY_F11 => NamedKey::F11,
+        keysyms::XKB_KEY_F12 => NamedKey::F12,
+        keysyms::XKB_KEY_F13 => NamedKey::F13,
+        keysyms::XKB_KEY_F14 => NamedKey::F14,
+        keysyms::XKB_KEY_F15 => NamedKey::F15,
+        keysyms::XKB_KEY_F16 => NamedKey::F16,
+        keysyms::XKB_KEY_F17 => NamedKey::F17,
+        keysyms::XKB_KEY_F18 => NamedKey::F18,
+        keysyms::XKB_KEY_F19 => NamedKey::F19,
+        keysyms::XKB_KEY_F20 => NamedKey::F20,
+        keysyms::XKB_KEY_F21 => NamedKey::F21,
+        keysyms::XKB_KEY_F22 => NamedKey::F22,
+        keysyms::XKB_KEY_F23 => NamedKey::F23,
+        keysyms::XKB_KEY_F24 => NamedKey::F24,
+
+        keysyms::XKB_KEY_XF86AudioLowerVolume => NamedKey::AudioVolumeDown,
+        keysyms::XKB_KEY_XF86AudioMute => NamedKey::AudioVolumeMute,
+        keysyms::XKB_KEY_XF86AudioRaiseVolume => NamedKey::AudioVolumeUp,
+        keysyms::XKB_KEY_XF86AudioPause => NamedKey::MediaPause,
+        // Most keyboards have a single play/pause key, which produces this keysym.
+        keysyms::XKB_KEY_XF86AudioPlay => NamedKey::MediaPlayPause,
+        keysyms::XKB_KEY_XF86AudioStop => NamedKey::MediaStop,
+        keysyms::XKB_KEY_XF86AudioNext => NamedKey::MediaTrackNext,
+        keysyms::XKB_KEY_XF86AudioPrev => NamedKey::MediaTrackPrevious,
+        keysyms::XKB_KEY_XF86Back => NamedKey::BrowserBack,
+        keysyms::XKB_KEY_XF86Favorites => NamedKey::BrowserFavorites,
+        keysyms::XKB_KEY_XF86Forward => NamedKey::BrowserForward,
+        keysyms::XKB_KEY_XF86HomePage => NamedKey::BrowserHome,
+        keysyms::XKB_KEY_XF86Refresh => NamedKey::BrowserRefresh,
+        keysyms::XKB_KEY_XF86Search => NamedKey::BrowserSearch,
+        keysyms::XKB_KEY_XF86Stop => NamedKey::BrowserStop,
+        keysyms::XKB_KEY_XF86MyComputer => NamedKey::LaunchApplication1,
+        keysyms::XKB_KEY_XF86Calculator => NamedKey::LaunchApplication2,
+        keysyms::XKB_KEY_XF86Mail => NamedKey::LaunchMail,
+        keysyms::XKB_KEY_XF86Eject => NamedKey::Eject,
+        keysyms::XKB_KEY_XF86PowerOff => NamedKey::Power,
+        keysyms::XKB_KEY_XF86Standby | keysyms::XKB_KEY_XF86Sleep => NamedKey::Standby,
+        keysyms::XKB_KEY_XF86WakeUp => NamedKey::WakeUp,
+        _ => return None,
+    })
+}
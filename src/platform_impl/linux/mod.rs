@@ -1,25 +1,73 @@
 #![cfg(any(target_os = "linux", target_os = "dragonfly", target_os = "freebsd", target_os = "netbsd", target_os = "openbsd"))]
 
-use std::{collections::VecDeque, env, ffi::CStr, fmt, mem::MaybeUninit, os::raw::*, sync::Arc};
+use std::{
+    collections::VecDeque, env, ffi::CStr, fmt, mem::MaybeUninit, os::raw::*, sync::Arc,
+    time::Duration,
+};
 
 use parking_lot::Mutex;
 use smithay_client_toolkit::reexports::client::ConnectError;
 
-pub use self::x11::XNotSupported;
 use self::x11::{ffi::XVisualInfo, get_xtarget, XConnection, XError};
+use self::xkb::KbdState;
+pub use self::{dnd::DragReply, x11::XNotSupported};
 use crate::{
     dpi::{LogicalPosition, LogicalSize, PhysicalPosition, PhysicalSize},
     error::{ExternalError, NotSupportedError, OsError as RootOsError},
-    event::Event,
+    event::{DragAction, Event, ScanCode, Selection},
     event_loop::{ControlFlow, EventLoopClosed, EventLoopWindowTarget as RootELW},
     icon::Icon,
+    keyboard::{Key, KeyCode, KeyRepeatInfo, KeyboardLayout},
     monitor::{MonitorHandle as RootMonitorHandle, VideoMode as RootVideoMode},
-    window::{CursorIcon, Fullscreen, WindowAttributes},
+    window::{CursorGrabMode, CursorIcon, CustomCursor, Fullscreen, ImePurpose, WindowAttributes},
 };
 
 mod dlopen;
+mod dnd;
+#[cfg(target_os = "linux")]
+mod gamepad;
+mod keyboard;
+mod vkey;
 pub mod wayland;
 pub mod x11;
+mod xkb;
+
+// Gamepads are only read from evdev on Linux, which the other systems don't all have.
+#[cfg(not(target_os = "linux"))]
+mod gamepad {
+    use std::time::Duration;
+
+    use calloop::LoopHandle;
+
+    use crate::{
+        error::{ExternalError, NotSupportedError},
+        event::DeviceEvent,
+    };
+
+    #[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+    pub struct DeviceId;
+
+    pub struct Gamepads;
+
+    impl Gamepads {
+        pub fn new<F>(_handle: LoopHandle<()>, _sink: F) -> Gamepads
+        where
+            F: FnMut(DeviceId, DeviceEvent) + 'static,
+        {
+            Gamepads
+        }
+
+        pub fn rumble(
+            &self,
+            _id: DeviceId,
+            _strong: f64,
+            _weak: f64,
+            _duration: Duration,
+        ) -> Result<(), ExternalError> {
+            Err(ExternalError::NotSupported(NotSupportedError::new()))
+        }
+    }
+}
 
 /// Environment variable specifying which backend should be used on unix platform.
 ///
@@ -52,6 +100,8 @@ lazy_static! {
 pub enum OsError {
     XError(XError),
     XMisc(&'static str),
+    WaylandMisc(&'static str),
+    EvdevMisc(&'static str),
 }
 
 impl fmt::Display for OsError {
@@ -59,6 +109,8 @@ impl fmt::Display for OsError {
         match self {
             OsError::XError(e) => f.pad(&e.description),
             OsError::XMisc(e) => f.pad(e),
+            OsError::WaylandMisc(e) => f.pad(e),
+            OsError::EvdevMisc(e) => f.pad(e),
         }
     }
 }
@@ -68,6 +120,74 @@ pub enum Window {
     Wayland(wayland::Window),
 }
 
+/// The cursor shown over a window, while it's visible.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Cursor {
+    Icon(CursorIcon),
+    Custom(CustomCursor),
+}
+
+impl Default for Cursor {
+    fn default() -> Self {
+        Cursor::Icon(CursorIcon::default())
+    }
+}
+
+// The nominal size of cursors, if neither the application nor the desktop chose one.
+const DEFAULT_CURSOR_SIZE: u32 = 24;
+
+/// The theme cursor icons are taken from, and their nominal size, which is scaled by the hidpi
+/// factor of the windows.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CursorTheme {
+    pub name: Option<String>,
+    pub size: u32,
+}
+
+impl CursorTheme {
+    /// Uses the theme and size chosen by the application, falling back to the `XCURSOR_THEME`
+    /// and `XCURSOR_SIZE` environment variables, then to those of the desktop.
+    pub fn new(
+        name: Option<&str>,
+        size: Option<u32>,
+        desktop_name: Option<String>,
+        desktop_size: Option<u32>,
+    ) -> Self {
+        let env_name = env::var("XCURSOR_THEME").ok();
+        let env_size = env::var("XCURSOR_SIZE").ok();
+        CursorTheme::from_sources(
+            name,
+            size,
+            env_name.as_deref(),
+            env_size.as_deref(),
+            desktop_name.as_deref(),
+            desktop_size,
+        )
+    }
+
+    // Takes the first theme and size set, from the application, the environment or the desktop.
+    fn from_sources(
+        name: Option<&str>,
+        size: Option<u32>,
+        env_name: Option<&str>,
+        env_size: Option<&str>,
+        desktop_name: Option<&str>,
+        desktop_size: Option<u32>,
+    ) -> Self {
+        let name = name
+            .or(env_name)
+            .or(desktop_name)
+            .filter(|name| !name.is_empty())
+            .map(str::to_owned);
+        let size = size
+            .or_else(|| env_size?.parse().ok())
+            .or(desktop_size)
+            .filter(|&size| size > 0)
+            .unwrap_or(DEFAULT_CURSOR_SIZE);
+        CursorTheme { name, size }
+    }
+}
+
 #[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub enum WindowId {
     X(x11::WindowId),
@@ -84,6 +204,7 @@ impl WindowId {
 pub enum DeviceId {
     X(x11::DeviceId),
     Wayland(wayland::DeviceId),
+    Gamepad(gamepad::DeviceId),
 }
 
 impl DeviceId {
@@ -92,6 +213,35 @@ impl DeviceId {
     }
 }
 
+#[derive(Debug, Clone, PartialEq)]
+pub enum DragOffer {
+    X(x11::DragOffer),
+    Wayland(wayland::DragOffer),
+}
+
+impl DragOffer {
+    pub fn mime_types(&self) -> &[String] {
+        match self {
+            DragOffer::X(offer) => offer.mime_types(),
+            DragOffer::Wayland(offer) => offer.mime_types(),
+        }
+    }
+
+    pub fn request_data(&self, mime_type: String) {
+        match self {
+            DragOffer::X(offer) => offer.request_data(mime_type),
+            DragOffer::Wayland(offer) => offer.request_data(mime_type),
+        }
+    }
+
+    pub fn reply(&self, action: Option<DragAction>) {
+        match self {
+            DragOffer::X(offer) => offer.reply(action),
+            DragOffer::Wayland(offer) => offer.reply(action),
+        }
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
 pub enum MonitorHandle {
     X(x11::MonitorHandle),
@@ -310,10 +460,18 @@ impl Window {
     }
 
     #[inline]
-    pub fn set_cursor_grab(&self, grab: bool) -> Result<(), ExternalError> {
+    pub fn set_custom_cursor(&self, cursor: CustomCursor) {
         match self {
-            &Window::X(ref window) => window.set_cursor_grab(grab),
-            &Window::Wayland(ref window) => window.set_cursor_grab(grab),
+            &Window::X(ref w) => w.set_custom_cursor(cursor),
+            &Window::Wayland(ref w) => w.set_custom_cursor(cursor),
+        }
+    }
+
+    #[inline]
+    pub fn set_cursor_grab(&self, mode: CursorGrabMode) -> Result<(), ExternalError> {
+        match self {
+            &Window::X(ref window) => window.set_cursor_grab(mode),
+            &Window::Wayland(ref window) => window.set_cursor_grab(mode),
         }
     }
 
@@ -390,10 +548,38 @@ impl Window {
     }
 
     #[inline]
-    pub fn set_ime_position(&self, position: LogicalPosition) {
+    pub fn set_ime_cursor_area(&self, position: LogicalPosition, size: LogicalSize) {
         match self {
-            &Window::X(ref w) => w.set_ime_position(position),
-            &Window::Wayland(_) => (),
+            &Window::X(ref w) => w.set_ime_cursor_area(position, size),
+            &Window::Wayland(ref w) => w.set_ime_cursor_area(position, size),
+        }
+    }
+
+    #[inline]
+    pub fn set_ime_allowed(&self, allowed: bool) {
+        match self {
+            &Window::X(ref w) => w.set_ime_allowed(allowed),
+            &Window::Wayland(ref w) => w.set_ime_allowed(allowed),
+        }
+    }
+
+    #[inline]
+    pub fn set_ime_purpose(&self, purpose: ImePurpose) {
+        match self {
+            &Window::X(ref w) => w.set_ime_purpose(purpose),
+            &Window::Wayland(ref w) => w.set_ime_purpose(purpose),
+        }
+    }
+
+    #[inline]
+    pub fn start_drag(
+        &self,
+        data: Vec<(String, Vec<u8>)>,
+        icon: Option<Icon>,
+    ) -> Result<(), ExternalError> {
+        match self {
+            &Window::X(ref w) => w.start_drag(data, icon),
+            &Window::Wayland(ref w) => w.start_drag(data, icon),
         }
     }
 
@@ -626,6 +812,95 @@ impl<T> EventLoopWindowTarget<T> {
             EventLoopWindowTarget::X(_) => false,
         }
     }
+
+    #[inline]
+    pub fn set_selection(
+        &self,
+        selection: Selection,
+        data: Vec<(String, Vec<u8>)>,
+    ) -> Result<(), ExternalError> {
+        match *self {
+            EventLoopWindowTarget::Wayland(ref evlp) => evlp.set_selection(selection, data),
+            EventLoopWindowTarget::X(ref evlp) => evlp.set_selection(selection, data),
+        }
+    }
+
+    #[inline]
+    pub fn request_selection(&self, selection: Selection, mime_type: String) {
+        match *self {
+            EventLoopWindowTarget::Wayland(ref evlp) => {
+                evlp.request_selection(selection, mime_type)
+            }
+            EventLoopWindowTarget::X(ref evlp) => evlp.request_selection(selection, mime_type),
+        }
+    }
+
+    #[inline]
+    pub fn set_cursor_theme(&self, name: Option<&str>, size: Option<u32>) {
+        match *self {
+            EventLoopWindowTarget::Wayland(ref evlp) => evlp.set_cursor_theme(name, size),
+            EventLoopWindowTarget::X(ref evlp) => evlp.set_cursor_theme(name, size),
+        }
+    }
+
+    #[inline]
+    pub fn keyboard_layout(&self) -> Option<KeyboardLayout> {
+        self.with_kbd_state(KbdState::layout).unwrap_or(None)
+    }
+
+    #[inline]
+    pub fn keyboard_layouts(&self) -> Vec<KeyboardLayout> {
+        self.with_kbd_state(KbdState::layouts).unwrap_or_default()
+    }
+
+    #[inline]
+    pub fn scancode_to_key(&self, scancode: ScanCode) -> Option<Key> {
+        // The keycodes of xkbcommon are the evdev scan codes plus 8.
+        let keycode = scancode.checked_add(8)?;
+        self.with_kbd_state(|kbd_state| kbd_state.key(keycode))
+    }
+
+    #[inline]
+    pub fn keycode_to_key(&self, keycode: KeyCode) -> Option<Key> {
+        self.scancode_to_key(keyboard::keycode_to_scancode(keycode)?)
+    }
+
+    #[inline]
+    pub fn key_repeat_info(&self) -> Option<KeyRepeatInfo> {
+        match *self {
+            EventLoopWindowTarget::Wayland(ref evlp) => evlp.key_repeat_info(),
+            EventLoopWindowTarget::X(ref evlp) => evlp.key_repeat_info(),
+        }
+    }
+
+    #[inline]
+    pub fn rumble_gamepad(
+        &self,
+        device_id: DeviceId,
+        strong: f64,
+        weak: f64,
+        duration: Duration,
+    ) -> Result<(), ExternalError> {
+        let device_id = match device_id {
+            DeviceId::Gamepad(device_id) => device_id,
+            _ => return Err(ExternalError::NotSupported(NotSupportedError::new())),
+        };
+        let gamepads = match *self {
+            EventLoopWindowTarget::Wayland(ref evlp) => &evlp.gamepads,
+            EventLoopWindowTarget::X(ref evlp) => evlp.gamepads(),
+        };
+        gamepads.rumble(device_id, strong, weak, duration)
+    }
+
+    fn with_kbd_state<F, R>(&self, f: F) -> Option<R>
+    where
+        F: FnOnce(&KbdState) -> R,
+    {
+        match *self {
+            EventLoopWindowTarget::Wayland(ref evlp) => evlp.with_kbd_state(f),
+            EventLoopWindowTarget::X(ref evlp) => evlp.with_kbd_state(f),
+        }
+    }
 }
 
 fn sticky_exit_callback<T, F>(
@@ -647,3 +922,58 @@ fn sticky_exit_callback<T, F>(
     // user callback
     callback(evt, target, cf)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cursor_theme_fallbacks() {
+        let theme = |name: Option<&str>, size| CursorTheme {
+            name: name.map(str::to_owned),
+            size,
+        };
+        let from_sources = CursorTheme::from_sources;
+
+        assert_eq!(
+            from_sources(None, None, None, None, None, None),
+            theme(None, DEFAULT_CURSOR_SIZE)
+        );
+        assert_eq!(
+            from_sources(None, None, None, None, Some("Adwaita"), Some(32)),
+            theme(Some("Adwaita"), 32)
+        );
+        assert_eq!(
+            from_sources(
+                None,
+                None,
+                Some("breeze_cursors"),
+                Some("48"),
+                Some("Adwaita"),
+                Some(32)
+            ),
+            theme(Some("breeze_cursors"), 48)
+        );
+        assert_eq!(
+            from_sources(
+                Some("DMZ-White"),
+                Some(64),
+                Some("breeze_cursors"),
+                Some("48"),
+                None,
+                None
+            ),
+            theme(Some("DMZ-White"), 64)
+        );
+
+        // Invalid values fall back to the next source, and empty ones to the default.
+        assert_eq!(
+            from_sources(None, None, None, Some("large"), None, Some(32)),
+            theme(None, 32)
+        );
+        assert_eq!(
+            from_sources(None, None, Some(""), Some("0"), Some("Adwaita"), Some(32)),
+            theme(None, DEFAULT_CURSOR_SIZE)
+        );
+    }
+}
@@ -1,6 +1,5 @@
-use super::ffi;
+use super::x11::ffi;
 use crate::event::VirtualKeyCode;
-use libc;
 
 pub fn keysym_to_element(keysym: libc::c_uint) -> Option<VirtualKeyCode> {
     Some(match keysym {
@@ -9,8 +8,8 @@ pub fn keysym_to_element(keysym: libc::c_uint) -> Option<VirtualKeyCode> {
         //ffi::XK_Linefeed => VirtualKeyCode::Linefeed,
         //ffi::XK_Clear => VirtualKeyCode::Clear,
         ffi::XK_Return => VirtualKeyCode::Return,
-        //ffi::XK_Pause => VirtualKeyCode::Pause,
-        //ffi::XK_Scroll_Lock => VirtualKeyCode::Scroll_lock,
+        ffi::XK_Pause => VirtualKeyCode::Pause,
+        ffi::XK_Scroll_Lock => VirtualKeyCode::Scroll,
         //ffi::XK_Sys_Req => VirtualKeyCode::Sys_req,
         ffi::XK_Escape => VirtualKeyCode::Escape,
         ffi::XK_Delete => VirtualKeyCode::Delete,
@@ -47,7 +46,7 @@ pub fn keysym_to_element(keysym: libc::c_uint) -> Option<VirtualKeyCode> {
         //ffi::XK_Win_R => VirtualKeyCode::Win_r,
         //ffi::XK_App => VirtualKeyCode::App,
         //ffi::XK_Select => VirtualKeyCode::Select,
-        //ffi::XK_Print => VirtualKeyCode::Print,
+        ffi::XK_Print => VirtualKeyCode::Snapshot,
         //ffi::XK_Execute => VirtualKeyCode::Execute,
         ffi::XK_Insert => VirtualKeyCode::Insert,
         //ffi::XK_Undo => VirtualKeyCode::Undo,
@@ -59,10 +58,10 @@ pub fn keysym_to_element(keysym: libc::c_uint) -> Option<VirtualKeyCode> {
         //ffi::XK_Break => VirtualKeyCode::Break,
         //ffi::XK_Mode_switch => VirtualKeyCode::Mode_switch,
         //ffi::XK_script_switch => VirtualKeyCode::Script_switch,
-        //ffi::XK_Num_Lock => VirtualKeyCode::Num_lock,
+        ffi::XK_Num_Lock => VirtualKeyCode::Numlock,
         //ffi::XK_KP_Space => VirtualKeyCode::Kp_space,
         //ffi::XK_KP_Tab => VirtualKeyCode::Kp_tab,
-        //ffi::XK_KP_Enter => VirtualKeyCode::Kp_enter,
+        ffi::XK_KP_Enter => VirtualKeyCode::NumpadEnter,
         //ffi::XK_KP_F1 => VirtualKeyCode::Kp_f1,
         //ffi::XK_KP_F2 => VirtualKeyCode::Kp_f2,
         //ffi::XK_KP_F3 => VirtualKeyCode::Kp_f3,
@@ -83,7 +82,7 @@ pub fn keysym_to_element(keysym: libc::c_uint) -> Option<VirtualKeyCode> {
         ffi::XK_KP_Equal => VirtualKeyCode::NumpadEquals,
         //ffi::XK_KP_Multiply => VirtualKeyCode::NumpadMultiply,
         ffi::XK_KP_Add => VirtualKeyCode::Add,
-        //ffi::XK_KP_Separator => VirtualKeyCode::Kp_separator,
+        ffi::XK_KP_Separator => VirtualKeyCode::NumpadComma,
         ffi::XK_KP_Subtract => VirtualKeyCode::Subtract,
         //ffi::XK_KP_Decimal => VirtualKeyCode::Kp_decimal,
         ffi::XK_KP_Divide => VirtualKeyCode::Divide,
@@ -183,10 +182,10 @@ pub fn keysym_to_element(keysym: libc::c_uint) -> Option<VirtualKeyCode> {
         //ffi::XK_quoteright => VirtualKeyCode::Quoteright,
         //ffi::XK_parenleft => VirtualKeyCode::Parenleft,
         //ffi::XK_parenright => VirtualKeyCode::Parenright,
-        //ffi::XK_asterisk => VirtualKeyCode::Asterisk,
+        ffi::XK_asterisk => VirtualKeyCode::Multiply,
         ffi::XK_plus => VirtualKeyCode::Add,
         ffi::XK_comma => VirtualKeyCode::Comma,
-        ffi::XK_minus => VirtualKeyCode::Subtract,
+        ffi::XK_minus => VirtualKeyCode::Minus,
         ffi::XK_period => VirtualKeyCode::Period,
         ffi::XK_slash => VirtualKeyCode::Slash,
         ffi::XK_0 => VirtualKeyCode::Key0,
@@ -998,6 +997,8 @@ pub fn keysym_to_element(keysym: libc::c_uint) -> Option<VirtualKeyCode> {
         //ffi::XK_hebrew_taw => VirtualKeyCode::Hebrew_taw,
         //ffi::XK_hebrew_taf => VirtualKeyCode::Hebrew_taf,
         //ffi::XK_Hebrew_switch => VirtualKeyCode::Hebrew_switch,
+        ffi::XF86XK_AudioLowerVolume => VirtualKeyCode::VolumeDown,
+        ffi::XF86XK_AudioRaiseVolume => VirtualKeyCode::VolumeUp,
         ffi::XF86XK_Back => VirtualKeyCode::NavigateBackward,
         ffi::XF86XK_Forward => VirtualKeyCode::NavigateForward,
         ffi::XF86XK_Copy => VirtualKeyCode::Copy,
@@ -0,0 +1,328 @@
+use std::{collections::HashMap, time::Duration};
+
+use calloop::timer::{Timeout, TimerHandle};
+use smithay_client_toolkit::{
+    reexports::client::{
+        cursor::{is_available, load_theme, CursorTheme as WlCursorTheme},
+        protocol::{
+            wl_compositor::WlCompositor, wl_pointer::WlPointer, wl_shm, wl_surface::WlSurface,
+        },
+        NewProxy,
+    },
+    utils::MemPool,
+};
+
+use crate::{
+    platform_impl::platform::{Cursor, CursorTheme},
+    window::{CursorIcon, Icon},
+};
+
+use super::WindowId;
+
+/// The cursors of our windows, shown by the pointers of all seats as they enter them.
+pub struct CursorManager {
+    // Only known once the globals of the environment have been bound, after the seats.
+    globals: Option<(WlCompositor, wl_shm::WlShm)>,
+    theme: CursorTheme,
+    // The theme loaded at each of the sizes its cursors have been shown at, for the hidpi
+    // factors of the windows.
+    loaded_themes: HashMap<u32, WlCursorTheme>,
+    // Windows without an entry show the default cursor.
+    cursors: HashMap<WindowId, LoadedCursor>,
+    // Windows without an entry have a hidpi factor of 1.
+    scales: HashMap<WindowId, i32>,
+    pointers: Vec<FocusedPointer>,
+    // Shows the next frame of animated cursors, on the pointer it comes with.
+    timer: TimerHandle<WlPointer>,
+}
+
+enum LoadedCursor {
+    Icon(CursorIcon),
+    Custom(Vec<LoadedFrame>),
+}
+
+struct LoadedFrame {
+    // The buffers of the pool are destroyed once the compositor releases them, so a new one is
+    // created every time the frame is shown.
+    pool: MemPool,
+    width: i32,
+    height: i32,
+    hotspot: (i32, i32),
+    delay: Duration,
+}
+
+// A pointer over one of our windows.
+struct FocusedPointer {
+    pointer: WlPointer,
+    surface: WlSurface,
+    wid: WindowId,
+    // The serial of the `enter` event, which the cursor can only be changed with.
+    serial: u32,
+    // The frame of the cursor shown, if it's animated, and the timeout showing the next one.
+    frame: usize,
+    timeout: Option<Timeout>,
+}
+
+impl CursorManager {
+    pub fn new(timer: TimerHandle<WlPointer>, theme: CursorTheme) -> CursorManager {
+        CursorManager {
+            globals: None,
+            theme,
+            loaded_themes: HashMap::new(),
+            cursors: HashMap::new(),
+            scales: HashMap::new(),
+            pointers: Vec::new(),
+            timer,
+        }
+    }
+
+    pub fn set_globals(&mut self, compositor: WlCompositor, shm: wl_shm::WlShm) {
+        self.globals = Some((compositor, shm));
+    }
+
+    pub fn set_cursor(&mut self, wid: WindowId, cursor: Cursor) {
+        let cursor = match cursor {
+            Cursor::Icon(icon) => LoadedCursor::Icon(icon),
+            Cursor::Custom(custom) => {
+                let shm = match self.globals {
+                    Some((_, ref shm)) => shm,
+                    None => return,
+                };
+                let frames = custom.frames.iter().map(|frame| {
+                    icon_pool(shm, &frame.image).map(|pool| LoadedFrame {
+                        pool,
+                        width: frame.image.width as i32,
+                        height: frame.image.height as i32,
+                        hotspot: (frame.hotspot_x as i32, frame.hotspot_y as i32),
+                        delay: frame.delay,
+                    })
+                });
+                match frames.collect() {
+                    Some(frames) => LoadedCursor::Custom(frames),
+                    None => return,
+                }
+            }
+        };
+        self.cursors.insert(wid, cursor);
+        self.show_window(Some(wid));
+    }
+
+    pub fn set_theme(&mut self, theme: CursorTheme) {
+        self.theme = theme;
+        self.loaded_themes.clear();
+        self.show_window(None);
+    }
+
+    pub fn set_scale(&mut self, wid: WindowId, scale: i32) {
+        self.scales.insert(wid, scale);
+        self.show_window(Some(wid));
+    }
+
+    pub fn remove_window(&mut self, wid: WindowId) {
+        self.cursors.remove(&wid);
+        self.scales.remove(&wid);
+    }
+
+    // Shows the cursor again from its first frame, over `wid` or all windows.
+    fn show_window(&mut self, wid: Option<WindowId>) {
+        for index in 0..self.pointers.len() {
+            if wid.is_none() || wid == Some(self.pointers[index].wid) {
+                self.pointers[index].frame = 0;
+                self.show(index);
+            }
+        }
+    }
+
+    pub fn pointer_entered(&mut self, pointer: &WlPointer, wid: WindowId, serial: u32) {
+        self.pointer_left(pointer);
+        let surface = match self.globals {
+            Some((ref compositor, _)) => compositor
+                .create_surface(NewProxy::implement_dummy)
+                .unwrap(),
+            None => return,
+        };
+        self.pointers.push(FocusedPointer {
+            pointer: pointer.clone(),
+            surface,
+            wid,
+            serial,
+            frame: 0,
+            timeout: None,
+        });
+        self.show(self.pointers.len() - 1);
+    }
+
+    pub fn pointer_left(&mut self, pointer: &WlPointer) {
+        let timer = &self.timer;
+        self.pointers.retain(|focused| {
+            if focused.pointer.as_ref().equals(pointer.as_ref()) {
+                if let Some(ref timeout) = focused.timeout {
+                    timer.cancel_timeout(timeout);
+                }
+                focused.surface.destroy();
+                false
+            } else {
+                true
+            }
+        });
+    }
+
+    /// Shows the next frame of the animated cursor of `pointer`.
+    pub fn next_frame(&mut self, pointer: &WlPointer) {
+        let index = self
+            .pointers
+            .iter()
+            .position(|focused| focused.pointer.as_ref().equals(pointer.as_ref()));
+        if let Some(index) = index {
+            self.pointers[index].frame += 1;
+            self.show(index);
+        }
+    }
+
+    fn show(&mut self, index: usize) {
+        let focused = &mut self.pointers[index];
+        if let Some(timeout) = focused.timeout.take() {
+            self.timer.cancel_timeout(&timeout);
+        }
+        let surface = &focused.surface;
+        let (hotspot, frame_count, delay) = match self.cursors.get(&focused.wid) {
+            Some(LoadedCursor::Custom(frames)) => {
+                let frame = &frames[focused.frame % frames.len()];
+                let (width, height) = (frame.width, frame.height);
+                let buffer =
+                    frame
+                        .pool
+                        .buffer(0, width, height, 4 * width, wl_shm::Format::Argb8888);
+                surface.set_buffer_scale(1);
+                surface.attach(Some(&buffer), 0, 0);
+                surface.damage(0, 0, width, height);
+                (frame.hotspot, frames.len(), frame.delay)
+            }
+            cursor => {
+                let icon = match cursor {
+                    Some(LoadedCursor::Icon(icon)) => *icon,
+                    _ => CursorIcon::Default,
+                };
+                // Theme cursors require `libwayland-cursor`.
+                let shm = match self.globals {
+                    Some((_, ref shm)) if is_available() => shm,
+                    _ => return,
+                };
+                // The theme is loaded at the size of the cursor in pixels, which is shown at the
+                // scale of the window.
+                let scale = self.scales.get(&focused.wid).cloned().unwrap_or(1);
+                let size = self.theme.size * scale as u32;
+                let name = self.theme.name.as_deref();
+                let theme = self
+                    .loaded_themes
+                    .entry(size)
+                    .or_insert_with(|| load_theme(name, size, shm));
+                let cursor = match cursor_names(icon)
+                    .iter()
+                    .find_map(|name| theme.get_cursor(name))
+                {
+                    Some(cursor) => cursor,
+                    None => return,
+                };
+                let frame_count = cursor.image_count();
+                let frame = focused.frame % frame_count.max(1);
+                let (buffer, (width, height, hotspot_x, hotspot_y, delay)) =
+                    match (cursor.frame_buffer(frame), cursor.frame_info(frame)) {
+                        (Some(buffer), Some(info)) => (buffer, info),
+                        _ => return,
+                    };
+                // The theme may lack the size, while buffers must be a multiple of their scale.
+                let (width, height) = (width as i32, height as i32);
+                let scale = if width % scale == 0 && height % scale == 0 {
+                    scale
+                } else {
+                    1
+                };
+                surface.set_buffer_scale(scale);
+                surface.attach(Some(&buffer), 0, 0);
+                surface.damage(0, 0, width / scale, height / scale);
+                let delay = Duration::from_millis(u64::from(delay));
+                let hotspot = (hotspot_x as i32 / scale, hotspot_y as i32 / scale);
+                (hotspot, frame_count, delay)
+            }
+        };
+        surface.commit();
+        focused
+            .pointer
+            .set_cursor(focused.serial, Some(surface), hotspot.0, hotspot.1);
+        if frame_count > 1 && delay > Duration::from_secs(0) {
+            let timeout = self.timer.add_timeout(delay, focused.pointer.clone());
+            focused.timeout = Some(timeout);
+        }
+    }
+}
+
+/// Creates a memory pool holding the pixels of `icon`, to be used in `Argb8888` buffers.
+pub fn icon_pool(shm: &wl_shm::WlShm, icon: &Icon) -> Option<MemPool> {
+    if icon.rgba.is_empty() {
+        return None;
+    }
+    let mut pool = MemPool::new(shm, || {}).ok()?;
+    pool.resize(icon.rgba.len()).ok()?;
+    // The buffer holds premultiplied ARGB pixels, in little endian.
+    for (dst, src) in pool
+        .mmap()
+        .chunks_exact_mut(4)
+        .zip(icon.rgba.chunks_exact(4))
+    {
+        let alpha = u32::from(src[3]);
+        let premultiply = |c: u8| (u32::from(c) * alpha / 255) as u8;
+        dst[0] = premultiply(src[2]);
+        dst[1] = premultiply(src[1]);
+        dst[2] = premultiply(src[0]);
+        dst[3] = src[3];
+    }
+    Some(pool)
+}
+
+// The names of the theme cursors matching `icon`, the better looking (or more suiting) ones
+// first, as they differ between themes.
+fn cursor_names(icon: CursorIcon) -> &'static [&'static str] {
+    match icon {
+        CursorIcon::Alias => &["link"],
+        CursorIcon::Arrow => &["arrow"],
+        CursorIcon::Cell => &["plus"],
+        CursorIcon::Copy => &["copy"],
+        CursorIcon::Crosshair => &["crosshair"],
+        CursorIcon::Default => &["left_ptr"],
+        CursorIcon::Hand => &["hand2", "hand1"],
+        CursorIcon::Help => &["question_arrow"],
+        CursorIcon::Move => &["move"],
+        CursorIcon::Grab => &["openhand", "grab"],
+        CursorIcon::Grabbing => &["closedhand", "grabbing"],
+        CursorIcon::Progress => &["left_ptr_watch"],
+        CursorIcon::AllScroll => &["all-scroll"],
+        CursorIcon::ContextMenu => &["context-menu"],
+
+        CursorIcon::NoDrop => &["no-drop", "circle"],
+        CursorIcon::NotAllowed => &["crossed_circle"],
+
+        CursorIcon::EResize => &["right_side"],
+        CursorIcon::NResize => &["top_side"],
+        CursorIcon::NeResize => &["top_right_corner"],
+        CursorIcon::NwResize => &["top_left_corner"],
+        CursorIcon::SResize => &["bottom_side"],
+        CursorIcon::SeResize => &["bottom_right_corner"],
+        CursorIcon::SwResize => &["bottom_left_corner"],
+        CursorIcon::WResize => &["left_side"],
+        CursorIcon::EwResize => &["h_double_arrow"],
+        CursorIcon::NsResize => &["v_double_arrow"],
+        CursorIcon::NwseResize => &["bd_double_arrow", "size_bdiag"],
+        CursorIcon::NeswResize => &["fd_double_arrow", "size_fdiag"],
+        CursorIcon::ColResize => &["split_h", "h_double_arrow"],
+        CursorIcon::RowResize => &["split_v", "v_double_arrow"],
+
+        CursorIcon::Text => &["text", "xterm"],
+        CursorIcon::VerticalText => &["vertical-text"],
+
+        CursorIcon::Wait => &["watch"],
+
+        CursorIcon::ZoomIn => &["zoom-in"],
+        CursorIcon::ZoomOut => &["zoom-out"],
+    }
+}
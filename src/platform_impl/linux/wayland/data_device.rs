@@ -0,0 +1,529 @@
+use std::{
+    collections::HashMap,
+    fs::File,
+    io::{Read, Write},
+    os::unix::io::{FromRawFd, RawFd},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+    thread,
+};
+
+use smithay_client_toolkit::reexports::{
+    client::{
+        protocol::{
+            wl_data_device::{self, WlDataDevice},
+            wl_data_device_manager::{DndAction, WlDataDeviceManager},
+            wl_data_offer::{self, WlDataOffer},
+            wl_data_source, wl_seat,
+        },
+        NewProxy,
+    },
+    protocols::unstable::primary_selection::v1::client::{
+        zwp_primary_selection_device_manager_v1::ZwpPrimarySelectionDeviceManagerV1,
+        zwp_primary_selection_device_v1::{self, ZwpPrimarySelectionDeviceV1},
+        zwp_primary_selection_offer_v1::{self, ZwpPrimarySelectionOfferV1},
+        zwp_primary_selection_source_v1,
+    },
+};
+
+use crate::event::{DragAction, Selection, SelectionEvent, WindowEvent};
+
+use super::{
+    dnd::{DragIcon, DragRequest, DragState},
+    make_wid, WindowId,
+};
+
+pub type WindowEventSender = ::calloop::channel::Sender<(WindowEvent, WindowId)>;
+
+// The content of a selection we own, in every MIME type it's offered as.
+type Content = Arc<Vec<(String, Vec<u8>)>>;
+
+// Offers keep the MIME types they're announced with as their user data.
+type MimeTypes = Mutex<Vec<String>>;
+
+struct DataDevice {
+    device: WlDataDevice,
+    selection: Arc<Mutex<Option<WlDataOffer>>>,
+}
+
+impl DataDevice {
+    fn init_for_seat(
+        manager: &WlDataDeviceManager,
+        seat: &wl_seat::WlSeat,
+        window_event_sender: WindowEventSender,
+    ) -> DataDevice {
+        let selection = Arc::new(Mutex::new(None::<WlDataOffer>));
+        let selection_offer = selection.clone();
+        // Drag and drop actions only exist since version 3, and are mandatory from then on.
+        let mut drag = DragState::new(manager.as_ref().version() >= 3, window_event_sender);
+        let device = manager
+            .get_data_device(seat, move |device| {
+                device.implement_closure(
+                    move |event, _| match event {
+                        wl_data_device::Event::DataOffer { id } => implement_data_offer(id),
+                        wl_data_device::Event::Enter {
+                            serial,
+                            surface,
+                            x,
+                            y,
+                            id,
+                        } => drag.enter(serial, &surface, (x, y), id),
+                        wl_data_device::Event::Motion { x, y, .. } => drag.motion((x, y)),
+                        wl_data_device::Event::Drop => drag.drop(),
+                        wl_data_device::Event::Leave => drag.leave(),
+                        wl_data_device::Event::Selection { id } => {
+                            let mut offer = selection_offer.lock().unwrap();
+                            if let Some(previous) = offer.take() {
+                                previous.destroy();
+                            }
+                            *offer = id;
+                        }
+                        _ => (),
+                    },
+                    (),
+                )
+            })
+            .unwrap();
+        DataDevice { device, selection }
+    }
+
+    fn receive(&self, mime_type: &str) -> Option<File> {
+        let offer = self.selection.lock().unwrap();
+        let offer = offer.as_ref()?;
+        if !offered_mime_types(offer).iter().any(|m| m == mime_type) {
+            return None;
+        }
+        receive_pipe(|fd| offer.receive(mime_type.to_owned(), fd))
+    }
+}
+
+impl Drop for DataDevice {
+    fn drop(&mut self) {
+        if let Some(offer) = self.selection.lock().unwrap().take() {
+            offer.destroy();
+        }
+        if self.device.as_ref().version() >= 2 {
+            self.device.release();
+        }
+    }
+}
+
+struct PrimarySelectionDevice {
+    device: ZwpPrimarySelectionDeviceV1,
+    offer: Arc<Mutex<Option<ZwpPrimarySelectionOfferV1>>>,
+}
+
+impl PrimarySelectionDevice {
+    fn init_for_seat(
+        manager: &ZwpPrimarySelectionDeviceManagerV1,
+        seat: &wl_seat::WlSeat,
+    ) -> PrimarySelectionDevice {
+        let offer = Arc::new(Mutex::new(None::<ZwpPrimarySelectionOfferV1>));
+        let selection_offer = offer.clone();
+        let device = manager
+            .get_device(seat, move |device| {
+                device.implement_closure(
+                    move |event, _| match event {
+                        zwp_primary_selection_device_v1::Event::DataOffer { offer } => {
+                            // The offered MIME types are announced right after the offer itself.
+                            offer.implement_closure(
+                                |event, offer: ZwpPrimarySelectionOfferV1| {
+                                    if let zwp_primary_selection_offer_v1::Event::Offer {
+                                        mime_type,
+                                    } = event
+                                    {
+                                        if let Some(mime_types) =
+                                            offer.as_ref().user_data::<MimeTypes>()
+                                        {
+                                            mime_types.lock().unwrap().push(mime_type);
+                                        }
+                                    }
+                                },
+                                MimeTypes::default(),
+                            );
+                        }
+                        zwp_primary_selection_device_v1::Event::Selection { id } => {
+                            let mut offer = selection_offer.lock().unwrap();
+                            if let Some(previous) = offer.take() {
+                                previous.destroy();
+                            }
+                            *offer = id;
+                        }
+                        _ => (),
+                    },
+                    (),
+                )
+            })
+            .unwrap();
+        PrimarySelectionDevice { device, offer }
+    }
+
+    fn receive(&self, mime_type: &str) -> Option<File> {
+        let offer = self.offer.lock().unwrap();
+        let offer = offer.as_ref()?;
+        let offered = offer
+            .as_ref()
+            .user_data::<MimeTypes>()
+            .map(|mime_types| mime_types.lock().unwrap().iter().any(|m| m == mime_type))
+            .unwrap_or(false);
+        if !offered {
+            return None;
+        }
+        receive_pipe(|fd| offer.receive(mime_type.to_owned(), fd))
+    }
+}
+
+impl Drop for PrimarySelectionDevice {
+    fn drop(&mut self) {
+        if let Some(offer) = self.offer.lock().unwrap().take() {
+            offer.destroy();
+        }
+        self.device.destroy();
+    }
+}
+
+struct SeatDataDevice {
+    id: u32,
+    seat: wl_seat::WlSeat,
+    device: Option<DataDevice>,
+    primary_selection_device: Option<PrimarySelectionDevice>,
+    keyboard_focus: Arc<Mutex<Option<(WindowId, u32)>>>,
+    // The window a mouse button is held down over, along with the serial of the press.
+    pressed_button: Arc<Mutex<Option<(WindowId, u32)>>>,
+}
+
+/// The data devices of all seats, used to exchange data with other clients.
+pub struct DataDevices {
+    manager: Option<WlDataDeviceManager>,
+    primary_selection_manager: Option<ZwpPrimarySelectionDeviceManagerV1>,
+    seats: Vec<SeatDataDevice>,
+    // Whether each selection is still owned by the source we last set for it.
+    owners: HashMap<Selection, Arc<AtomicBool>>,
+    selection_sender: ::calloop::channel::Sender<(Selection, SelectionEvent)>,
+    window_event_sender: WindowEventSender,
+}
+
+impl DataDevices {
+    pub fn new(
+        selection_sender: ::calloop::channel::Sender<(Selection, SelectionEvent)>,
+        window_event_sender: WindowEventSender,
+    ) -> DataDevices {
+        DataDevices {
+            manager: None,
+            primary_selection_manager: None,
+            seats: Vec::new(),
+            owners: HashMap::new(),
+            selection_sender,
+            window_event_sender,
+        }
+    }
+
+    // The managers and the seats can be advertised in any order, so devices are created by
+    // whichever of the two comes last.
+    pub fn set_manager(&mut self, manager: WlDataDeviceManager) {
+        for seat in &mut self.seats {
+            seat.device = Some(DataDevice::init_for_seat(
+                &manager,
+                &seat.seat,
+                self.window_event_sender.clone(),
+            ));
+        }
+        self.manager = Some(manager);
+    }
+
+    pub fn set_primary_selection_manager(&mut self, manager: ZwpPrimarySelectionDeviceManagerV1) {
+        for seat in &mut self.seats {
+            seat.primary_selection_device =
+                Some(PrimarySelectionDevice::init_for_seat(&manager, &seat.seat));
+        }
+        self.primary_selection_manager = Some(manager);
+    }
+
+    pub fn add_seat(
+        &mut self,
+        id: u32,
+        seat: &wl_seat::WlSeat,
+        keyboard_focus: Arc<Mutex<Option<(WindowId, u32)>>>,
+        pressed_button: Arc<Mutex<Option<(WindowId, u32)>>>,
+    ) {
+        let device = self.manager.as_ref().map(|manager| {
+            DataDevice::init_for_seat(manager, seat, self.window_event_sender.clone())
+        });
+        let primary_selection_device = self
+            .primary_selection_manager
+            .as_ref()
+            .map(|manager| PrimarySelectionDevice::init_for_seat(manager, seat));
+        self.seats.push(SeatDataDevice {
+            id,
+            seat: seat.clone(),
+            device,
+            primary_selection_device,
+            keyboard_focus,
+            pressed_button,
+        });
+    }
+
+    pub fn remove_seat(&mut self, id: u32) {
+        self.seats.retain(|seat| seat.id != id);
+    }
+
+    // The seat whose keyboard is focused on one of our windows, along with that window and the
+    // serial of its latest keyboard event.
+    fn focused_seat(&self) -> Option<(&SeatDataDevice, WindowId, u32)> {
+        self.seats.iter().find_map(|seat| {
+            let focus = *seat.keyboard_focus.lock().unwrap();
+            focus.map(|(wid, serial)| (seat, wid, serial))
+        })
+    }
+
+    pub fn set_selection(
+        &mut self,
+        selection: Selection,
+        data: Vec<(String, Vec<u8>)>,
+    ) -> Result<(), &'static str> {
+        let (seat, wid, serial) = self
+            .focused_seat()
+            .ok_or("The selection can only be set while a window has keyboard focus")?;
+
+        // Our previous source is cancelled as well when we replace it, which must not be
+        // reported as a loss of the selection.
+        let owned = Arc::new(AtomicBool::new(true));
+        let source_owned = owned.clone();
+        let window_event_sender = self.window_event_sender.clone();
+        let on_cancelled = move || {
+            if source_owned.swap(false, Ordering::SeqCst) {
+                let _ = window_event_sender.send((WindowEvent::SelectionLost(selection), wid));
+            }
+        };
+
+        let data: Content = Arc::new(data);
+        let source_data = data.clone();
+        match selection {
+            Selection::Clipboard => {
+                let (manager, device) = match (self.manager.as_ref(), seat.device.as_ref()) {
+                    (Some(manager), Some(device)) => (manager, device),
+                    _ => return Err("The compositor doesn't support data devices"),
+                };
+                let source = manager
+                    .create_data_source(move |source| {
+                        source.implement_closure(
+                            move |event, source| match event {
+                                wl_data_source::Event::Send { mime_type, fd } => {
+                                    let pipe = unsafe { File::from_raw_fd(fd) };
+                                    send_content(&source_data, mime_type, pipe)
+                                }
+                                wl_data_source::Event::Cancelled => {
+                                    source.destroy();
+                                    on_cancelled();
+                                }
+                                _ => (),
+                            },
+                            (),
+                        )
+                    })
+                    .unwrap();
+                for (mime_type, _) in data.iter() {
+                    source.offer(mime_type.clone());
+                }
+                device.device.set_selection(Some(&source), serial);
+            }
+            Selection::Primary => {
+                let (manager, device) = match (
+                    self.primary_selection_manager.as_ref(),
+                    seat.primary_selection_device.as_ref(),
+                ) {
+                    (Some(manager), Some(device)) => (manager, device),
+                    _ => return Err("The compositor doesn't support the primary selection"),
+                };
+                let source = manager
+                    .create_source(move |source| {
+                        source.implement_closure(
+                            move |event, source| match event {
+                                zwp_primary_selection_source_v1::Event::Send { mime_type, fd } => {
+                                    let pipe = unsafe { File::from_raw_fd(fd) };
+                                    send_content(&source_data, mime_type, pipe)
+                                }
+                                zwp_primary_selection_source_v1::Event::Cancelled => {
+                                    source.destroy();
+                                    on_cancelled();
+                                }
+                                _ => (),
+                            },
+                            (),
+                        )
+                    })
+                    .unwrap();
+                for (mime_type, _) in data.iter() {
+                    source.offer(mime_type.clone());
+                }
+                device.device.set_selection(Some(&source), serial);
+            }
+        }
+
+        if let Some(previous) = self.owners.insert(selection, owned) {
+            previous.store(false, Ordering::SeqCst);
+        }
+        Ok(())
+    }
+
+    pub fn start_drag(
+        &self,
+        request: DragRequest,
+        icon: Option<DragIcon>,
+    ) -> Result<(), &'static str> {
+        let wid = make_wid(&request.surface);
+        let (seat, serial) = self
+            .seats
+            .iter()
+            .find_map(|seat| match *seat.pressed_button.lock().unwrap() {
+                Some((pressed_wid, serial)) if pressed_wid == wid => Some((seat, serial)),
+                _ => None,
+            })
+            .ok_or("A drag can only be started while a mouse button is held down")?;
+        let (manager, device) = match (self.manager.as_ref(), seat.device.as_ref()) {
+            (Some(manager), Some(device)) => (manager, device),
+            _ => return Err("The compositor doesn't support data devices"),
+        };
+        // The outcome of the drop is only reported since version 3.
+        if manager.as_ref().version() < 3 {
+            return Err("The compositor doesn't support drag and drop actions");
+        }
+
+        let data: Content = Arc::new(request.data);
+        let source_data = data.clone();
+        let window_event_sender = self.window_event_sender.clone();
+        let icon_surface = icon.as_ref().map(|icon| icon.surface().clone());
+        let mut icon = icon;
+        let mut action = None;
+        let source = manager
+            .create_data_source(move |source| {
+                source.implement_closure(
+                    move |event, source| {
+                        let outcome = match event {
+                            wl_data_source::Event::Send { mime_type, fd } => {
+                                let pipe = unsafe { File::from_raw_fd(fd) };
+                                send_content(&source_data, mime_type, pipe);
+                                return;
+                            }
+                            wl_data_source::Event::Action { dnd_action } => {
+                                action = match DndAction::from_raw(dnd_action) {
+                                    Some(DndAction::Copy) => Some(DragAction::Copy),
+                                    Some(DndAction::Move) => Some(DragAction::Move),
+                                    _ => None,
+                                };
+                                return;
+                            }
+                            wl_data_source::Event::DndFinished => action,
+                            wl_data_source::Event::Cancelled => None,
+                            _ => return,
+                        };
+                        icon.take();
+                        source.destroy();
+                        let _ = window_event_sender.send((WindowEvent::DragEnded(outcome), wid));
+                    },
+                    (),
+                )
+            })
+            .unwrap();
+        for (mime_type, _) in data.iter() {
+            source.offer(mime_type.clone());
+        }
+        source.set_actions((DndAction::Copy | DndAction::Move).to_raw());
+        device.device.start_drag(
+            Some(&source),
+            &request.surface,
+            icon_surface.as_ref(),
+            serial,
+        );
+        if let Some(surface) = icon_surface {
+            surface.commit();
+        }
+        Ok(())
+    }
+
+    pub fn request_selection(&self, selection: Selection, mime_type: String) {
+        // Prefer the selection of the focused seat, as that's the one the user interacts with.
+        let seats = self
+            .focused_seat()
+            .map(|(seat, _, _)| seat)
+            .into_iter()
+            .chain(self.seats.iter());
+        let pipe = seats
+            .filter_map(|seat| match selection {
+                Selection::Clipboard => seat.device.as_ref()?.receive(&mime_type),
+                Selection::Primary => seat.primary_selection_device.as_ref()?.receive(&mime_type),
+            })
+            .next();
+
+        let sender = self.selection_sender.clone();
+        match pipe {
+            // The data is only sent once our request reaches the compositor, so it has to be
+            // read without blocking the event loop.
+            Some(pipe) => {
+                thread::spawn(move || {
+                    let _ = sender.send((selection, read_data(pipe, mime_type)));
+                });
+            }
+            None => {
+                let _ = sender.send((selection, SelectionEvent::Failed { mime_type }));
+            }
+        }
+    }
+}
+
+fn implement_data_offer(offer: NewProxy<WlDataOffer>) {
+    // The offered MIME types are announced right after the offer itself.
+    offer.implement_closure(
+        |event, offer: WlDataOffer| {
+            if let wl_data_offer::Event::Offer { mime_type } = event {
+                if let Some(mime_types) = offer.as_ref().user_data::<MimeTypes>() {
+                    mime_types.lock().unwrap().push(mime_type);
+                }
+            }
+        },
+        MimeTypes::default(),
+    );
+}
+
+pub fn offered_mime_types(offer: &WlDataOffer) -> Vec<String> {
+    offer
+        .as_ref()
+        .user_data::<MimeTypes>()
+        .map(|mime_types| mime_types.lock().unwrap().clone())
+        .unwrap_or_default()
+}
+
+// Creates a pipe whose write end is handed to `receive`, returning the read end.
+pub fn receive_pipe<F: FnOnce(RawFd)>(receive: F) -> Option<File> {
+    let mut fds = [0; 2];
+    if unsafe { libc::pipe2(fds.as_mut_ptr(), libc::O_CLOEXEC) } != 0 {
+        return None;
+    }
+    // The write end is duplicated when the request is sent, so our copy can be closed right away
+    // for the read to end once the other client is done.
+    receive(fds[1]);
+    unsafe {
+        libc::close(fds[1]);
+        Some(File::from_raw_fd(fds[0]))
+    }
+}
+
+pub fn read_data<R: Read>(mut pipe: R, mime_type: String) -> SelectionEvent {
+    let mut data = Vec::new();
+    match pipe.read_to_end(&mut data) {
+        Ok(_) => SelectionEvent::Received { mime_type, data },
+        Err(_) => SelectionEvent::Failed { mime_type },
+    }
+}
+
+fn send_content<W: Write + Send + 'static>(content: &Content, mime_type: String, mut pipe: W) {
+    let content = content.clone();
+    // Writing may block until the receiving client reads, which may well be us.
+    thread::spawn(move || {
+        if let Some((_, data)) = content.iter().find(|(m, _)| *m == mime_type) {
+            let _ = pipe.write_all(data);
+        }
+    });
+}
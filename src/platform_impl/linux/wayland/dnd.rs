@@ -0,0 +1,361 @@
+use std::{
+    fmt,
+    io::Read,
+    path::PathBuf,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+    thread,
+};
+
+use smithay_client_toolkit::{
+    reexports::client::{
+        protocol::{
+            wl_compositor::WlCompositor, wl_data_device_manager::DndAction,
+            wl_data_offer::WlDataOffer, wl_shm, wl_surface,
+        },
+        NewProxy,
+    },
+    utils::MemPool,
+};
+
+use crate::{
+    dpi::LogicalPosition,
+    event::{
+        DragAction, DragOffer as RootDragOffer, DragReply as RootDragReply, SelectionEvent,
+        WindowEvent,
+    },
+    platform_impl::platform::{
+        dnd::{parse_uri_list, DragReply, URI_LIST_MIME_TYPE},
+        DragOffer as PlatformDragOffer,
+    },
+    window::Icon,
+};
+
+use super::{
+    cursor::icon_pool,
+    data_device::{offered_mime_types, read_data, receive_pipe, WindowEventSender},
+    make_wid, WindowId,
+};
+
+#[derive(Clone)]
+pub struct DragOffer {
+    inner: Arc<DragOfferInner>,
+}
+
+struct DragOfferInner {
+    offer: WlDataOffer,
+    mime_types: Vec<String>,
+    // The serial of the `enter` event, used to accept the offer.
+    serial: u32,
+    wid: WindowId,
+    sender: WindowEventSender,
+    has_actions: bool,
+    // The action last accepted by the application, if any.
+    action: Mutex<Option<DragAction>>,
+    dropped: AtomicBool,
+}
+
+impl DragOffer {
+    pub fn mime_types(&self) -> &[String] {
+        &self.inner.mime_types
+    }
+
+    pub fn request_data(&self, mime_type: String) {
+        let wid = self.inner.wid;
+        let sender = self.inner.sender.clone();
+        match receive_pipe(|fd| self.inner.offer.receive(mime_type.clone(), fd)) {
+            Some(pipe) => {
+                thread::spawn(move || {
+                    let event = read_data(pipe, mime_type);
+                    let _ = sender.send((WindowEvent::DragData(event), wid));
+                });
+            }
+            None => {
+                let event = SelectionEvent::Failed { mime_type };
+                let _ = sender.send((WindowEvent::DragData(event), wid));
+            }
+        }
+    }
+
+    pub fn reply(&self, action: Option<DragAction>) {
+        let inner = &self.inner;
+        // Only the `ask` action can be changed once the data has been dropped.
+        if inner.dropped.load(Ordering::SeqCst) {
+            return;
+        }
+        *inner.action.lock().unwrap() = action;
+        // The accepted MIME type is only used for feedback, so paths are favoured as we read them
+        // anyway.
+        let mime_type = action.and_then(|_| {
+            let has_paths = inner.mime_types.iter().any(|m| m == URI_LIST_MIME_TYPE);
+            if has_paths {
+                Some(URI_LIST_MIME_TYPE.to_owned())
+            } else {
+                inner.mime_types.first().cloned()
+            }
+        });
+        inner.offer.accept(inner.serial, mime_type);
+        if inner.has_actions {
+            let action = match action {
+                Some(DragAction::Copy) | Some(DragAction::Link) => DndAction::Copy,
+                Some(DragAction::Move) => DndAction::Move,
+                None => DndAction::None,
+            };
+            inner.offer.set_actions(action.to_raw(), action.to_raw());
+        }
+    }
+
+    fn set_dropped(&self) {
+        self.inner.dropped.store(true, Ordering::SeqCst);
+    }
+}
+
+impl fmt::Debug for DragOffer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("DragOffer")
+            .field("mime_types", &self.inner.mime_types)
+            .finish()
+    }
+}
+
+impl PartialEq for DragOffer {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.inner, &other.inner)
+    }
+}
+
+impl Drop for DragOfferInner {
+    fn drop(&mut self) {
+        // The source may discard the data once the drop is finished, so it's only told so once
+        // the application can't request it anymore.
+        let accepted = self.action.lock().unwrap().is_some();
+        if self.dropped.load(Ordering::SeqCst) && accepted && self.has_actions {
+            self.offer.finish();
+        }
+        self.offer.destroy();
+    }
+}
+
+// The drag and drop operation currently over one of our windows, as seen by a data device.
+pub struct DragState {
+    // Drop actions only exist since version 3, and the drop is cancelled without them.
+    has_actions: bool,
+    sender: WindowEventSender,
+    current: Option<Drag>,
+}
+
+struct Drag {
+    offer: DragOffer,
+    position: LogicalPosition,
+    files: Option<Arc<Mutex<DraggedFiles>>>,
+}
+
+impl DragState {
+    pub fn new(has_actions: bool, sender: WindowEventSender) -> DragState {
+        DragState {
+            has_actions,
+            sender,
+            current: None,
+        }
+    }
+
+    pub fn enter(
+        &mut self,
+        serial: u32,
+        surface: &wl_surface::WlSurface,
+        (x, y): (f64, f64),
+        offer: Option<WlDataOffer>,
+    ) {
+        self.current = None;
+        let offer = match offer {
+            Some(offer) => offer,
+            None => return,
+        };
+        let wid = make_wid(surface);
+        let mime_types = offered_mime_types(&offer);
+        let has_paths = mime_types.iter().any(|m| m == URI_LIST_MIME_TYPE);
+
+        // The offer is accepted or rejected by the replies to `DragHovered`.
+        let offer = DragOffer {
+            inner: Arc::new(DragOfferInner {
+                offer,
+                mime_types,
+                serial,
+                wid,
+                sender: self.sender.clone(),
+                has_actions: self.has_actions,
+                action: Mutex::new(None),
+                dropped: AtomicBool::new(false),
+            }),
+        };
+
+        // The paths are read right away, so that they can be reported while hovering.
+        let files = if has_paths {
+            let pipe =
+                receive_pipe(|fd| offer.inner.offer.receive(URI_LIST_MIME_TYPE.to_owned(), fd));
+            pipe.map(|pipe| {
+                let files = Arc::new(Mutex::new(DraggedFiles::default()));
+                let thread_files = files.clone();
+                let sender = self.sender.clone();
+                thread::spawn(move || read_paths(pipe, wid, &thread_files, &sender));
+                files
+            })
+        } else {
+            None
+        };
+
+        let drag = Drag {
+            offer,
+            position: LogicalPosition::new(x, y),
+            files,
+        };
+        drag.send_hovered(&self.sender);
+        self.current = Some(drag);
+    }
+
+    pub fn motion(&mut self, (x, y): (f64, f64)) {
+        if let Some(ref mut drag) = self.current {
+            drag.position = LogicalPosition::new(x, y);
+            drag.send_hovered(&self.sender);
+        }
+    }
+
+    pub fn drop(&mut self) {
+        if let Some(drag) = self.current.take() {
+            let wid = drag.offer.inner.wid;
+            if let Some(ref files) = drag.files {
+                files.lock().unwrap().on_drop(wid, &self.sender);
+            }
+            drag.offer.set_dropped();
+            let event = WindowEvent::DragDropped {
+                offer: root_offer(drag.offer),
+                position: drag.position,
+            };
+            let _ = self.sender.send((event, wid));
+        }
+    }
+
+    pub fn leave(&mut self) {
+        if let Some(drag) = self.current.take() {
+            let wid = drag.offer.inner.wid;
+            if let Some(ref files) = drag.files {
+                files.lock().unwrap().on_leave(wid, &self.sender);
+            }
+            let _ = self.sender.send((WindowEvent::DragLeft, wid));
+        }
+    }
+}
+
+impl Drag {
+    fn send_hovered(&self, sender: &WindowEventSender) {
+        let reply = DragReply::new(PlatformDragOffer::Wayland(self.offer.clone()));
+        let event = WindowEvent::DragHovered {
+            offer: root_offer(self.offer.clone()),
+            position: self.position,
+            reply: RootDragReply(reply),
+        };
+        let _ = sender.send((event, self.offer.inner.wid));
+    }
+}
+
+fn root_offer(offer: DragOffer) -> RootDragOffer {
+    RootDragOffer(PlatformDragOffer::Wayland(offer))
+}
+
+// The files of a drag and drop operation.
+#[derive(Default)]
+struct DraggedFiles {
+    // Only known once the data has been read from the other client.
+    paths: Option<Vec<PathBuf>>,
+    dropped: bool,
+    left: bool,
+}
+
+impl DraggedFiles {
+    fn on_paths(&mut self, paths: Vec<PathBuf>, wid: WindowId, sender: &WindowEventSender) {
+        if self.left && !self.dropped {
+            return;
+        }
+        for path in &paths {
+            let _ = sender.send((WindowEvent::HoveredFile(path.clone()), wid));
+        }
+        if self.dropped {
+            for path in &paths {
+                let _ = sender.send((WindowEvent::DroppedFile(path.clone()), wid));
+            }
+        }
+        self.paths = Some(paths);
+    }
+
+    fn on_drop(&mut self, wid: WindowId, sender: &WindowEventSender) {
+        self.dropped = true;
+        if let Some(ref paths) = self.paths {
+            for path in paths {
+                let _ = sender.send((WindowEvent::DroppedFile(path.clone()), wid));
+            }
+        }
+    }
+
+    fn on_leave(&mut self, wid: WindowId, sender: &WindowEventSender) {
+        self.left = true;
+        if !self.dropped && self.paths.is_some() {
+            let _ = sender.send((WindowEvent::HoveredFileCancelled, wid));
+        }
+    }
+}
+
+fn read_paths<R: Read>(
+    mut pipe: R,
+    wid: WindowId,
+    files: &Mutex<DraggedFiles>,
+    sender: &WindowEventSender,
+) {
+    let mut data = Vec::new();
+    if pipe.read_to_end(&mut data).is_ok() {
+        if let Ok(paths) = parse_uri_list(&data) {
+            files.lock().unwrap().on_paths(paths, wid, sender);
+        }
+    }
+}
+
+/// A request to start dragging data out of one of our windows, made through `Window::start_drag`.
+pub struct DragRequest {
+    pub surface: wl_surface::WlSurface,
+    pub data: Vec<(String, Vec<u8>)>,
+    pub icon: Option<Icon>,
+}
+
+// The surface showing the icon of a drag started from one of our windows, which must be kept
+// alive until the drag ends.
+pub struct DragIcon {
+    surface: wl_surface::WlSurface,
+    _pool: MemPool,
+}
+
+impl DragIcon {
+    pub fn new(compositor: &WlCompositor, shm: &wl_shm::WlShm, icon: &Icon) -> Option<DragIcon> {
+        let pool = icon_pool(shm, icon)?;
+        let (width, height) = (icon.width as i32, icon.height as i32);
+        let buffer = pool.buffer(0, width, height, 4 * width, wl_shm::Format::Argb8888);
+        let surface = compositor.create_surface(NewProxy::implement_dummy).ok()?;
+        // The icon is centered on the pointer.
+        surface.attach(Some(&buffer), -width / 2, -height / 2);
+        surface.damage(0, 0, width, height);
+        Some(DragIcon {
+            surface,
+            _pool: pool,
+        })
+    }
+
+    pub fn surface(&self) -> &wl_surface::WlSurface {
+        &self.surface
+    }
+}
+
+impl Drop for DragIcon {
+    fn drop(&mut self) {
+        self.surface.destroy();
+    }
+}
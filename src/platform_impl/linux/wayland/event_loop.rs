@@ -1,12 +1,17 @@
 use std::{
     cell::RefCell,
-    collections::VecDeque,
+    collections::{HashSet, VecDeque},
     fmt,
     rc::Rc,
     sync::{Arc, Mutex},
     time::Instant,
 };
 
+use smithay_client_toolkit::reexports::protocols::unstable::pointer_gestures::v1::client::{
+    zwp_pointer_gesture_pinch_v1::ZwpPointerGesturePinchV1,
+    zwp_pointer_gesture_swipe_v1::ZwpPointerGestureSwipeV1,
+    zwp_pointer_gestures_v1::ZwpPointerGesturesV1,
+};
 use smithay_client_toolkit::reexports::protocols::unstable::relative_pointer::v1::client::{
     zwp_relative_pointer_manager_v1::ZwpRelativePointerManagerV1,
     zwp_relative_pointer_v1::ZwpRelativePointerV1,
@@ -14,16 +19,32 @@ use smithay_client_toolkit::reexports::protocols::unstable::relative_pointer::v1
 
 use crate::{
     dpi::{PhysicalPosition, PhysicalSize},
-    event::ModifiersState,
+    error::ExternalError,
+    event::{ModifiersState, Selection, SelectionEvent, WindowEvent},
     event_loop::{ControlFlow, EventLoopClosed, EventLoopWindowTarget as RootELW},
+    keyboard::KeyRepeatInfo,
     monitor::{MonitorHandle as RootMonitorHandle, VideoMode as RootVideoMode},
     platform_impl::platform::{
-        sticky_exit_callback, MonitorHandle as PlatformMonitorHandle,
+        gamepad::{DeviceId as GamepadId, Gamepads},
+        sticky_exit_callback,
+        xkb::KbdState,
+        Cursor, CursorTheme, MonitorHandle as PlatformMonitorHandle, OsError,
         VideoMode as PlatformVideoMode,
     },
 };
 
-use super::{window::WindowStore, DeviceId, WindowId};
+use super::{
+    cursor::CursorManager,
+    data_device::DataDevices,
+    dnd::{DragIcon, DragRequest},
+    keyboard::{KeyRepeat, KeyRepeater, Keymaps},
+    make_wid,
+    pointer::CursorGrabs,
+    tablet::Tablets,
+    text_input::TextInputs,
+    window::WindowStore,
+    DeviceId, WindowId,
+};
 
 use smithay_client_toolkit::{
     output::OutputMgr,
@@ -59,6 +80,20 @@ impl<T> WindowEventsSink<T> {
         });
     }
 
+    pub fn send_gamepad_event(&mut self, evt: crate::event::DeviceEvent, dev_id: GamepadId) {
+        self.buffer.push_back(crate::event::Event::DeviceEvent {
+            event: evt,
+            device_id: crate::event::DeviceId(crate::platform_impl::DeviceId::Gamepad(dev_id)),
+        });
+    }
+
+    pub fn send_selection_event(&mut self, evt: SelectionEvent, selection: Selection) {
+        self.buffer.push_back(crate::event::Event::SelectionEvent {
+            event: evt,
+            selection,
+        });
+    }
+
     fn empty_with<F>(&mut self, mut callback: F)
     where
         F: FnMut(crate::event::Event<T>),
@@ -84,6 +119,11 @@ pub struct EventLoop<T: 'static> {
     _kbd_source: ::calloop::Source<
         ::calloop::channel::Channel<(crate::event::WindowEvent, super::WindowId)>,
     >,
+    _selection_source: ::calloop::Source<::calloop::channel::Channel<(Selection, SelectionEvent)>>,
+    _drag_request_source: ::calloop::Source<::calloop::channel::Channel<DragRequest>>,
+    _cursor_request_source: ::calloop::Source<::calloop::channel::Channel<(WindowId, Cursor)>>,
+    _cursor_timer_source: ::calloop::Source<::calloop::timer::Timer<wl_pointer::WlPointer>>,
+    _key_repeat_source: ::calloop::Source<::calloop::timer::Timer<KeyRepeat>>,
     window_target: RootELW<T>,
 }
 
@@ -107,6 +147,24 @@ pub struct EventLoopWindowTarget<T> {
     pub display: Arc<Display>,
     // The list of seats
     pub seats: Arc<Mutex<Vec<(u32, wl_seat::WlSeat)>>>,
+    // The data devices of the seats
+    pub data_devices: Arc<Mutex<DataDevices>>,
+    // Sends the drags started with `Window::start_drag` to the event loop
+    pub drag_requests: ::calloop::channel::Sender<DragRequest>,
+    // The cursors of the windows
+    pub cursor_manager: Arc<Mutex<CursorManager>>,
+    // Sends the cursors set on the windows to the event loop
+    pub cursor_requests: ::calloop::channel::Sender<(WindowId, Cursor)>,
+    // The cursor grabs of the windows
+    pub cursor_grabs: Arc<Mutex<CursorGrabs>>,
+    // The text inputs of the seats
+    pub text_inputs: Arc<Mutex<TextInputs>>,
+    // The keyboard states of the seats
+    pub keymaps: Arc<Mutex<Keymaps>>,
+    // The windows with key repeat disabled
+    pub no_repeat_windows: Arc<Mutex<HashSet<WindowId>>>,
+    // The gamepads, read from evdev
+    pub gamepads: Gamepads,
     _marker: ::std::marker::PhantomData<T>,
 }
 
@@ -135,6 +193,14 @@ impl<T: 'static> EventLoop<T> {
 
         let inner_loop = ::calloop::EventLoop::new().unwrap();
 
+        let gamepad_sink = sink.clone();
+        let gamepads = Gamepads::new(inner_loop.handle(), move |device_id, event| {
+            gamepad_sink
+                .lock()
+                .unwrap()
+                .send_gamepad_event(event, device_id)
+        });
+
         let (kbd_sender, kbd_channel) = ::calloop::channel::channel();
         let kbd_sink = sink.clone();
         let kbd_source = inner_loop
@@ -146,11 +212,57 @@ impl<T: 'static> EventLoop<T> {
             })
             .unwrap();
 
+        let (selection_sender, selection_channel) = ::calloop::channel::channel();
+        let selection_sink = sink.clone();
+        let selection_source = inner_loop
+            .handle()
+            .insert_source(selection_channel, move |evt, &mut ()| {
+                if let ::calloop::channel::Event::Msg((selection, evt)) = evt {
+                    selection_sink
+                        .lock()
+                        .unwrap()
+                        .send_selection_event(evt, selection);
+                }
+            })
+            .unwrap();
+        let data_devices = Arc::new(Mutex::new(DataDevices::new(
+            selection_sender,
+            kbd_sender.clone(),
+        )));
+        let text_inputs = Arc::new(Mutex::new(TextInputs::new(kbd_sender.clone())));
+        let tablets = Arc::new(Mutex::new(Tablets::new(kbd_sender.clone())));
+        let keymaps = Arc::new(Mutex::new(Keymaps::new()));
+        let no_repeat_windows = Arc::new(Mutex::new(HashSet::new()));
+        let drag_ended_sender = kbd_sender.clone();
+        // Animated cursors are driven by the timer, which is inserted once the cursor manager can
+        // be locked to advance them.
+        let cursor_timer = ::calloop::timer::Timer::new();
+        let cursor_manager = Arc::new(Mutex::new(CursorManager::new(
+            cursor_timer.handle(),
+            CursorTheme::new(None, None, None, None),
+        )));
+
+        let cursor_grabs = Arc::new(Mutex::new(CursorGrabs::new()));
+        // The keys are repeated from the event loop, so that a repeat can't follow the release of
+        // its key.
+        let key_repeat_timer = ::calloop::timer::Timer::new();
+
         let mut seat_manager = SeatManager {
             sink: sink.clone(),
             relative_pointer_manager_proxy: None,
+            pointer_gestures_proxy: None,
             store: store.clone(),
             seats: seats.clone(),
+            data_devices: data_devices.clone(),
+            cursor_manager: cursor_manager.clone(),
+            cursor_grabs: cursor_grabs.clone(),
+            text_inputs: text_inputs.clone(),
+            tablets,
+            keymaps: keymaps.clone(),
+            key_repeater: KeyRepeater {
+                timer: key_repeat_timer.handle(),
+                no_repeat_windows: no_repeat_windows.clone(),
+            },
             kbd_sender,
         };
 
@@ -172,6 +284,73 @@ impl<T: 'static> EventLoop<T> {
                                 .unwrap(),
                         )
                     }
+                    if interface == "zwp_pointer_gestures_v1" {
+                        seat_manager.pointer_gestures_proxy = Some(
+                            registry
+                                .bind(1, id, move |pointer_gestures| {
+                                    pointer_gestures.implement_closure(|_, _| (), ())
+                                })
+                                .unwrap(),
+                        )
+                    }
+                    if interface == "zwp_pointer_constraints_v1" {
+                        let manager = registry
+                            .bind(1, id, move |manager| {
+                                manager.implement_closure(|_, _| (), ())
+                            })
+                            .unwrap();
+                        seat_manager
+                            .cursor_grabs
+                            .lock()
+                            .unwrap()
+                            .set_manager(manager);
+                    }
+                    if interface == "zwp_text_input_manager_v3" {
+                        let manager = registry
+                            .bind(1, id, move |manager| {
+                                manager.implement_closure(|_, _| (), ())
+                            })
+                            .unwrap();
+                        seat_manager
+                            .text_inputs
+                            .lock()
+                            .unwrap()
+                            .set_manager(manager);
+                    }
+                    if interface == "zwp_tablet_manager_v2" {
+                        let manager = registry
+                            .bind(1, id, move |manager| {
+                                manager.implement_closure(|_, _| (), ())
+                            })
+                            .unwrap();
+                        seat_manager.tablets.lock().unwrap().set_manager(manager);
+                    }
+                    if interface == "wl_data_device_manager" {
+                        use std::cmp::min;
+
+                        let manager = registry
+                            .bind(min(version, 3), id, move |manager| {
+                                manager.implement_closure(|_, _| (), ())
+                            })
+                            .unwrap();
+                        seat_manager
+                            .data_devices
+                            .lock()
+                            .unwrap()
+                            .set_manager(manager);
+                    }
+                    if interface == "zwp_primary_selection_device_manager_v1" {
+                        let manager = registry
+                            .bind(1, id, move |manager| {
+                                manager.implement_closure(|_, _| (), ())
+                            })
+                            .unwrap();
+                        seat_manager
+                            .data_devices
+                            .lock()
+                            .unwrap()
+                            .set_primary_selection_manager(manager);
+                    }
                     if interface == "wl_seat" {
                         seat_manager.add_seat(id, version, registry)
                     }
@@ -185,6 +364,62 @@ impl<T: 'static> EventLoop<T> {
         )
         .unwrap();
 
+        // Drags are started from the event loop, as our protocol objects can only be created
+        // from its thread.
+        let (drag_requests, drag_request_channel) = ::calloop::channel::channel::<DragRequest>();
+        let drag_data_devices = data_devices.clone();
+        let compositor = env.compositor.clone();
+        let shm = env.shm.clone();
+        let drag_request_source = inner_loop
+            .handle()
+            .insert_source(drag_request_channel, move |evt, &mut ()| {
+                if let ::calloop::channel::Event::Msg(request) = evt {
+                    let wid = make_wid(&request.surface);
+                    let icon = request
+                        .icon
+                        .as_ref()
+                        .and_then(|icon| DragIcon::new(&compositor, &shm, icon));
+                    let result = drag_data_devices.lock().unwrap().start_drag(request, icon);
+                    if result.is_err() {
+                        let _ = drag_ended_sender.send((WindowEvent::DragEnded(None), wid));
+                    }
+                }
+            })
+            .unwrap();
+
+        // Cursors are set from the event loop as well, for their buffers to be created from its
+        // thread.
+        cursor_manager
+            .lock()
+            .unwrap()
+            .set_globals(env.compositor.clone(), env.shm.clone());
+        let (cursor_requests, cursor_request_channel) = ::calloop::channel::channel();
+        let request_cursor_manager = cursor_manager.clone();
+        let timer_cursor_manager = cursor_manager.clone();
+        let cursor_timer_source = inner_loop
+            .handle()
+            .insert_source(cursor_timer, move |(pointer, _), &mut ()| {
+                timer_cursor_manager.lock().unwrap().next_frame(&pointer);
+            })
+            .unwrap();
+        let key_repeat_source = inner_loop
+            .handle()
+            .insert_source(key_repeat_timer, |(repeat, timer), &mut ()| {
+                repeat.repeat(&timer)
+            })
+            .unwrap();
+        let cursor_request_source = inner_loop
+            .handle()
+            .insert_source(cursor_request_channel, move |evt, &mut ()| {
+                if let ::calloop::channel::Event::Msg((wid, cursor)) = evt {
+                    request_cursor_manager
+                        .lock()
+                        .unwrap()
+                        .set_cursor(wid, cursor);
+                }
+            })
+            .unwrap();
+
         let source = inner_loop
             .handle()
             .insert_source(event_queue, |(), &mut ()| {})
@@ -213,6 +448,11 @@ impl<T: 'static> EventLoop<T> {
             _user_source: user_source,
             user_sender,
             _kbd_source: kbd_source,
+            _selection_source: selection_source,
+            _drag_request_source: drag_request_source,
+            _cursor_request_source: cursor_request_source,
+            _cursor_timer_source: cursor_timer_source,
+            _key_repeat_source: key_repeat_source,
             window_target: RootELW {
                 p: crate::platform_impl::EventLoopWindowTarget::Wayland(EventLoopWindowTarget {
                     evq: RefCell::new(source),
@@ -220,6 +460,15 @@ impl<T: 'static> EventLoop<T> {
                     env,
                     cleanup_needed: Arc::new(Mutex::new(false)),
                     seats,
+                    data_devices,
+                    drag_requests,
+                    cursor_manager,
+                    cursor_requests,
+                    cursor_grabs,
+                    text_inputs,
+                    keymaps,
+                    no_repeat_windows,
+                    gamepads,
                     display,
                     _marker: ::std::marker::PhantomData,
                 }),
@@ -399,6 +648,43 @@ impl<T> EventLoopWindowTarget<T> {
     pub fn display(&self) -> &Display {
         &*self.display
     }
+
+    pub fn set_selection(
+        &self,
+        selection: Selection,
+        data: Vec<(String, Vec<u8>)>,
+    ) -> Result<(), ExternalError> {
+        self.data_devices
+            .lock()
+            .unwrap()
+            .set_selection(selection, data)
+            .map_err(|err| ExternalError::Os(os_error!(OsError::WaylandMisc(err))))
+    }
+
+    pub fn request_selection(&self, selection: Selection, mime_type: String) {
+        self.data_devices
+            .lock()
+            .unwrap()
+            .request_selection(selection, mime_type)
+    }
+
+    pub fn set_cursor_theme(&self, name: Option<&str>, size: Option<u32>) {
+        self.cursor_manager
+            .lock()
+            .unwrap()
+            .set_theme(CursorTheme::new(name, size, None, None))
+    }
+
+    pub fn with_kbd_state<F, R>(&self, f: F) -> Option<R>
+    where
+        F: FnOnce(&KbdState) -> R,
+    {
+        self.keymaps.lock().unwrap().with_kbd_state(f)
+    }
+
+    pub fn key_repeat_info(&self) -> Option<KeyRepeatInfo> {
+        self.keymaps.lock().unwrap().key_repeat_info()
+    }
 }
 
 /*
@@ -419,11 +705,17 @@ impl<T> EventLoop<T> {
                 let pruned = window_target.store.lock().unwrap().cleanup();
                 *cleanup_needed = false;
                 for wid in pruned {
+                    window_target
+                        .cursor_manager
+                        .lock()
+                        .unwrap()
+                        .remove_window(wid);
                     sink.send_window_event(crate::event::WindowEvent::Destroyed, wid);
                 }
             }
         }
         // process pending resize/refresh
+        let cursor_manager = &window_target.cursor_manager;
         window_target.store.lock().unwrap().for_each(
             |newsize, size, new_dpi, refresh, frame_refresh, closed, wid, frame| {
                 if let Some(frame) = frame {
@@ -444,6 +736,7 @@ impl<T> EventLoop<T> {
                     }
                 }
                 if let Some(dpi) = new_dpi {
+                    cursor_manager.lock().unwrap().set_scale(wid, dpi);
                     sink.send_window_event(
                         crate::event::WindowEvent::HiDpiFactorChanged(dpi as f64),
                         wid,
@@ -468,35 +761,74 @@ struct SeatManager<T: 'static> {
     sink: Arc<Mutex<WindowEventsSink<T>>>,
     store: Arc<Mutex<WindowStore>>,
     seats: Arc<Mutex<Vec<(u32, wl_seat::WlSeat)>>>,
+    data_devices: Arc<Mutex<DataDevices>>,
+    cursor_manager: Arc<Mutex<CursorManager>>,
+    cursor_grabs: Arc<Mutex<CursorGrabs>>,
+    text_inputs: Arc<Mutex<TextInputs>>,
+    tablets: Arc<Mutex<Tablets>>,
+    keymaps: Arc<Mutex<Keymaps>>,
+    key_repeater: KeyRepeater,
     kbd_sender: ::calloop::channel::Sender<(crate::event::WindowEvent, super::WindowId)>,
     relative_pointer_manager_proxy: Option<ZwpRelativePointerManagerV1>,
+    pointer_gestures_proxy: Option<ZwpPointerGesturesV1>,
 }
 
 impl<T: 'static> SeatManager<T> {
     fn add_seat(&mut self, id: u32, version: u32, registry: wl_registry::WlRegistry) {
         use std::cmp::min;
 
+        let keyboard_focus = Arc::new(Mutex::new(None));
+        let pressed_button = Arc::new(Mutex::new(None));
+        // Without libxkbcommon, which most compositors need anyway, we don't have the keymap
+        // serialized by the compositor, so only the physical keys are known.
+        let kbd_state = Arc::new(Mutex::new(KbdState::new()));
+        // Until the seat has a keyboard, there's no key to repeat.
+        let repeat_info = Arc::new(Mutex::new(None));
+        self.keymaps
+            .lock()
+            .unwrap()
+            .add_seat(id, kbd_state.clone(), repeat_info.clone());
         let mut seat_data = SeatData {
             sink: self.sink.clone(),
             store: self.store.clone(),
             pointer: None,
             relative_pointer: None,
             relative_pointer_manager_proxy: self.relative_pointer_manager_proxy.as_ref().cloned(),
+            swipe_gesture: None,
+            pinch_gesture: None,
+            pointer_gestures_proxy: self.pointer_gestures_proxy.as_ref().cloned(),
             keyboard: None,
             touch: None,
             kbd_sender: self.kbd_sender.clone(),
+            kbd_state,
+            repeat_info,
+            key_repeater: self.key_repeater.clone(),
+            cursor_manager: self.cursor_manager.clone(),
+            cursor_grabs: self.cursor_grabs.clone(),
             modifiers_tracker: Arc::new(Mutex::new(ModifiersState::default())),
+            keyboard_focus: keyboard_focus.clone(),
+            pressed_button: pressed_button.clone(),
         };
         let seat = registry
-            .bind(min(version, 5), id, move |seat| {
+            .bind(min(version, 6), id, move |seat| {
                 seat.implement_closure(move |event, seat| seat_data.receive(event, seat), ())
             })
             .unwrap();
         self.store.lock().unwrap().new_seat(&seat);
+        self.data_devices
+            .lock()
+            .unwrap()
+            .add_seat(id, &seat, keyboard_focus, pressed_button);
+        self.text_inputs.lock().unwrap().add_seat(id, &seat);
+        self.tablets.lock().unwrap().add_seat(id, &seat);
         self.seats.lock().unwrap().push((id, seat));
     }
 
     fn remove_seat(&mut self, id: u32) {
+        self.data_devices.lock().unwrap().remove_seat(id);
+        self.text_inputs.lock().unwrap().remove_seat(id);
+        self.tablets.lock().unwrap().remove_seat(id);
+        self.keymaps.lock().unwrap().remove_seat(id);
         let mut seats = self.seats.lock().unwrap();
         if let Some(idx) = seats.iter().position(|&(i, _)| i == id) {
             let (_, seat) = seats.swap_remove(idx);
@@ -511,12 +843,22 @@ struct SeatData<T> {
     sink: Arc<Mutex<WindowEventsSink<T>>>,
     store: Arc<Mutex<WindowStore>>,
     kbd_sender: ::calloop::channel::Sender<(crate::event::WindowEvent, super::WindowId)>,
+    kbd_state: Arc<Mutex<Option<KbdState>>>,
+    repeat_info: Arc<Mutex<Option<KeyRepeatInfo>>>,
+    key_repeater: KeyRepeater,
+    cursor_manager: Arc<Mutex<CursorManager>>,
+    cursor_grabs: Arc<Mutex<CursorGrabs>>,
     pointer: Option<wl_pointer::WlPointer>,
     relative_pointer: Option<ZwpRelativePointerV1>,
     relative_pointer_manager_proxy: Option<ZwpRelativePointerManagerV1>,
+    swipe_gesture: Option<ZwpPointerGestureSwipeV1>,
+    pinch_gesture: Option<ZwpPointerGesturePinchV1>,
+    pointer_gestures_proxy: Option<ZwpPointerGesturesV1>,
     keyboard: Option<wl_keyboard::WlKeyboard>,
     touch: Option<wl_touch::WlTouch>,
     modifiers_tracker: Arc<Mutex<ModifiersState>>,
+    keyboard_focus: Arc<Mutex<Option<(WindowId, u32)>>>,
+    pressed_button: Arc<Mutex<Option<(WindowId, u32)>>>,
 }
 
 impl<T: 'static> SeatData<T> {
@@ -531,7 +873,13 @@ impl<T: 'static> SeatData<T> {
                         self.sink.clone(),
                         self.store.clone(),
                         self.modifiers_tracker.clone(),
+                        self.pressed_button.clone(),
+                        self.cursor_manager.clone(),
                     ));
+                    self.cursor_grabs
+                        .lock()
+                        .unwrap()
+                        .add_pointer(self.pointer.as_ref().unwrap());
 
                     self.relative_pointer =
                         self.relative_pointer_manager_proxy
@@ -543,11 +891,31 @@ impl<T: 'static> SeatData<T> {
                                     manager,
                                 )
                                 .ok()
-                            })
+                            });
+
+                    if let Some(ref manager) = self.pointer_gestures_proxy {
+                        let pointer = self.pointer.as_ref().unwrap();
+                        self.swipe_gesture = super::pointer::implement_swipe_gesture(
+                            self.sink.clone(),
+                            self.store.clone(),
+                            pointer,
+                            manager,
+                        )
+                        .ok();
+                        self.pinch_gesture = super::pointer::implement_pinch_gesture(
+                            self.sink.clone(),
+                            self.store.clone(),
+                            pointer,
+                            manager,
+                        )
+                        .ok();
+                    }
                 }
                 // destroy pointer if applicable
                 if !capabilities.contains(wl_seat::Capability::Pointer) {
+                    self.destroy_gestures();
                     if let Some(pointer) = self.pointer.take() {
+                        self.cursor_grabs.lock().unwrap().remove_pointer(&pointer);
                         if pointer.as_ref().version() >= 3 {
                             pointer.release();
                         }
@@ -558,12 +926,17 @@ impl<T: 'static> SeatData<T> {
                     self.keyboard = Some(super::keyboard::init_keyboard(
                         &seat,
                         self.kbd_sender.clone(),
+                        self.kbd_state.clone(),
+                        self.repeat_info.clone(),
+                        self.key_repeater.clone(),
                         self.modifiers_tracker.clone(),
+                        self.keyboard_focus.clone(),
                     ))
                 }
                 // destroy keyboard if applicable
                 if !capabilities.contains(wl_seat::Capability::Keyboard) {
                     if let Some(kbd) = self.keyboard.take() {
+                        *self.repeat_info.lock().unwrap() = None;
                         if kbd.as_ref().version() >= 3 {
                             kbd.release();
                         }
@@ -591,9 +964,22 @@ impl<T: 'static> SeatData<T> {
     }
 }
 
+impl<T> SeatData<T> {
+    fn destroy_gestures(&mut self) {
+        if let Some(swipe) = self.swipe_gesture.take() {
+            swipe.destroy();
+        }
+        if let Some(pinch) = self.pinch_gesture.take() {
+            pinch.destroy();
+        }
+    }
+}
+
 impl<T> Drop for SeatData<T> {
     fn drop(&mut self) {
+        self.destroy_gestures();
         if let Some(pointer) = self.pointer.take() {
+            self.cursor_grabs.lock().unwrap().remove_pointer(&pointer);
             if pointer.as_ref().version() >= 3 {
                 pointer.release();
             }
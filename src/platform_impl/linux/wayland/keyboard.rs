@@ -1,378 +1,394 @@
-use std::sync::{Arc, Mutex};
+use std::{
+    collections::HashSet,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use super::{make_wid, DeviceId, WindowId};
+use calloop::timer::{Timeout, TimerHandle};
+use smithay_client_toolkit::reexports::client::protocol::{wl_keyboard, wl_seat};
 
-use super::{make_wid, DeviceId};
-use smithay_client_toolkit::{
-    keyboard::{
-        self, map_keyboard_auto_with_repeat, Event as KbEvent, KeyRepeatEvent, KeyRepeatKind,
-    },
-    reexports::client::protocol::{wl_keyboard, wl_seat},
+use crate::{
+    event::{ElementState, KeyEvent, KeyboardInput, ModifiersState, WindowEvent},
+    keyboard::{KeyRepeatInfo, KeyboardLayout},
+    platform_impl::platform::{keyboard, xkb::KbdState},
 };
 
-use crate::event::{ElementState, KeyboardInput, ModifiersState, VirtualKeyCode, WindowEvent};
+type KbdSink = ::calloop::channel::Sender<(WindowEvent, WindowId)>;
+
+// The timing of the key repeat until the compositor sends its own, which is the default of the X
+// server.
+const DEFAULT_REPEAT_RATE: u32 = 25;
+const DEFAULT_REPEAT_DELAY: u64 = 600;
+
+/// The keyboard states and key repeat of the seats, which the event loop queries for the keymap.
+pub struct Keymaps {
+    seats: Vec<SeatKeyboard>,
+}
+
+struct SeatKeyboard {
+    id: u32,
+    kbd_state: Arc<Mutex<Option<KbdState>>>,
+    repeat_info: Arc<Mutex<Option<KeyRepeatInfo>>>,
+}
+
+impl Keymaps {
+    pub fn new() -> Keymaps {
+        Keymaps { seats: Vec::new() }
+    }
+
+    pub fn add_seat(
+        &mut self,
+        id: u32,
+        kbd_state: Arc<Mutex<Option<KbdState>>>,
+        repeat_info: Arc<Mutex<Option<KeyRepeatInfo>>>,
+    ) {
+        self.seats.push(SeatKeyboard {
+            id,
+            kbd_state,
+            repeat_info,
+        });
+    }
+
+    pub fn remove_seat(&mut self, id: u32) {
+        self.seats.retain(|seat| seat.id != id);
+    }
+
+    /// Calls `f` with the keyboard state of the first seat, unless libxkbcommon isn't available.
+    pub fn with_kbd_state<F, R>(&self, f: F) -> Option<R>
+    where
+        F: FnOnce(&KbdState) -> R,
+    {
+        let kbd_state = self.seats.first()?.kbd_state.lock().unwrap();
+        kbd_state.as_ref().map(f)
+    }
+
+    /// The key repeat of the keyboard of the first seat, if it has a keyboard repeating keys.
+    pub fn key_repeat_info(&self) -> Option<KeyRepeatInfo> {
+        *self.seats.first()?.repeat_info.lock().unwrap()
+    }
+}
+
+/// How the keyboards repeat their keys, which is from the timer of the event loop.
+#[derive(Clone)]
+pub struct KeyRepeater {
+    pub timer: TimerHandle<KeyRepeat>,
+    // The windows that don't want their keys repeated.
+    pub no_repeat_windows: Arc<Mutex<HashSet<WindowId>>>,
+}
 
 pub fn init_keyboard(
     seat: &wl_seat::WlSeat,
-    sink: ::calloop::channel::Sender<(crate::event::WindowEvent, super::WindowId)>,
+    sink: KbdSink,
+    kbd_state: Arc<Mutex<Option<KbdState>>>,
+    repeat_info: Arc<Mutex<Option<KeyRepeatInfo>>>,
+    repeater: KeyRepeater,
     modifiers_tracker: Arc<Mutex<ModifiersState>>,
+    focus: Arc<Mutex<Option<(WindowId, u32)>>>,
 ) -> wl_keyboard::WlKeyboard {
-    // { variables to be captured by the closures
-    let target = Arc::new(Mutex::new(None));
-    let my_sink = sink.clone();
-    let repeat_sink = sink.clone();
-    let repeat_target = target.clone();
-    let my_modifiers = modifiers_tracker.clone();
-    // }
-    let ret = map_keyboard_auto_with_repeat(
-        seat,
-        KeyRepeatKind::System,
-        move |evt: KbEvent<'_>, _| {
-            match evt {
-                KbEvent::Enter { surface, .. } => {
-                    let wid = make_wid(&surface);
-                    my_sink.send((WindowEvent::Focused(true), wid)).unwrap();
-                    *target.lock().unwrap() = Some(wid);
+    *repeat_info.lock().unwrap() = Some(KeyRepeatInfo {
+        rate: DEFAULT_REPEAT_RATE,
+        delay: Duration::from_millis(DEFAULT_REPEAT_DELAY),
+    });
+    let mut handler = KbdHandler {
+        sink,
+        kbd_state,
+        modifiers_tracker,
+        target: focus,
+        repeat_timer: repeater.timer,
+        repeat_key: None,
+        repeat_timeout: Arc::new(Mutex::new(None)),
+        repeat_info,
+        no_repeat_windows: repeater.no_repeat_windows,
+        reported_modifiers: ModifiersState::default(),
+        reported_layout: None,
+    };
+    seat.get_keyboard(|keyboard| keyboard.implement_closure(move |evt, _| handler.event(evt), ()))
+        .unwrap()
+}
+
+struct KbdHandler {
+    sink: KbdSink,
+    kbd_state: Arc<Mutex<Option<KbdState>>>,
+    modifiers_tracker: Arc<Mutex<ModifiersState>>,
+    target: Arc<Mutex<Option<(WindowId, u32)>>>,
+    // The timer repeating the keys, on the thread of the event loop like the key events.
+    repeat_timer: TimerHandle<KeyRepeat>,
+    // The key being repeated, and the pending timeout of its next repeat.
+    repeat_key: Option<u32>,
+    repeat_timeout: Arc<Mutex<Option<Timeout>>>,
+    // The timing of the repeat, which is `None` when the compositor disables it.
+    repeat_info: Arc<Mutex<Option<KeyRepeatInfo>>>,
+    // The windows that don't want their keys repeated.
+    no_repeat_windows: Arc<Mutex<HashSet<WindowId>>>,
+    // The modifiers and layout last sent with `ModifiersChanged` and `KeyboardLayoutChanged`.
+    reported_modifiers: ModifiersState,
+    reported_layout: Option<KeyboardLayout>,
+}
+
+impl KbdHandler {
+    fn event(&mut self, evt: wl_keyboard::Event) {
+        match evt {
+            wl_keyboard::Event::Keymap { format, fd, size } => {
+                let layout = match *self.kbd_state.lock().unwrap() {
+                    Some(ref mut kbd_state) if format == wl_keyboard::KeymapFormat::XkbV1 => {
+                        unsafe { kbd_state.set_keymap_from_fd(fd, size as usize) };
+                        kbd_state.layout()
+                    }
+                    _ => {
+                        unsafe { libc::close(fd) };
+                        return;
+                    }
+                };
+                let target = *self.target.lock().unwrap();
+                if let Some((wid, _)) = target {
+                    self.report_layout(wid, layout);
                 }
-                KbEvent::Leave { surface, .. } => {
-                    let wid = make_wid(&surface);
-                    my_sink.send((WindowEvent::Focused(false), wid)).unwrap();
-                    *target.lock().unwrap() = None;
+            }
+            wl_keyboard::Event::Enter {
+                surface, serial, ..
+            } => {
+                let wid = make_wid(&surface);
+                self.sink.send((WindowEvent::Focused(true), wid)).unwrap();
+                *self.target.lock().unwrap() = Some((wid, serial));
+            }
+            wl_keyboard::Event::Leave { surface, .. } => {
+                self.stop_repeat();
+                let wid = make_wid(&surface);
+                if let Some(ref mut kbd_state) = *self.kbd_state.lock().unwrap() {
+                    kbd_state.clear_modifier_keys();
                 }
-                KbEvent::Key {
-                    rawkey,
-                    keysym,
-                    state,
-                    utf8,
-                    ..
-                } => {
-                    if let Some(wid) = *target.lock().unwrap() {
-                        let state = match state {
-                            wl_keyboard::KeyState::Pressed => ElementState::Pressed,
-                            wl_keyboard::KeyState::Released => ElementState::Released,
-                            _ => unreachable!(),
-                        };
-                        let vkcode = key_to_vkey(rawkey, keysym);
-                        my_sink
-                            .send((
-                                WindowEvent::KeyboardInput {
-                                    device_id: crate::event::DeviceId(
-                                        crate::platform_impl::DeviceId::Wayland(DeviceId),
-                                    ),
-                                    input: KeyboardInput {
-                                        state,
-                                        scancode: rawkey,
-                                        virtual_keycode: vkcode,
-                                        modifiers: modifiers_tracker.lock().unwrap().clone(),
-                                    },
-                                },
-                                wid,
-                            ))
-                            .unwrap();
-                        // send char event only on key press, not release
-                        if let ElementState::Released = state {
-                            return;
+                // The compositor sends the modifiers again when a window gains the focus.
+                self.report_modifiers(wid, ModifiersState::default());
+                self.sink.send((WindowEvent::Focused(false), wid)).unwrap();
+                *self.target.lock().unwrap() = None;
+            }
+            wl_keyboard::Event::Key {
+                serial, key, state, ..
+            } => {
+                let state = match state {
+                    wl_keyboard::KeyState::Pressed => ElementState::Pressed,
+                    wl_keyboard::KeyState::Released => ElementState::Released,
+                    _ => unreachable!(),
+                };
+                let wid = {
+                    let mut target = self.target.lock().unwrap();
+                    match *target {
+                        Some((wid, ref mut last_serial)) => {
+                            *last_serial = serial;
+                            wid
                         }
-                        if let Some(txt) = utf8 {
-                            for chr in txt.chars() {
-                                my_sink
-                                    .send((WindowEvent::ReceivedCharacter(chr), wid))
-                                    .unwrap();
-                            }
+                        None => return,
+                    }
+                };
+
+                // The keycodes of xkbcommon are the evdev scan codes plus 8, as on X11.
+                let keycode = key + 8;
+                let (keysym, event, modifiers, repeats) = match *self.kbd_state.lock().unwrap() {
+                    Some(ref mut kbd_state) => {
+                        kbd_state.update_modifier_key(keycode, state);
+                        (
+                            kbd_state.keysym(keycode),
+                            kbd_state.process_key_event(keycode, state, false),
+                            kbd_state.mods_state(),
+                            kbd_state.key_repeats(keycode),
+                        )
+                    }
+                    None => (
+                        0,
+                        keyboard::key_event(key, 0, None, state, false),
+                        ModifiersState::default(),
+                        false,
+                    ),
+                };
+                self.report_modifiers(wid, modifiers);
+                send_key_events(&self.sink, wid, key, keysym, event, modifiers);
+
+                let repeats = repeats && !self.no_repeat_windows.lock().unwrap().contains(&wid);
+                match state {
+                    ElementState::Pressed if repeats => self.start_repeat(key, wid),
+                    ElementState::Pressed => (),
+                    ElementState::Released => {
+                        if self.repeat_key == Some(key) {
+                            self.stop_repeat();
                         }
                     }
                 }
-                KbEvent::RepeatInfo { .. } => { /* Handled by smithay client toolkit */ }
-                KbEvent::Modifiers {
-                    modifiers: event_modifiers,
-                } => *modifiers_tracker.lock().unwrap() = event_modifiers.into(),
             }
-        },
-        move |repeat_event: KeyRepeatEvent, _| {
-            if let Some(wid) = *repeat_target.lock().unwrap() {
-                let state = ElementState::Pressed;
-                let vkcode = key_to_vkey(repeat_event.rawkey, repeat_event.keysym);
-                repeat_sink
-                    .send((
-                        WindowEvent::KeyboardInput {
-                            device_id: crate::event::DeviceId(
-                                crate::platform_impl::DeviceId::Wayland(DeviceId),
-                            ),
-                            input: KeyboardInput {
-                                state,
-                                scancode: repeat_event.rawkey,
-                                virtual_keycode: vkcode,
-                                modifiers: my_modifiers.lock().unwrap().clone(),
-                            },
-                        },
-                        wid,
-                    ))
-                    .unwrap();
-                if let Some(txt) = repeat_event.utf8 {
-                    for chr in txt.chars() {
-                        repeat_sink
-                            .send((WindowEvent::ReceivedCharacter(chr), wid))
-                            .unwrap();
+            wl_keyboard::Event::Modifiers {
+                mods_depressed,
+                mods_latched,
+                mods_locked,
+                group,
+                ..
+            } => {
+                let (modifiers, layout) = match *self.kbd_state.lock().unwrap() {
+                    Some(ref mut kbd_state) => {
+                        kbd_state.update_modifiers(
+                            mods_depressed,
+                            mods_latched,
+                            mods_locked,
+                            0,
+                            0,
+                            group,
+                        );
+                        (kbd_state.mods_state(), kbd_state.layout())
                     }
+                    None => return,
+                };
+                *self.modifiers_tracker.lock().unwrap() = modifiers;
+                let target = *self.target.lock().unwrap();
+                if let Some((wid, _)) = target {
+                    self.report_modifiers(wid, modifiers);
+                    self.report_layout(wid, layout);
                 }
             }
-        },
-    );
+            wl_keyboard::Event::RepeatInfo { rate, delay } => {
+                // A rate of 0 disables the repeat.
+                *self.repeat_info.lock().unwrap() = if rate > 0 {
+                    Some(KeyRepeatInfo {
+                        rate: rate as u32,
+                        delay: Duration::from_millis(delay.max(0) as u64),
+                    })
+                } else {
+                    None
+                };
+            }
+            _ => (),
+        }
+    }
 
-    match ret {
-        Ok(keyboard) => keyboard,
-        Err(_) => {
-            // This is a fallback impl if libxkbcommon was not available
-            // This case should probably never happen, as most wayland
-            // compositors _need_ libxkbcommon anyway...
-            //
-            // In this case, we don't have the keymap information (it is
-            // supposed to be serialized by the compositor using libxkbcommon)
+    // Sends `ModifiersChanged` to the focused window, if the modifiers changed since it was last
+    // told.
+    fn report_modifiers(&mut self, wid: WindowId, modifiers: ModifiersState) {
+        if modifiers != self.reported_modifiers {
+            self.reported_modifiers = modifiers;
+            self.sink
+                .send((WindowEvent::ModifiersChanged(modifiers), wid))
+                .unwrap();
+        }
+    }
 
-            seat.get_keyboard(|keyboard| {
-                // { variables to be captured by the closure
-                let mut target = None;
-                let my_sink = sink;
-                // }
+    // Sends `KeyboardLayoutChanged` to the focused window, if the layout changed since it was last
+    // told.
+    fn report_layout(&mut self, wid: WindowId, layout: Option<KeyboardLayout>) {
+        if layout != self.reported_layout {
+            self.reported_layout = layout.clone();
+            if let Some(layout) = layout {
+                self.sink
+                    .send((WindowEvent::KeyboardLayoutChanged(layout), wid))
+                    .unwrap();
+            }
+        }
+    }
 
-                keyboard.implement_closure(
-                    move |evt, _| match evt {
-                        wl_keyboard::Event::Enter { surface, .. } => {
-                            let wid = make_wid(&surface);
-                            my_sink.send((WindowEvent::Focused(true), wid)).unwrap();
-                            target = Some(wid);
-                        }
-                        wl_keyboard::Event::Leave { surface, .. } => {
-                            let wid = make_wid(&surface);
-                            my_sink.send((WindowEvent::Focused(false), wid)).unwrap();
-                            target = None;
-                        }
-                        wl_keyboard::Event::Key { key, state, .. } => {
-                            if let Some(wid) = target {
-                                let state = match state {
-                                    wl_keyboard::KeyState::Pressed => ElementState::Pressed,
-                                    wl_keyboard::KeyState::Released => ElementState::Released,
-                                    _ => unreachable!(),
-                                };
-                                my_sink
-                                    .send((
-                                        WindowEvent::KeyboardInput {
-                                            device_id: crate::event::DeviceId(
-                                                crate::platform_impl::DeviceId::Wayland(DeviceId),
-                                            ),
-                                            input: KeyboardInput {
-                                                state,
-                                                scancode: key,
-                                                virtual_keycode: None,
-                                                modifiers: ModifiersState::default(),
-                                            },
-                                        },
-                                        wid,
-                                    ))
-                                    .unwrap();
-                            }
-                        }
-                        _ => (),
-                    },
-                    (),
-                )
-            })
-            .unwrap()
+    // Repeats a key until it's released or the window loses the focus.
+    fn start_repeat(&mut self, key: u32, wid: WindowId) {
+        self.stop_repeat();
+        let repeat_info = match *self.repeat_info.lock().unwrap() {
+            Some(repeat_info) => repeat_info,
+            None => return,
+        };
+
+        let repeat = KeyRepeat {
+            key,
+            wid,
+            interval: Duration::from_micros(1_000_000 / u64::from(repeat_info.rate)),
+            sink: self.sink.clone(),
+            kbd_state: self.kbd_state.clone(),
+            timeout: self.repeat_timeout.clone(),
+        };
+        *self.repeat_timeout.lock().unwrap() =
+            Some(self.repeat_timer.add_timeout(repeat_info.delay, repeat));
+        self.repeat_key = Some(key);
+    }
+
+    fn stop_repeat(&mut self) {
+        self.repeat_key = None;
+        if let Some(timeout) = self.repeat_timeout.lock().unwrap().take() {
+            self.repeat_timer.cancel_timeout(&timeout);
         }
     }
 }
 
-fn key_to_vkey(rawkey: u32, keysym: u32) -> Option<VirtualKeyCode> {
-    match rawkey {
-        1 => Some(VirtualKeyCode::Escape),
-        2 => Some(VirtualKeyCode::Key1),
-        3 => Some(VirtualKeyCode::Key2),
-        4 => Some(VirtualKeyCode::Key3),
-        5 => Some(VirtualKeyCode::Key4),
-        6 => Some(VirtualKeyCode::Key5),
-        7 => Some(VirtualKeyCode::Key6),
-        8 => Some(VirtualKeyCode::Key7),
-        9 => Some(VirtualKeyCode::Key8),
-        10 => Some(VirtualKeyCode::Key9),
-        11 => Some(VirtualKeyCode::Key0),
-        _ => keysym_to_vkey(keysym),
+impl Drop for KbdHandler {
+    fn drop(&mut self) {
+        self.stop_repeat();
     }
 }
 
-fn keysym_to_vkey(keysym: u32) -> Option<VirtualKeyCode> {
-    use smithay_client_toolkit::keyboard::keysyms;
-    match keysym {
-        // letters
-        keysyms::XKB_KEY_A | keysyms::XKB_KEY_a => Some(VirtualKeyCode::A),
-        keysyms::XKB_KEY_B | keysyms::XKB_KEY_b => Some(VirtualKeyCode::B),
-        keysyms::XKB_KEY_C | keysyms::XKB_KEY_c => Some(VirtualKeyCode::C),
-        keysyms::XKB_KEY_D | keysyms::XKB_KEY_d => Some(VirtualKeyCode::D),
-        keysyms::XKB_KEY_E | keysyms::XKB_KEY_e => Some(VirtualKeyCode::E),
-        keysyms::XKB_KEY_F | keysyms::XKB_KEY_f => Some(VirtualKeyCode::F),
-        keysyms::XKB_KEY_G | keysyms::XKB_KEY_g => Some(VirtualKeyCode::G),
-        keysyms::XKB_KEY_H | keysyms::XKB_KEY_h => Some(VirtualKeyCode::H),
-        keysyms::XKB_KEY_I | keysyms::XKB_KEY_i => Some(VirtualKeyCode::I),
-        keysyms::XKB_KEY_J | keysyms::XKB_KEY_j => Some(VirtualKeyCode::J),
-        keysyms::XKB_KEY_K | keysyms::XKB_KEY_k => Some(VirtualKeyCode::K),
-        keysyms::XKB_KEY_L | keysyms::XKB_KEY_l => Some(VirtualKeyCode::L),
-        keysyms::XKB_KEY_M | keysyms::XKB_KEY_m => Some(VirtualKeyCode::M),
-        keysyms::XKB_KEY_N | keysyms::XKB_KEY_n => Some(VirtualKeyCode::N),
-        keysyms::XKB_KEY_O | keysyms::XKB_KEY_o => Some(VirtualKeyCode::O),
-        keysyms::XKB_KEY_P | keysyms::XKB_KEY_p => Some(VirtualKeyCode::P),
-        keysyms::XKB_KEY_Q | keysyms::XKB_KEY_q => Some(VirtualKeyCode::Q),
-        keysyms::XKB_KEY_R | keysyms::XKB_KEY_r => Some(VirtualKeyCode::R),
-        keysyms::XKB_KEY_S | keysyms::XKB_KEY_s => Some(VirtualKeyCode::S),
-        keysyms::XKB_KEY_T | keysyms::XKB_KEY_t => Some(VirtualKeyCode::T),
-        keysyms::XKB_KEY_U | keysyms::XKB_KEY_u => Some(VirtualKeyCode::U),
-        keysyms::XKB_KEY_V | keysyms::XKB_KEY_v => Some(VirtualKeyCode::V),
-        keysyms::XKB_KEY_W | keysyms::XKB_KEY_w => Some(VirtualKeyCode::W),
-        keysyms::XKB_KEY_X | keysyms::XKB_KEY_x => Some(VirtualKeyCode::X),
-        keysyms::XKB_KEY_Y | keysyms::XKB_KEY_y => Some(VirtualKeyCode::Y),
-        keysyms::XKB_KEY_Z | keysyms::XKB_KEY_z => Some(VirtualKeyCode::Z),
-        // F--
-        keysyms::XKB_KEY_F1 => Some(VirtualKeyCode::F1),
-        keysyms::XKB_KEY_F2 => Some(VirtualKeyCode::F2),
-        keysyms::XKB_KEY_F3 => Some(VirtualKeyCode::F3),
-        keysyms::XKB_KEY_F4 => Some(VirtualKeyCode::F4),
-        keysyms::XKB_KEY_F5 => Some(VirtualKeyCode::F5),
-        keysyms::XKB_KEY_F6 => Some(VirtualKeyCode::F6),
-        keysyms::XKB_KEY_F7 => Some(VirtualKeyCode::F7),
-        keysyms::XKB_KEY_F8 => Some(VirtualKeyCode::F8),
-        keysyms::XKB_KEY_F9 => Some(VirtualKeyCode::F9),
-        keysyms::XKB_KEY_F10 => Some(VirtualKeyCode::F10),
-        keysyms::XKB_KEY_F11 => Some(VirtualKeyCode::F11),
-        keysyms::XKB_KEY_F12 => Some(VirtualKeyCode::F12),
-        keysyms::XKB_KEY_F13 => Some(VirtualKeyCode::F13),
-        keysyms::XKB_KEY_F14 => Some(VirtualKeyCode::F14),
-        keysyms::XKB_KEY_F15 => Some(VirtualKeyCode::F15),
-        keysyms::XKB_KEY_F16 => Some(VirtualKeyCode::F16),
-        keysyms::XKB_KEY_F17 => Some(VirtualKeyCode::F17),
-        keysyms::XKB_KEY_F18 => Some(VirtualKeyCode::F18),
-        keysyms::XKB_KEY_F19 => Some(VirtualKeyCode::F19),
-        keysyms::XKB_KEY_F20 => Some(VirtualKeyCode::F20),
-        keysyms::XKB_KEY_F21 => Some(VirtualKeyCode::F21),
-        keysyms::XKB_KEY_F22 => Some(VirtualKeyCode::F22),
-        keysyms::XKB_KEY_F23 => Some(VirtualKeyCode::F23),
-        keysyms::XKB_KEY_F24 => Some(VirtualKeyCode::F24),
-        // flow control
-        keysyms::XKB_KEY_Print => Some(VirtualKeyCode::Snapshot),
-        keysyms::XKB_KEY_Scroll_Lock => Some(VirtualKeyCode::Scroll),
-        keysyms::XKB_KEY_Pause => Some(VirtualKeyCode::Pause),
-        keysyms::XKB_KEY_Insert => Some(VirtualKeyCode::Insert),
-        keysyms::XKB_KEY_Home => Some(VirtualKeyCode::Home),
-        keysyms::XKB_KEY_Delete => Some(VirtualKeyCode::Delete),
-        keysyms::XKB_KEY_End => Some(VirtualKeyCode::End),
-        keysyms::XKB_KEY_Page_Down => Some(VirtualKeyCode::PageDown),
-        keysyms::XKB_KEY_Page_Up => Some(VirtualKeyCode::PageUp),
-        // arrows
-        keysyms::XKB_KEY_Left => Some(VirtualKeyCode::Left),
-        keysyms::XKB_KEY_Up => Some(VirtualKeyCode::Up),
-        keysyms::XKB_KEY_Right => Some(VirtualKeyCode::Right),
-        keysyms::XKB_KEY_Down => Some(VirtualKeyCode::Down),
-        //
-        keysyms::XKB_KEY_BackSpace => Some(VirtualKeyCode::Back),
-        keysyms::XKB_KEY_Return => Some(VirtualKeyCode::Return),
-        keysyms::XKB_KEY_space => Some(VirtualKeyCode::Space),
-        // keypad
-        keysyms::XKB_KEY_Num_Lock => Some(VirtualKeyCode::Numlock),
-        keysyms::XKB_KEY_KP_0 => Some(VirtualKeyCode::Numpad0),
-        keysyms::XKB_KEY_KP_1 => Some(VirtualKeyCode::Numpad1),
-        keysyms::XKB_KEY_KP_2 => Some(VirtualKeyCode::Numpad2),
-        keysyms::XKB_KEY_KP_3 => Some(VirtualKeyCode::Numpad3),
-        keysyms::XKB_KEY_KP_4 => Some(VirtualKeyCode::Numpad4),
-        keysyms::XKB_KEY_KP_5 => Some(VirtualKeyCode::Numpad5),
-        keysyms::XKB_KEY_KP_6 => Some(VirtualKeyCode::Numpad6),
-        keysyms::XKB_KEY_KP_7 => Some(VirtualKeyCode::Numpad7),
-        keysyms::XKB_KEY_KP_8 => Some(VirtualKeyCode::Numpad8),
-        keysyms::XKB_KEY_KP_9 => Some(VirtualKeyCode::Numpad9),
-        // misc
-        // => Some(VirtualKeyCode::AbntC1),
-        // => Some(VirtualKeyCode::AbntC2),
-        keysyms::XKB_KEY_plus => Some(VirtualKeyCode::Add),
-        keysyms::XKB_KEY_apostrophe => Some(VirtualKeyCode::Apostrophe),
-        // => Some(VirtualKeyCode::Apps),
-        // => Some(VirtualKeyCode::At),
-        // => Some(VirtualKeyCode::Ax),
-        keysyms::XKB_KEY_backslash => Some(VirtualKeyCode::Backslash),
-        // => Some(VirtualKeyCode::Calculator),
-        // => Some(VirtualKeyCode::Capital),
-        keysyms::XKB_KEY_colon => Some(VirtualKeyCode::Colon),
-        keysyms::XKB_KEY_comma => Some(VirtualKeyCode::Comma),
-        // => Some(VirtualKeyCode::Convert),
-        // => Some(VirtualKeyCode::Decimal),
-        // => Some(VirtualKeyCode::Divide),
-        keysyms::XKB_KEY_equal => Some(VirtualKeyCode::Equals),
-        // => Some(VirtualKeyCode::Grave),
-        // => Some(VirtualKeyCode::Kana),
-        // => Some(VirtualKeyCode::Kanji),
-        keysyms::XKB_KEY_Alt_L => Some(VirtualKeyCode::LAlt),
-        // => Some(VirtualKeyCode::LBracket),
-        keysyms::XKB_KEY_Control_L => Some(VirtualKeyCode::LControl),
-        keysyms::XKB_KEY_Shift_L => Some(VirtualKeyCode::LShift),
-        // => Some(VirtualKeyCode::LWin),
-        // => Some(VirtualKeyCode::Mail),
-        // => Some(VirtualKeyCode::MediaSelect),
-        // => Some(VirtualKeyCode::MediaStop),
-        keysyms::XKB_KEY_minus => Some(VirtualKeyCode::Minus),
-        keysyms::XKB_KEY_asterisk => Some(VirtualKeyCode::Multiply),
-        // => Some(VirtualKeyCode::Mute),
-        // => Some(VirtualKeyCode::MyComputer),
-        // => Some(VirtualKeyCode::NextTrack),
-        // => Some(VirtualKeyCode::NoConvert),
-        keysyms::XKB_KEY_KP_Separator => Some(VirtualKeyCode::NumpadComma),
-        keysyms::XKB_KEY_KP_Enter => Some(VirtualKeyCode::NumpadEnter),
-        keysyms::XKB_KEY_KP_Equal => Some(VirtualKeyCode::NumpadEquals),
-        keysyms::XKB_KEY_KP_Add => Some(VirtualKeyCode::Add),
-        keysyms::XKB_KEY_KP_Subtract => Some(VirtualKeyCode::Subtract),
-        keysyms::XKB_KEY_KP_Divide => Some(VirtualKeyCode::Divide),
-        keysyms::XKB_KEY_KP_Page_Up => Some(VirtualKeyCode::PageUp),
-        keysyms::XKB_KEY_KP_Page_Down => Some(VirtualKeyCode::PageDown),
-        keysyms::XKB_KEY_KP_Home => Some(VirtualKeyCode::Home),
-        keysyms::XKB_KEY_KP_End => Some(VirtualKeyCode::End),
-        // => Some(VirtualKeyCode::OEM102),
-        // => Some(VirtualKeyCode::Period),
-        // => Some(VirtualKeyCode::Playpause),
-        // => Some(VirtualKeyCode::Power),
-        // => Some(VirtualKeyCode::Prevtrack),
-        keysyms::XKB_KEY_Alt_R => Some(VirtualKeyCode::RAlt),
-        // => Some(VirtualKeyCode::RBracket),
-        keysyms::XKB_KEY_Control_R => Some(VirtualKeyCode::RControl),
-        keysyms::XKB_KEY_Shift_R => Some(VirtualKeyCode::RShift),
-        // => Some(VirtualKeyCode::RWin),
-        keysyms::XKB_KEY_semicolon => Some(VirtualKeyCode::Semicolon),
-        keysyms::XKB_KEY_slash => Some(VirtualKeyCode::Slash),
-        // => Some(VirtualKeyCode::Sleep),
-        // => Some(VirtualKeyCode::Stop),
-        // => Some(VirtualKeyCode::Subtract),
-        // => Some(VirtualKeyCode::Sysrq),
-        keysyms::XKB_KEY_Tab => Some(VirtualKeyCode::Tab),
-        keysyms::XKB_KEY_ISO_Left_Tab => Some(VirtualKeyCode::Tab),
-        // => Some(VirtualKeyCode::Underline),
-        // => Some(VirtualKeyCode::Unlabeled),
-        keysyms::XKB_KEY_XF86AudioLowerVolume => Some(VirtualKeyCode::VolumeDown),
-        keysyms::XKB_KEY_XF86AudioRaiseVolume => Some(VirtualKeyCode::VolumeUp),
-        // => Some(VirtualKeyCode::Wake),
-        // => Some(VirtualKeyCode::Webback),
-        // => Some(VirtualKeyCode::WebFavorites),
-        // => Some(VirtualKeyCode::WebForward),
-        // => Some(VirtualKeyCode::WebHome),
-        // => Some(VirtualKeyCode::WebRefresh),
-        // => Some(VirtualKeyCode::WebSearch),
-        // => Some(VirtualKeyCode::WebStop),
-        // => Some(VirtualKeyCode::Yen),
-        keysyms::XKB_KEY_XF86Copy => Some(VirtualKeyCode::Copy),
-        keysyms::XKB_KEY_XF86Paste => Some(VirtualKeyCode::Paste),
-        keysyms::XKB_KEY_XF86Cut => Some(VirtualKeyCode::Cut),
-        // fallback
-        _ => None,
-    }
+/// A key being repeated, which the timer of the event loop sends again at every timeout.
+pub struct KeyRepeat {
+    key: u32,
+    wid: WindowId,
+    interval: Duration,
+    sink: KbdSink,
+    kbd_state: Arc<Mutex<Option<KbdState>>>,
+    // Where the timeout of the next repeat is kept for the keyboard to cancel it.
+    timeout: Arc<Mutex<Option<Timeout>>>,
 }
 
-impl From<keyboard::ModifiersState> for ModifiersState {
-    fn from(mods: keyboard::ModifiersState) -> ModifiersState {
-        ModifiersState {
-            shift: mods.shift,
-            ctrl: mods.ctrl,
-            alt: mods.alt,
-            logo: mods.logo,
+impl KeyRepeat {
+    /// Sends the key again and schedules the next repeat, unless the keyboard is gone.
+    pub fn repeat(self, timer: &TimerHandle<KeyRepeat>) {
+        {
+            let kbd_state = self.kbd_state.lock().unwrap();
+            let kbd_state = match *kbd_state {
+                Some(ref kbd_state) => kbd_state,
+                None => return,
+            };
+            let keycode = self.key + 8;
+            let keysym = kbd_state.keysym(keycode);
+            // The compose sequences are left alone, as the key was already fed to them.
+            let event = keyboard::key_event(
+                self.key,
+                keysym,
+                kbd_state.text(keycode),
+                ElementState::Pressed,
+                true,
+            );
+            let modifiers = kbd_state.mods_state();
+            if !send_key_events(&self.sink, self.wid, self.key, keysym, event, modifiers) {
+                return;
+            }
         }
+        let timeout = self.timeout.clone();
+        *timeout.lock().unwrap() = Some(timer.add_timeout(self.interval, self));
     }
 }
+
+// Sends the `KeyboardInput`, `Key` and `ReceivedCharacter` events of a key, returning `false` if
+// the event loop is gone.
+fn send_key_events(
+    sink: &KbdSink,
+    wid: WindowId,
+    key: u32,
+    keysym: u32,
+    event: KeyEvent,
+    modifiers: ModifiersState,
+) -> bool {
+    let device_id = crate::event::DeviceId(crate::platform_impl::DeviceId::Wayland(DeviceId));
+    let text = event.text.clone();
+    let mut events = vec![
+        WindowEvent::KeyboardInput {
+            device_id,
+            input: KeyboardInput {
+                state: event.state,
+                scancode: key,
+                virtual_keycode: keyboard::virtual_keycode(key, keysym),
+                modifiers,
+                is_repeat: event.repeat,
+            },
+        },
+        WindowEvent::Key { device_id, event },
+    ];
+    // The text is only set on key presses.
+    events.extend(
+        text.iter()
+            .flat_map(|text| text.chars())
+            .map(WindowEvent::ReceivedCharacter),
+    );
+    events
+        .into_iter()
+        .all(|event| sink.send((event, wid)).is_ok())
+}
@@ -2,6 +2,7 @@
            target_os = "netbsd", target_os = "openbsd"))]
 
 pub use self::{
+    dnd::DragOffer,
     event_loop::{
         EventLoop, EventLoopProxy, EventLoopWindowTarget, MonitorHandle, VideoMode,
         WindowEventsSink,
@@ -11,9 +12,14 @@ pub use self::{
 
 use smithay_client_toolkit::reexports::client::protocol::wl_surface;
 
+mod cursor;
+mod data_device;
+mod dnd;
 mod event_loop;
 mod keyboard;
 mod pointer;
+mod tablet;
+mod text_input;
 mod touch;
 mod window;
 
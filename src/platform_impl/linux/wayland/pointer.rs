@@ -1,15 +1,41 @@
-use std::sync::{Arc, Mutex};
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
+
+use crate::{
+    error::{ExternalError, NotSupportedError},
+    event::{
+        DeviceEvent, ElementState, ModifiersState, MouseButton, MouseScrollDelta, ScrollSource,
+        TouchPhase, WheelDelta, WindowEvent,
+    },
+    platform_impl::platform::OsError,
+    window::CursorGrabMode,
+};
 
-use crate::event::{
-    DeviceEvent, ElementState, ModifiersState, MouseButton, MouseScrollDelta, TouchPhase,
-    WindowEvent,
+use super::{
+    cursor::CursorManager, event_loop::WindowEventsSink, window::WindowStore, DeviceId, WindowId,
 };
 
-use super::{event_loop::WindowEventsSink, window::WindowStore, DeviceId};
+use smithay_client_toolkit::reexports::client::{
+    protocol::{
+        wl_pointer::{self, Event as PtrEvent, WlPointer},
+        wl_seat,
+        wl_surface::WlSurface,
+    },
+    NewProxy,
+};
+
+use smithay_client_toolkit::reexports::protocols::unstable::pointer_constraints::v1::client::{
+    zwp_confined_pointer_v1::ZwpConfinedPointerV1,
+    zwp_locked_pointer_v1::ZwpLockedPointerV1,
+    zwp_pointer_constraints_v1::{Lifetime, ZwpPointerConstraintsV1},
+};
 
-use smithay_client_toolkit::reexports::client::protocol::{
-    wl_pointer::{self, Event as PtrEvent, WlPointer},
-    wl_seat,
+use smithay_client_toolkit::reexports::protocols::unstable::pointer_gestures::v1::client::{
+    zwp_pointer_gesture_pinch_v1::{Event as PinchEvent, ZwpPointerGesturePinchV1},
+    zwp_pointer_gesture_swipe_v1::{Event as SwipeEvent, ZwpPointerGestureSwipeV1},
+    zwp_pointer_gestures_v1::ZwpPointerGesturesV1,
 };
 
 use smithay_client_toolkit::reexports::protocols::unstable::relative_pointer::v1::client::{
@@ -22,12 +48,16 @@ pub fn implement_pointer<T: 'static>(
     sink: Arc<Mutex<WindowEventsSink<T>>>,
     store: Arc<Mutex<WindowStore>>,
     modifiers_tracker: Arc<Mutex<ModifiersState>>,
+    pressed_button: Arc<Mutex<Option<(WindowId, u32)>>>,
+    cursor_manager: Arc<Mutex<CursorManager>>,
 ) -> WlPointer {
     seat.get_pointer(|pointer| {
         let mut mouse_focus = None;
         let mut axis_buffer = None;
         let mut axis_discrete_buffer = None;
         let mut axis_state = TouchPhase::Ended;
+        let mut axis_source = None;
+        let mut axis_stopped = false;
 
         pointer.implement_closure(
             move |evt, pointer| {
@@ -35,14 +65,18 @@ pub fn implement_pointer<T: 'static>(
                 let store = store.lock().unwrap();
                 match evt {
                     PtrEvent::Enter {
+                        serial,
                         surface,
                         surface_x,
                         surface_y,
-                        ..
                     } => {
                         let wid = store.find_wid(&surface);
                         if let Some(wid) = wid {
                             mouse_focus = Some(wid);
+                            cursor_manager
+                                .lock()
+                                .unwrap()
+                                .pointer_entered(&pointer, wid, serial);
                             sink.send_window_event(
                                 WindowEvent::CursorEntered {
                                     device_id: crate::event::DeviceId(
@@ -65,6 +99,8 @@ pub fn implement_pointer<T: 'static>(
                     }
                     PtrEvent::Leave { surface, .. } => {
                         mouse_focus = None;
+                        *pressed_button.lock().unwrap() = None;
+                        cursor_manager.lock().unwrap().pointer_left(&pointer);
                         let wid = store.find_wid(&surface);
                         if let Some(wid) = wid {
                             sink.send_window_event(
@@ -95,13 +131,23 @@ pub fn implement_pointer<T: 'static>(
                             );
                         }
                     }
-                    PtrEvent::Button { button, state, .. } => {
+                    PtrEvent::Button {
+                        serial,
+                        button,
+                        state,
+                        ..
+                    } => {
                         if let Some(wid) = mouse_focus {
                             let state = match state {
                                 wl_pointer::ButtonState::Pressed => ElementState::Pressed,
                                 wl_pointer::ButtonState::Released => ElementState::Released,
                                 _ => unreachable!(),
                             };
+                            // Drags can only be started while a button is held down.
+                            *pressed_button.lock().unwrap() = match state {
+                                ElementState::Pressed => Some((wid, serial)),
+                                ElementState::Released => None,
+                            };
                             let button = match button {
                                 0x110 => MouseButton::Left,
                                 0x111 => MouseButton::Right,
@@ -129,8 +175,8 @@ pub fn implement_pointer<T: 'static>(
                                 // old seat compatibility
                                 match axis {
                                     // wayland vertical sign convention is the inverse of winit
-                                    wl_pointer::Axis::VerticalScroll => y -= value as f32,
-                                    wl_pointer::Axis::HorizontalScroll => x += value as f32,
+                                    wl_pointer::Axis::VerticalScroll => y -= value,
+                                    wl_pointer::Axis::HorizontalScroll => x += value,
                                     _ => unreachable!(),
                                 }
                                 sink.send_window_event(
@@ -138,20 +184,31 @@ pub fn implement_pointer<T: 'static>(
                                         device_id: crate::event::DeviceId(
                                             crate::platform_impl::DeviceId::Wayland(DeviceId),
                                         ),
-                                        delta: MouseScrollDelta::PixelDelta(
-                                            (x as f64, y as f64).into(),
-                                        ),
+                                        delta: MouseScrollDelta::PixelDelta((x, y).into()),
                                         phase: TouchPhase::Moved,
                                         modifiers: modifiers_tracker.lock().unwrap().clone(),
                                     },
                                     wid,
                                 );
+                                sink.send_window_event(
+                                    WindowEvent::WheelInput {
+                                        device_id: crate::event::DeviceId(
+                                            crate::platform_impl::DeviceId::Wayland(DeviceId),
+                                        ),
+                                        detents: (0, 0),
+                                        delta: WheelDelta::PixelDelta((x, y).into()),
+                                        source: None,
+                                        phase: TouchPhase::Moved,
+                                        modifiers: *modifiers_tracker.lock().unwrap(),
+                                    },
+                                    wid,
+                                );
                             } else {
                                 let (mut x, mut y) = axis_buffer.unwrap_or((0.0, 0.0));
                                 match axis {
                                     // wayland vertical sign convention is the inverse of winit
-                                    wl_pointer::Axis::VerticalScroll => y -= value as f32,
-                                    wl_pointer::Axis::HorizontalScroll => x += value as f32,
+                                    wl_pointer::Axis::VerticalScroll => y -= value,
+                                    wl_pointer::Axis::HorizontalScroll => x += value,
                                     _ => unreachable!(),
                                 }
                                 axis_buffer = Some((x, y));
@@ -165,6 +222,7 @@ pub fn implement_pointer<T: 'static>(
                     PtrEvent::Frame => {
                         let axis_buffer = axis_buffer.take();
                         let axis_discrete_buffer = axis_discrete_buffer.take();
+                        let stopped = std::mem::replace(&mut axis_stopped, false);
                         if let Some(wid) = mouse_focus {
                             if let Some((x, y)) = axis_discrete_buffer {
                                 sink.send_window_event(
@@ -184,20 +242,47 @@ pub fn implement_pointer<T: 'static>(
                                         device_id: crate::event::DeviceId(
                                             crate::platform_impl::DeviceId::Wayland(DeviceId),
                                         ),
-                                        delta: MouseScrollDelta::PixelDelta(
-                                            (x as f64, y as f64).into(),
-                                        ),
+                                        delta: MouseScrollDelta::PixelDelta((x, y).into()),
                                         phase: axis_state,
                                         modifiers: modifiers_tracker.lock().unwrap().clone(),
                                     },
                                     wid,
                                 );
                             }
+                            // The frame of a stop carries no motion, only the end of the sequence.
+                            if axis_buffer.is_some() || axis_discrete_buffer.is_some() || stopped {
+                                sink.send_window_event(
+                                    WindowEvent::WheelInput {
+                                        device_id: crate::event::DeviceId(
+                                            crate::platform_impl::DeviceId::Wayland(DeviceId),
+                                        ),
+                                        detents: axis_discrete_buffer.unwrap_or((0, 0)),
+                                        delta: WheelDelta::PixelDelta(
+                                            axis_buffer.unwrap_or((0.0, 0.0)).into(),
+                                        ),
+                                        source: axis_source,
+                                        phase: axis_state,
+                                        modifiers: *modifiers_tracker.lock().unwrap(),
+                                    },
+                                    wid,
+                                );
+                            }
                         }
                     }
-                    PtrEvent::AxisSource { .. } => (),
+                    PtrEvent::AxisSource {
+                        axis_source: source,
+                    } => {
+                        axis_source = match source {
+                            wl_pointer::AxisSource::Wheel => Some(ScrollSource::Wheel),
+                            wl_pointer::AxisSource::Finger => Some(ScrollSource::Finger),
+                            wl_pointer::AxisSource::Continuous => Some(ScrollSource::Continuous),
+                            wl_pointer::AxisSource::WheelTilt => Some(ScrollSource::WheelTilt),
+                            _ => None,
+                        };
+                    }
                     PtrEvent::AxisStop { .. } => {
                         axis_state = TouchPhase::Ended;
+                        axis_stopped = true;
                     }
                     PtrEvent::AxisDiscrete { axis, discrete } => {
                         let (mut x, mut y) = axis_discrete_buffer.unwrap_or((0, 0));
@@ -241,3 +326,278 @@ pub fn implement_relative_pointer<T: 'static>(
         )
     })
 }
+
+fn gesture_phase(cancelled: i32) -> TouchPhase {
+    if cancelled != 0 {
+        TouchPhase::Cancelled
+    } else {
+        TouchPhase::Ended
+    }
+}
+
+pub fn implement_swipe_gesture<T: 'static>(
+    sink: Arc<Mutex<WindowEventsSink<T>>>,
+    store: Arc<Mutex<WindowStore>>,
+    pointer: &WlPointer,
+    manager: &ZwpPointerGesturesV1,
+) -> Result<ZwpPointerGestureSwipeV1, ()> {
+    manager.get_swipe_gesture(pointer, |swipe| {
+        // The window and the number of fingers of the gesture in progress.
+        let mut gesture = None;
+        swipe.implement_closure(
+            move |evt, _swipe| {
+                let mut sink = sink.lock().unwrap();
+                let (phase, delta) = match evt {
+                    SwipeEvent::Begin {
+                        surface, fingers, ..
+                    } => {
+                        gesture = store
+                            .lock()
+                            .unwrap()
+                            .find_wid(&surface)
+                            .map(|wid| (wid, fingers));
+                        (TouchPhase::Started, (0.0, 0.0))
+                    }
+                    SwipeEvent::Update { dx, dy, .. } => (TouchPhase::Moved, (dx, dy)),
+                    SwipeEvent::End { cancelled, .. } => (gesture_phase(cancelled), (0.0, 0.0)),
+                    _ => unreachable!(),
+                };
+                if let Some((wid, fingers)) = gesture {
+                    sink.send_window_event(
+                        WindowEvent::SwipeGesture {
+                            device_id: crate::event::DeviceId(
+                                crate::platform_impl::DeviceId::Wayland(DeviceId),
+                            ),
+                            fingers,
+                            delta: delta.into(),
+                            phase,
+                        },
+                        wid,
+                    );
+                }
+            },
+            (),
+        )
+    })
+}
+
+pub fn implement_pinch_gesture<T: 'static>(
+    sink: Arc<Mutex<WindowEventsSink<T>>>,
+    store: Arc<Mutex<WindowStore>>,
+    pointer: &WlPointer,
+    manager: &ZwpPointerGesturesV1,
+) -> Result<ZwpPointerGesturePinchV1, ()> {
+    manager.get_pinch_gesture(pointer, |pinch| {
+        let mut focus = None;
+        // The compositor sends the scale relative to the beginning of the gesture, while the
+        // rotation is relative to the last event.
+        let mut last_scale = 1.0;
+        pinch.implement_closure(
+            move |evt, _pinch| {
+                let mut sink = sink.lock().unwrap();
+                let (phase, scale, rotation) = match evt {
+                    PinchEvent::Begin { surface, .. } => {
+                        focus = store.lock().unwrap().find_wid(&surface);
+                        last_scale = 1.0;
+                        (TouchPhase::Started, 1.0, 0.0)
+                    }
+                    PinchEvent::Update {
+                        scale, rotation, ..
+                    } => (TouchPhase::Moved, scale, rotation),
+                    PinchEvent::End { cancelled, .. } => {
+                        (gesture_phase(cancelled), last_scale, 0.0)
+                    }
+                    _ => unreachable!(),
+                };
+                if let Some(wid) = focus {
+                    let device_id =
+                        crate::event::DeviceId(crate::platform_impl::DeviceId::Wayland(DeviceId));
+                    sink.send_window_event(
+                        WindowEvent::PinchGesture {
+                            device_id,
+                            delta: scale - last_scale,
+                            phase,
+                        },
+                        wid,
+                    );
+                    sink.send_window_event(
+                        WindowEvent::RotationGesture {
+                            device_id,
+                            delta: rotation,
+                            phase,
+                        },
+                        wid,
+                    );
+                }
+                last_scale = scale;
+            },
+            (),
+        )
+    })
+}
+
+/// Locks `pointer` in place while it's over `surface`, its motion then only being reported
+/// through the relative pointer.
+pub fn implement_locked_pointer(
+    manager: &ZwpPointerConstraintsV1,
+    pointer: &WlPointer,
+    surface: &WlSurface,
+) -> Result<ZwpLockedPointerV1, ()> {
+    manager.lock_pointer(
+        surface,
+        pointer,
+        None,
+        Lifetime::Persistent.to_raw(),
+        NewProxy::implement_dummy,
+    )
+}
+
+/// Confines `pointer` to `surface` while it's over it.
+pub fn implement_confined_pointer(
+    manager: &ZwpPointerConstraintsV1,
+    pointer: &WlPointer,
+    surface: &WlSurface,
+) -> Result<ZwpConfinedPointerV1, ()> {
+    manager.confine_pointer(
+        surface,
+        pointer,
+        None,
+        Lifetime::Persistent.to_raw(),
+        NewProxy::implement_dummy,
+    )
+}
+
+/// The cursor grabs of the windows, applied to the pointers of all seats through the pointer
+/// constraints protocol.
+pub struct CursorGrabs {
+    manager: Option<ZwpPointerConstraintsV1>,
+    pointers: Vec<WlPointer>,
+    grabs: HashMap<WindowId, CursorGrab>,
+}
+
+struct CursorGrab {
+    surface: WlSurface,
+    mode: CursorGrabMode,
+    constraints: Vec<(WlPointer, PointerConstraint)>,
+}
+
+enum PointerConstraint {
+    Confined(ZwpConfinedPointerV1),
+    Locked(ZwpLockedPointerV1),
+}
+
+impl PointerConstraint {
+    fn destroy(&self) {
+        match *self {
+            PointerConstraint::Confined(ref confined_pointer) => confined_pointer.destroy(),
+            PointerConstraint::Locked(ref locked_pointer) => locked_pointer.destroy(),
+        }
+    }
+}
+
+impl CursorGrabs {
+    pub fn new() -> CursorGrabs {
+        CursorGrabs {
+            manager: None,
+            pointers: Vec::new(),
+            grabs: HashMap::new(),
+        }
+    }
+
+    pub fn set_manager(&mut self, manager: ZwpPointerConstraintsV1) {
+        self.manager = Some(manager);
+    }
+
+    pub fn add_pointer(&mut self, pointer: &WlPointer) {
+        self.pointers.push(pointer.clone());
+        if let Some(ref manager) = self.manager {
+            for grab in self.grabs.values_mut() {
+                if let Some(constraint) = constrain_pointer(manager, pointer, grab) {
+                    grab.constraints.push((pointer.clone(), constraint));
+                }
+            }
+        }
+    }
+
+    pub fn remove_pointer(&mut self, pointer: &WlPointer) {
+        self.pointers
+            .retain(|other| !other.as_ref().equals(pointer.as_ref()));
+        for grab in self.grabs.values_mut() {
+            grab.constraints.retain(|(other, constraint)| {
+                if other.as_ref().equals(pointer.as_ref()) {
+                    constraint.destroy();
+                    false
+                } else {
+                    true
+                }
+            });
+        }
+    }
+
+    pub fn set_grab(
+        &mut self,
+        wid: WindowId,
+        surface: &WlSurface,
+        mode: CursorGrabMode,
+    ) -> Result<(), ExternalError> {
+        if self.grabs.get(&wid).map(|grab| grab.mode) == Some(mode) {
+            return Ok(());
+        }
+        // A pointer can only have a single constraint on a surface, so the previous one goes
+        // first.
+        self.remove_window(wid);
+        if mode == CursorGrabMode::None {
+            return Ok(());
+        }
+        let manager = match self.manager {
+            Some(ref manager) => manager,
+            None => return Err(ExternalError::NotSupported(NotSupportedError::new())),
+        };
+        let mut grab = CursorGrab {
+            surface: surface.clone(),
+            mode,
+            constraints: Vec::new(),
+        };
+        for pointer in &self.pointers {
+            match constrain_pointer(manager, pointer, &grab) {
+                Some(constraint) => grab.constraints.push((pointer.clone(), constraint)),
+                None => {
+                    for (_, constraint) in &grab.constraints {
+                        constraint.destroy();
+                    }
+                    return Err(ExternalError::Os(os_error!(OsError::WaylandMisc(
+                        "Failed to constrain the pointer"
+                    ))));
+                }
+            }
+        }
+        self.grabs.insert(wid, grab);
+        Ok(())
+    }
+
+    pub fn remove_window(&mut self, wid: WindowId) {
+        if let Some(grab) = self.grabs.remove(&wid) {
+            for (_, constraint) in &grab.constraints {
+                constraint.destroy();
+            }
+        }
+    }
+}
+
+// Constrains `pointer` whenever it's over the surface of `grab`, the constraint persisting after
+// the pointer leaves it.
+fn constrain_pointer(
+    manager: &ZwpPointerConstraintsV1,
+    pointer: &WlPointer,
+    grab: &CursorGrab,
+) -> Option<PointerConstraint> {
+    match grab.mode {
+        CursorGrabMode::None => None,
+        CursorGrabMode::Confined => implement_confined_pointer(manager, pointer, &grab.surface)
+            .ok()
+            .map(PointerConstraint::Confined),
+        CursorGrabMode::Locked => implement_locked_pointer(manager, pointer, &grab.surface)
+            .ok()
+            .map(PointerConstraint::Locked),
+    }
+}
@@ -0,0 +1,258 @@
+use smithay_client_toolkit::reexports::{
+    client::{protocol::wl_seat, NewProxy},
+    protocols::unstable::tablet::v2::client::{
+        zwp_tablet_manager_v2::ZwpTabletManagerV2,
+        zwp_tablet_pad_group_v2,
+        zwp_tablet_pad_v2::{self, ZwpTabletPadV2},
+        zwp_tablet_seat_v2::{self, ZwpTabletSeatV2},
+        zwp_tablet_tool_v2::{self, ButtonState, Capability, Type, ZwpTabletToolV2},
+        zwp_tablet_v2::{self, ZwpTabletV2},
+    },
+};
+
+use crate::event::{ElementState, TabletTool, TabletToolEvent, TabletToolState, WindowEvent};
+
+use super::{data_device::WindowEventSender, make_wid, DeviceId, WindowId};
+
+// The pressure and distance are reported from 0 to 65535.
+const AXIS_MAX: f64 = 65535.0;
+
+// A tool of a tablet, whose events are sent once their frame is complete.
+struct Tool {
+    tool: TabletTool,
+    state: TabletToolState,
+    // The window the tool is over.
+    focus: Option<WindowId>,
+    // `None` stands for a `Moved` event, taking the state of the tool at the end of the frame.
+    frame: Vec<Option<TabletToolEvent>>,
+    window_event_sender: WindowEventSender,
+}
+
+impl Tool {
+    fn new(window_event_sender: WindowEventSender) -> Tool {
+        Tool {
+            tool: TabletTool::Unknown,
+            state: TabletToolState {
+                location: (0.0, 0.0).into(),
+                pressure: None,
+                distance: None,
+                tilt: None,
+                rotation: None,
+            },
+            focus: None,
+            frame: Vec::new(),
+            window_event_sender,
+        }
+    }
+
+    fn moved(&mut self) {
+        if !self.frame.contains(&None) {
+            self.frame.push(None);
+        }
+    }
+
+    fn event(&mut self, event: zwp_tablet_tool_v2::Event, tool: ZwpTabletToolV2) {
+        use self::zwp_tablet_tool_v2::Event;
+        match event {
+            Event::Type { tool_type } => {
+                self.tool = match tool_type {
+                    Type::Pen => TabletTool::Pen,
+                    Type::Eraser => TabletTool::Eraser,
+                    Type::Brush => TabletTool::Brush,
+                    Type::Pencil => TabletTool::Pencil,
+                    Type::Airbrush => TabletTool::Airbrush,
+                    Type::Finger => TabletTool::Finger,
+                    Type::Mouse => TabletTool::Mouse,
+                    Type::Lens => TabletTool::Lens,
+                    _ => TabletTool::Unknown,
+                }
+            }
+            // The axes the tool doesn't have stay `None`.
+            Event::Capability { capability } => match capability {
+                Capability::Tilt => self.state.tilt = Some((0.0, 0.0)),
+                Capability::Pressure => self.state.pressure = Some(0.0),
+                Capability::Distance => self.state.distance = Some(0.0),
+                Capability::Rotation => self.state.rotation = Some(0.0),
+                _ => (),
+            },
+            Event::Removed => tool.destroy(),
+            Event::ProximityIn { surface, .. } => {
+                self.focus = Some(make_wid(&surface));
+                self.frame
+                    .push(Some(TabletToolEvent::ProximityIn(self.tool)));
+            }
+            Event::ProximityOut => self.frame.push(Some(TabletToolEvent::ProximityOut)),
+            Event::Down { .. } => self.frame.push(Some(TabletToolEvent::Down)),
+            Event::Up => self.frame.push(Some(TabletToolEvent::Up)),
+            Event::Motion { x, y } => {
+                self.state.location = (x, y).into();
+                self.moved();
+            }
+            Event::Pressure { pressure } => {
+                self.state.pressure = Some(f64::from(pressure) / AXIS_MAX);
+                self.moved();
+            }
+            Event::Distance { distance } => {
+                self.state.distance = Some(f64::from(distance) / AXIS_MAX);
+                self.moved();
+            }
+            Event::Tilt { tilt_x, tilt_y } => {
+                self.state.tilt = Some((tilt_x, tilt_y));
+                self.moved();
+            }
+            Event::Rotation { degrees } => {
+                self.state.rotation = Some(degrees.rem_euclid(360.0));
+                self.moved();
+            }
+            Event::Button { button, state, .. } => {
+                let state = match state {
+                    ButtonState::Pressed => ElementState::Pressed,
+                    _ => ElementState::Released,
+                };
+                self.frame
+                    .push(Some(TabletToolEvent::Button { button, state }));
+            }
+            Event::Frame { .. } => {
+                let wid = match self.focus {
+                    Some(wid) => wid,
+                    None => {
+                        self.frame.clear();
+                        return;
+                    }
+                };
+                let device_id =
+                    crate::event::DeviceId(crate::platform_impl::DeviceId::Wayland(DeviceId));
+                for event in self.frame.drain(..) {
+                    let event = event.unwrap_or(TabletToolEvent::Moved(self.state));
+                    if event == TabletToolEvent::ProximityOut {
+                        self.focus = None;
+                    }
+                    let _ = self
+                        .window_event_sender
+                        .send((WindowEvent::TabletTool { device_id, event }, wid));
+                }
+            }
+            _ => (),
+        }
+    }
+}
+
+fn implement_tablet(tablet: NewProxy<ZwpTabletV2>) {
+    tablet.implement_closure(
+        |event, tablet| {
+            if let zwp_tablet_v2::Event::Removed = event {
+                tablet.destroy();
+            }
+        },
+        (),
+    );
+}
+
+// The pads are only implemented for their objects to be released, as their buttons, rings and
+// strips aren't reported.
+fn implement_pad(pad: NewProxy<ZwpTabletPadV2>) {
+    pad.implement_closure(
+        |event, pad| match event {
+            zwp_tablet_pad_v2::Event::Group { pad_group } => {
+                pad_group.implement_closure(
+                    |event, _| match event {
+                        zwp_tablet_pad_group_v2::Event::Ring { ring } => {
+                            ring.implement_dummy();
+                        }
+                        zwp_tablet_pad_group_v2::Event::Strip { strip } => {
+                            strip.implement_dummy();
+                        }
+                        _ => (),
+                    },
+                    (),
+                );
+            }
+            zwp_tablet_pad_v2::Event::Removed => pad.destroy(),
+            _ => (),
+        },
+        (),
+    );
+}
+
+fn init_tablet_seat(
+    manager: &ZwpTabletManagerV2,
+    seat: &wl_seat::WlSeat,
+    window_event_sender: WindowEventSender,
+) -> ZwpTabletSeatV2 {
+    manager
+        .get_tablet_seat(seat, move |tablet_seat| {
+            tablet_seat.implement_closure(
+                move |event, _| match event {
+                    zwp_tablet_seat_v2::Event::TabletAdded { id } => implement_tablet(id),
+                    zwp_tablet_seat_v2::Event::ToolAdded { id } => {
+                        let mut tool = Tool::new(window_event_sender.clone());
+                        id.implement_closure(move |event, proxy| tool.event(event, proxy), ());
+                    }
+                    zwp_tablet_seat_v2::Event::PadAdded { id } => implement_pad(id),
+                    _ => (),
+                },
+                (),
+            )
+        })
+        .unwrap()
+}
+
+struct SeatTablets {
+    id: u32,
+    seat: wl_seat::WlSeat,
+    tablet_seat: Option<ZwpTabletSeatV2>,
+}
+
+/// The tablets of all seats, whose tools are reported with `WindowEvent::TabletTool`.
+pub struct Tablets {
+    manager: Option<ZwpTabletManagerV2>,
+    seats: Vec<SeatTablets>,
+    window_event_sender: WindowEventSender,
+}
+
+impl Tablets {
+    pub fn new(window_event_sender: WindowEventSender) -> Tablets {
+        Tablets {
+            manager: None,
+            seats: Vec::new(),
+            window_event_sender,
+        }
+    }
+
+    // The manager and the seats can be advertised in any order, so the tablet seats are created
+    // by whichever of the two comes last.
+    pub fn set_manager(&mut self, manager: ZwpTabletManagerV2) {
+        for seat in &mut self.seats {
+            seat.tablet_seat = Some(init_tablet_seat(
+                &manager,
+                &seat.seat,
+                self.window_event_sender.clone(),
+            ));
+        }
+        self.manager = Some(manager);
+    }
+
+    pub fn add_seat(&mut self, id: u32, seat: &wl_seat::WlSeat) {
+        let tablet_seat = self
+            .manager
+            .as_ref()
+            .map(|manager| init_tablet_seat(manager, seat, self.window_event_sender.clone()));
+        self.seats.push(SeatTablets {
+            id,
+            seat: seat.clone(),
+            tablet_seat,
+        });
+    }
+
+    pub fn remove_seat(&mut self, id: u32) {
+        self.seats.retain(|seat| {
+            if seat.id != id {
+                return true;
+            }
+            if let Some(ref tablet_seat) = seat.tablet_seat {
+                tablet_seat.destroy();
+            }
+            false
+        });
+    }
+}
@@ -0,0 +1,290 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
+
+use smithay_client_toolkit::reexports::{
+    client::protocol::wl_seat,
+    protocols::unstable::text_input::v3::client::{
+        zwp_text_input_manager_v3::ZwpTextInputManagerV3,
+        zwp_text_input_v3::{self, ContentHint, ContentPurpose, ZwpTextInputV3},
+    },
+};
+
+use crate::{
+    event::{Ime, WindowEvent},
+    window::ImePurpose,
+};
+
+use super::{data_device::WindowEventSender, make_wid, WindowId};
+
+// The input method settings of a window.
+#[derive(Debug, Clone, Copy)]
+struct WindowIme {
+    // The area of the text cursor, in surface coordinates.
+    cursor_area: Option<(i32, i32, i32, i32)>,
+    allowed: bool,
+    purpose: ImePurpose,
+}
+
+const DEFAULT_WINDOW_IME: WindowIme = WindowIme {
+    cursor_area: None,
+    allowed: true,
+    purpose: ImePurpose::Normal,
+};
+
+// Windows without an entry have the default settings.
+type WindowImes = Arc<Mutex<HashMap<WindowId, WindowIme>>>;
+
+fn window_ime(imes: &WindowImes, wid: WindowId) -> WindowIme {
+    imes.lock()
+        .unwrap()
+        .get(&wid)
+        .cloned()
+        .unwrap_or(DEFAULT_WINDOW_IME)
+}
+
+fn content_type(purpose: ImePurpose) -> (ContentHint, ContentPurpose) {
+    match purpose {
+        ImePurpose::Normal => (ContentHint::None, ContentPurpose::Normal),
+        ImePurpose::Password => (
+            ContentHint::HiddenText | ContentHint::SensitiveData,
+            ContentPurpose::Password,
+        ),
+        ImePurpose::Email => (ContentHint::None, ContentPurpose::Email),
+        ImePurpose::Number => (ContentHint::None, ContentPurpose::Number),
+        ImePurpose::Phone => (ContentHint::None, ContentPurpose::Phone),
+        ImePurpose::Url => (ContentHint::None, ContentPurpose::Url),
+        ImePurpose::Terminal => (ContentHint::None, ContentPurpose::Terminal),
+    }
+}
+
+fn enable(text_input: &ZwpTextInputV3, ime: &WindowIme) {
+    text_input.enable();
+    update_state(text_input, ime);
+}
+
+// Enabling a text input resets its state, which is otherwise updated as is, not to disturb the
+// text being composed.
+fn update_state(text_input: &ZwpTextInputV3, ime: &WindowIme) {
+    let (hint, purpose) = content_type(ime.purpose);
+    text_input.set_content_type(hint, purpose);
+    if let Some((x, y, width, height)) = ime.cursor_area {
+        text_input.set_cursor_rectangle(x, y, width, height);
+    }
+    text_input.commit();
+}
+
+fn disable(text_input: &ZwpTextInputV3) {
+    text_input.disable();
+    text_input.commit();
+}
+
+// The window a text input is focused on, and whether it's enabled there.
+type TextInputFocus = Arc<Mutex<Option<(WindowId, bool)>>>;
+
+struct TextInput {
+    text_input: ZwpTextInputV3,
+    // Follows the keyboard focus of the seat.
+    focus: TextInputFocus,
+}
+
+impl TextInput {
+    fn init_for_seat(
+        manager: &ZwpTextInputManagerV3,
+        seat: &wl_seat::WlSeat,
+        imes: WindowImes,
+        window_event_sender: WindowEventSender,
+    ) -> TextInput {
+        let focus = Arc::new(Mutex::new(None));
+        let text_input_focus = focus.clone();
+        // The preedit and commit strings are only applied once the `done` event is received.
+        let mut pending_preedit = None;
+        let mut pending_commit = None;
+        let text_input = manager
+            .get_text_input(seat, move |text_input| {
+                text_input.implement_closure(
+                    move |event, text_input| {
+                        let mut focus = text_input_focus.lock().unwrap();
+                        let send = |wid, event| {
+                            let _ = window_event_sender.send((WindowEvent::Ime(event), wid));
+                        };
+                        match event {
+                            zwp_text_input_v3::Event::Enter { surface } => {
+                                let wid = make_wid(&surface);
+                                let ime = window_ime(&imes, wid);
+                                *focus = Some((wid, ime.allowed));
+                                if ime.allowed {
+                                    enable(&text_input, &ime);
+                                    send(wid, Ime::Enabled);
+                                }
+                            }
+                            zwp_text_input_v3::Event::Leave { .. } => {
+                                if let Some((wid, true)) = focus.take() {
+                                    disable(&text_input);
+                                    send(wid, Ime::Disabled);
+                                }
+                            }
+                            zwp_text_input_v3::Event::PreeditString {
+                                text,
+                                cursor_begin,
+                                cursor_end,
+                            } => {
+                                // The cursor is hidden when its bounds are -1.
+                                let cursor = if cursor_begin < 0 || cursor_end < 0 {
+                                    None
+                                } else {
+                                    Some((cursor_begin as usize, cursor_end as usize))
+                                };
+                                pending_preedit = Some((text.unwrap_or_default(), cursor));
+                            }
+                            zwp_text_input_v3::Event::CommitString { text } => {
+                                pending_commit = text;
+                            }
+                            zwp_text_input_v3::Event::Done { .. } => {
+                                let wid = match *focus {
+                                    Some((wid, true)) => wid,
+                                    _ => return,
+                                };
+                                // The text being composed is removed before the commit.
+                                if let Some(text) = pending_commit.take() {
+                                    send(wid, Ime::Preedit(String::new(), None));
+                                    send(wid, Ime::Commit(text));
+                                }
+                                if let Some((text, cursor)) = pending_preedit.take() {
+                                    let cursor = if text.is_empty() { None } else { cursor };
+                                    send(wid, Ime::Preedit(text, cursor));
+                                }
+                            }
+                            _ => (),
+                        }
+                    },
+                    (),
+                )
+            })
+            .unwrap();
+        TextInput { text_input, focus }
+    }
+}
+
+impl Drop for TextInput {
+    fn drop(&mut self) {
+        self.text_input.destroy();
+    }
+}
+
+struct SeatTextInput {
+    id: u32,
+    seat: wl_seat::WlSeat,
+    text_input: Option<TextInput>,
+}
+
+/// The text inputs of all seats, through which input methods compose text in our windows.
+pub struct TextInputs {
+    manager: Option<ZwpTextInputManagerV3>,
+    seats: Vec<SeatTextInput>,
+    imes: WindowImes,
+    window_event_sender: WindowEventSender,
+}
+
+impl TextInputs {
+    pub fn new(window_event_sender: WindowEventSender) -> TextInputs {
+        TextInputs {
+            manager: None,
+            seats: Vec::new(),
+            imes: Arc::new(Mutex::new(HashMap::new())),
+            window_event_sender,
+        }
+    }
+
+    // The manager and the seats can be advertised in any order, so text inputs are created by
+    // whichever of the two comes last.
+    pub fn set_manager(&mut self, manager: ZwpTextInputManagerV3) {
+        for seat in &mut self.seats {
+            seat.text_input = Some(TextInput::init_for_seat(
+                &manager,
+                &seat.seat,
+                self.imes.clone(),
+                self.window_event_sender.clone(),
+            ));
+        }
+        self.manager = Some(manager);
+    }
+
+    pub fn add_seat(&mut self, id: u32, seat: &wl_seat::WlSeat) {
+        let text_input = self.manager.as_ref().map(|manager| {
+            TextInput::init_for_seat(
+                manager,
+                seat,
+                self.imes.clone(),
+                self.window_event_sender.clone(),
+            )
+        });
+        self.seats.push(SeatTextInput {
+            id,
+            seat: seat.clone(),
+            text_input,
+        });
+    }
+
+    pub fn remove_seat(&mut self, id: u32) {
+        self.seats.retain(|seat| seat.id != id);
+    }
+
+    // Changes the settings of `wid`, and applies them to the text inputs focused on it, along
+    // with whether they are enabled there.
+    fn update<F>(&self, wid: WindowId, update: F)
+    where
+        F: FnOnce(&mut WindowIme),
+    {
+        let ime = {
+            let mut imes = self.imes.lock().unwrap();
+            let ime = imes.entry(wid).or_insert(DEFAULT_WINDOW_IME);
+            update(ime);
+            *ime
+        };
+        for text_input in self
+            .seats
+            .iter()
+            .filter_map(|seat| seat.text_input.as_ref())
+        {
+            let mut focus = text_input.focus.lock().unwrap();
+            let enabled = match *focus {
+                Some((focused, ref mut enabled)) if focused == wid => enabled,
+                _ => continue,
+            };
+            if ime.allowed && *enabled {
+                update_state(&text_input.text_input, &ime);
+            } else if ime.allowed {
+                enable(&text_input.text_input, &ime);
+                let _ = self
+                    .window_event_sender
+                    .send((WindowEvent::Ime(Ime::Enabled), wid));
+            } else if *enabled {
+                disable(&text_input.text_input);
+                let _ = self
+                    .window_event_sender
+                    .send((WindowEvent::Ime(Ime::Disabled), wid));
+            }
+            *enabled = ime.allowed;
+        }
+    }
+
+    /// Sets the area of the text cursor of `wid`, which the IME candidate window is placed next
+    /// to.
+    pub fn set_ime_cursor_area(&self, wid: WindowId, area: (i32, i32, i32, i32)) {
+        self.update(wid, |ime| ime.cursor_area = Some(area));
+    }
+
+    pub fn set_ime_allowed(&self, wid: WindowId, allowed: bool) {
+        self.update(wid, |ime| ime.allowed = allowed);
+    }
+
+    pub fn set_ime_purpose(&self, wid: WindowId, purpose: ImePurpose) {
+        self.update(wid, |ime| ime.purpose = purpose);
+    }
+
+    pub fn remove_window(&mut self, wid: WindowId) {
+        self.imes.lock().unwrap().remove(&wid);
+    }
+}
@@ -1,6 +1,6 @@
 use std::sync::{Arc, Mutex};
 
-use crate::event::{TouchPhase, WindowEvent};
+use crate::event::{TouchContact, TouchPhase, WindowEvent};
 
 use super::{event_loop::WindowEventsSink, window::WindowStore, DeviceId, WindowId};
 
@@ -12,15 +12,38 @@ use smithay_client_toolkit::reexports::client::protocol::{
 struct TouchPoint {
     wid: WindowId,
     location: (f64, f64),
+    // The lengths of the axes of the contact, if the compositor sent them.
+    shape: Option<(f64, f64)>,
+    orientation: f64,
     id: i32,
 }
 
+impl TouchPoint {
+    fn event(&self, phase: TouchPhase) -> WindowEvent {
+        WindowEvent::Touch(crate::event::Touch {
+            device_id: crate::event::DeviceId(crate::platform_impl::DeviceId::Wayland(DeviceId)),
+            phase,
+            location: self.location.into(),
+            force: None,
+            contact: self.shape.map(|(major, minor)| TouchContact {
+                major,
+                minor,
+                orientation: self.orientation,
+            }),
+            id: self.id as u64,
+        })
+    }
+}
+
 pub(crate) fn implement_touch<T: 'static>(
     seat: &wl_seat::WlSeat,
     sink: Arc<Mutex<WindowEventsSink<T>>>,
     store: Arc<Mutex<WindowStore>>,
 ) -> WlTouch {
-    let mut pending_ids = Vec::new();
+    let mut pending_ids: Vec<TouchPoint> = Vec::new();
+    // The changes of the touch points, sent once the frame is complete as the shape of a touch
+    // point comes after it's put down or moved.
+    let mut frame: Vec<(i32, TouchPhase)> = Vec::new();
     seat.get_touch(|touch| {
         touch.implement_closure(
             move |evt, _| {
@@ -32,72 +55,60 @@ pub(crate) fn implement_touch<T: 'static>(
                     } => {
                         let wid = store.find_wid(&surface);
                         if let Some(wid) = wid {
-                            sink.send_window_event(
-                                WindowEvent::Touch(crate::event::Touch {
-                                    device_id: crate::event::DeviceId(
-                                        crate::platform_impl::DeviceId::Wayland(DeviceId),
-                                    ),
-                                    phase: TouchPhase::Started,
-                                    location: (x, y).into(),
-                                    id: id as u64,
-                                }),
-                                wid,
-                            );
                             pending_ids.push(TouchPoint {
                                 wid,
                                 location: (x, y),
+                                shape: None,
+                                orientation: 0.0,
                                 id,
                             });
+                            frame.push((id, TouchPhase::Started));
                         }
                     }
                     TouchEvent::Up { id, .. } => {
-                        let idx = pending_ids.iter().position(|p| p.id == id);
-                        if let Some(idx) = idx {
-                            let pt = pending_ids.remove(idx);
-                            sink.send_window_event(
-                                WindowEvent::Touch(crate::event::Touch {
-                                    device_id: crate::event::DeviceId(
-                                        crate::platform_impl::DeviceId::Wayland(DeviceId),
-                                    ),
-                                    phase: TouchPhase::Ended,
-                                    location: pt.location.into(),
-                                    id: id as u64,
-                                }),
-                                pt.wid,
-                            );
+                        if pending_ids.iter().any(|p| p.id == id) {
+                            frame.push((id, TouchPhase::Ended));
                         }
                     }
                     TouchEvent::Motion { id, x, y, .. } => {
                         let pt = pending_ids.iter_mut().find(|p| p.id == id);
                         if let Some(pt) = pt {
                             pt.location = (x, y);
-                            sink.send_window_event(
-                                WindowEvent::Touch(crate::event::Touch {
-                                    device_id: crate::event::DeviceId(
-                                        crate::platform_impl::DeviceId::Wayland(DeviceId),
-                                    ),
-                                    phase: TouchPhase::Moved,
-                                    location: (x, y).into(),
-                                    id: id as u64,
-                                }),
-                                pt.wid,
-                            );
+                            if !frame.iter().any(|&(frame_id, _)| frame_id == id) {
+                                frame.push((id, TouchPhase::Moved));
+                            }
+                        }
+                    }
+                    TouchEvent::Shape { id, major, minor } => {
+                        let pt = pending_ids.iter_mut().find(|p| p.id == id);
+                        if let Some(pt) = pt {
+                            pt.shape = Some((major, minor));
+                        }
+                    }
+                    TouchEvent::Orientation { id, orientation } => {
+                        let pt = pending_ids.iter_mut().find(|p| p.id == id);
+                        if let Some(pt) = pt {
+                            pt.orientation = orientation;
+                        }
+                    }
+                    TouchEvent::Frame => {
+                        for (id, phase) in frame.drain(..) {
+                            let idx = pending_ids.iter().position(|p| p.id == id);
+                            if let Some(idx) = idx {
+                                sink.send_window_event(
+                                    pending_ids[idx].event(phase),
+                                    pending_ids[idx].wid,
+                                );
+                                if phase == TouchPhase::Ended {
+                                    pending_ids.remove(idx);
+                                }
+                            }
                         }
                     }
-                    TouchEvent::Frame => (),
                     TouchEvent::Cancel => {
+                        frame.clear();
                         for pt in pending_ids.drain(..) {
-                            sink.send_window_event(
-                                WindowEvent::Touch(crate::event::Touch {
-                                    device_id: crate::event::DeviceId(
-                                        crate::platform_impl::DeviceId::Wayland(DeviceId),
-                                    ),
-                                    phase: TouchPhase::Cancelled,
-                                    location: pt.location.into(),
-                                    id: pt.id as u64,
-                                }),
-                                pt.wid,
-                            );
+                            sink.send_window_event(pt.event(TouchPhase::Cancelled), pt.wid);
                         }
                     }
                     _ => unreachable!(),
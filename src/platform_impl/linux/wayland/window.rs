@@ -1,5 +1,5 @@
 use std::{
-    collections::VecDeque,
+    collections::{HashSet, VecDeque},
     sync::{Arc, Mutex, Weak},
 };
 
@@ -8,11 +8,16 @@ use crate::{
     error::{ExternalError, NotSupportedError, OsError as RootOsError},
     monitor::MonitorHandle as RootMonitorHandle,
     platform_impl::{
-        platform::wayland::event_loop::{available_monitors, primary_monitor},
+        platform::{
+            wayland::event_loop::{available_monitors, primary_monitor},
+            Cursor, OsError,
+        },
         MonitorHandle as PlatformMonitorHandle,
         PlatformSpecificWindowBuilderAttributes as PlAttributes,
     },
-    window::{CursorIcon, Fullscreen, WindowAttributes},
+    window::{
+        CursorGrabMode, CursorIcon, CustomCursor, Fullscreen, Icon, ImePurpose, WindowAttributes,
+    },
 };
 
 use smithay_client_toolkit::{
@@ -25,7 +30,10 @@ use smithay_client_toolkit::{
     window::{ConceptFrame, Event as WEvent, State as WState, Theme, Window as SWindow},
 };
 
-use super::{make_wid, EventLoopWindowTarget, MonitorHandle, WindowId};
+use super::{
+    dnd::DragRequest, make_wid, pointer::CursorGrabs, text_input::TextInputs,
+    EventLoopWindowTarget, MonitorHandle, WindowId,
+};
 
 pub struct Window {
     surface: wl_surface::WlSurface,
@@ -37,6 +45,11 @@ pub struct Window {
     need_frame_refresh: Arc<Mutex<bool>>,
     need_refresh: Arc<Mutex<bool>>,
     fullscreen: Arc<Mutex<bool>>,
+    drag_requests: Mutex<::calloop::channel::Sender<DragRequest>>,
+    cursor_requests: Mutex<::calloop::channel::Sender<(WindowId, Cursor)>>,
+    cursor_grabs: Arc<Mutex<CursorGrabs>>,
+    text_inputs: Arc<Mutex<TextInputs>>,
+    no_repeat_windows: Arc<Mutex<HashSet<WindowId>>>,
 }
 
 impl Window {
@@ -165,6 +178,11 @@ impl Window {
             need_frame_refresh,
             need_refresh,
             fullscreen,
+            drag_requests: Mutex::new(evlp.drag_requests.clone()),
+            cursor_requests: Mutex::new(evlp.cursor_requests.clone()),
+            cursor_grabs: evlp.cursor_grabs.clone(),
+            text_inputs: evlp.text_inputs.clone(),
+            no_repeat_windows: evlp.no_repeat_windows.clone(),
         })
     }
 
@@ -291,8 +309,21 @@ impl Window {
     }
 
     #[inline]
-    pub fn set_cursor_icon(&self, _cursor: CursorIcon) {
-        // TODO
+    pub fn set_cursor_icon(&self, cursor: CursorIcon) {
+        self.set_cursor(Cursor::Icon(cursor));
+    }
+
+    #[inline]
+    pub fn set_custom_cursor(&self, cursor: CustomCursor) {
+        self.set_cursor(Cursor::Custom(cursor));
+    }
+
+    fn set_cursor(&self, cursor: Cursor) {
+        let _ = self
+            .cursor_requests
+            .lock()
+            .unwrap()
+            .send((self.id(), cursor));
     }
 
     #[inline]
@@ -301,8 +332,11 @@ impl Window {
     }
 
     #[inline]
-    pub fn set_cursor_grab(&self, _grab: bool) -> Result<(), ExternalError> {
-        Err(ExternalError::NotSupported(NotSupportedError::new()))
+    pub fn set_cursor_grab(&self, mode: CursorGrabMode) -> Result<(), ExternalError> {
+        self.cursor_grabs
+            .lock()
+            .unwrap()
+            .set_grab(self.id(), &self.surface, mode)
     }
 
     #[inline]
@@ -310,6 +344,62 @@ impl Window {
         Err(ExternalError::NotSupported(NotSupportedError::new()))
     }
 
+    #[inline]
+    pub fn set_ime_cursor_area(&self, position: LogicalPosition, size: LogicalSize) {
+        let (x, y) = position.into();
+        let (width, height): (u32, u32) = size.into();
+        self.text_inputs
+            .lock()
+            .unwrap()
+            .set_ime_cursor_area(self.id(), (x, y, width as i32, height as i32));
+    }
+
+    #[inline]
+    pub fn set_ime_allowed(&self, allowed: bool) {
+        self.text_inputs
+            .lock()
+            .unwrap()
+            .set_ime_allowed(self.id(), allowed);
+    }
+
+    #[inline]
+    pub fn set_ime_purpose(&self, purpose: ImePurpose) {
+        self.text_inputs
+            .lock()
+            .unwrap()
+            .set_ime_purpose(self.id(), purpose);
+    }
+
+    #[inline]
+    pub fn set_key_repeat(&self, enabled: bool) {
+        let mut no_repeat_windows = self.no_repeat_windows.lock().unwrap();
+        if enabled {
+            no_repeat_windows.remove(&self.id());
+        } else {
+            no_repeat_windows.insert(self.id());
+        }
+    }
+
+    #[inline]
+    pub fn start_drag(
+        &self,
+        data: Vec<(String, Vec<u8>)>,
+        icon: Option<Icon>,
+    ) -> Result<(), ExternalError> {
+        let request = DragRequest {
+            surface: self.surface.clone(),
+            data,
+            icon,
+        };
+        self.drag_requests
+            .lock()
+            .unwrap()
+            .send(request)
+            .map_err(|_| {
+                ExternalError::Os(os_error!(OsError::WaylandMisc("The event loop is gone")))
+            })
+    }
+
     pub fn display(&self) -> &Display {
         &*self.display
     }
@@ -337,6 +427,9 @@ impl Window {
 
 impl Drop for Window {
     fn drop(&mut self) {
+        self.cursor_grabs.lock().unwrap().remove_window(self.id());
+        self.text_inputs.lock().unwrap().remove_window(self.id());
+        self.no_repeat_windows.lock().unwrap().remove(&self.id());
         *(self.kill_switch.0.lock().unwrap()) = true;
         *(self.kill_switch.1.lock().unwrap()) = true;
     }
@@ -1,14 +1,17 @@
 use std::{
-    io,
+    fmt,
     os::raw::*,
-    path::{Path, PathBuf},
-    str::Utf8Error,
-    sync::Arc,
+    path::PathBuf,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
 };
 
-use percent_encoding::percent_decode;
-
 use super::{ffi, util, XConnection, XError};
+use crate::{
+    dpi::LogicalPosition, event::DragAction, platform_impl::platform::dnd::DndDataParseError,
+};
 
 #[derive(Debug)]
 pub struct DndAtoms {
@@ -18,7 +21,9 @@ pub struct DndAtoms {
     pub drop: ffi::Atom,
     pub position: ffi::Atom,
     pub status: ffi::Atom,
-    pub action_private: ffi::Atom,
+    pub action_copy: ffi::Atom,
+    pub action_move: ffi::Atom,
+    pub action_link: ffi::Atom,
     pub selection: ffi::Atom,
     pub finished: ffi::Atom,
     pub type_list: ffi::Atom,
@@ -35,7 +40,9 @@ impl DndAtoms {
             b"XdndDrop\0".as_ptr() as *mut c_char,
             b"XdndPosition\0".as_ptr() as *mut c_char,
             b"XdndStatus\0".as_ptr() as *mut c_char,
-            b"XdndActionPrivate\0".as_ptr() as *mut c_char,
+            b"XdndActionCopy\0".as_ptr() as *mut c_char,
+            b"XdndActionMove\0".as_ptr() as *mut c_char,
+            b"XdndActionLink\0".as_ptr() as *mut c_char,
             b"XdndSelection\0".as_ptr() as *mut c_char,
             b"XdndFinished\0".as_ptr() as *mut c_char,
             b"XdndTypeList\0".as_ptr() as *mut c_char,
@@ -50,64 +57,173 @@ impl DndAtoms {
             drop: atoms[3],
             position: atoms[4],
             status: atoms[5],
-            action_private: atoms[6],
-            selection: atoms[7],
-            finished: atoms[8],
-            type_list: atoms[9],
-            uri_list: atoms[10],
-            none: atoms[11],
+            action_copy: atoms[6],
+            action_move: atoms[7],
+            action_link: atoms[8],
+            selection: atoms[9],
+            finished: atoms[10],
+            type_list: atoms[11],
+            uri_list: atoms[12],
+            none: atoms[13],
         })
     }
+
+    // The `accepted` flag and action sent in `XdndStatus` and `XdndFinished` messages.
+    fn reply(&self, state: DndState) -> (c_long, c_long) {
+        match state {
+            DndState::Accepted(action) => {
+                let action = match action {
+                    DragAction::Copy => self.action_copy,
+                    DragAction::Move => self.action_move,
+                    DragAction::Link => self.action_link,
+                };
+                (1, action as c_long)
+            }
+            DndState::Rejected => (0, self.none as c_long),
+        }
+    }
 }
 
 #[derive(Debug, Clone, Copy)]
 pub enum DndState {
-    Accepted,
+    Accepted(DragAction),
     Rejected,
 }
 
+/// A request for the data of a drag and drop operation, made through its `DragOffer`.
 #[derive(Debug)]
-pub enum DndDataParseError {
-    EmptyData,
-    InvalidUtf8(Utf8Error),
-    HostnameSpecified(String),
-    UnexpectedProtocol(String),
-    UnresolvablePath(io::Error),
+pub struct DropRequest {
+    pub window: ffi::Window,
+    pub time: ffi::Time,
+    pub mime_type: String,
+}
+
+#[derive(Clone)]
+pub struct DragOffer {
+    inner: Arc<DragOfferInner>,
+}
+
+struct DragOfferInner {
+    xconn: Arc<XConnection>,
+    atoms: Arc<DndAtoms>,
+    window: ffi::Window,
+    source_window: ffi::Window,
+    mime_types: Vec<String>,
+    // The data has to be requested with the time of the latest XdndPosition or XdndDrop message.
+    time: Mutex<ffi::Time>,
+    // The outcome of the drop, as replied by the application for the latest XdndPosition message.
+    state: Mutex<DndState>,
+    dropped: AtomicBool,
+    requests: ::calloop::channel::Sender<DropRequest>,
+}
+
+impl DragOffer {
+    pub fn mime_types(&self) -> &[String] {
+        &self.inner.mime_types
+    }
+
+    pub fn request_data(&self, mime_type: String) {
+        let _ = self.inner.requests.send(DropRequest {
+            window: self.inner.window,
+            time: *self.inner.time.lock().unwrap(),
+            mime_type,
+        });
+    }
+
+    pub fn reply(&self, action: Option<DragAction>) {
+        // The source stops listening once the data has been dropped.
+        if self.inner.dropped.load(Ordering::SeqCst) {
+            return;
+        }
+        let state = match action {
+            Some(action) => DndState::Accepted(action),
+            None => DndState::Rejected,
+        };
+        *self.inner.state.lock().unwrap() = state;
+        let inner = &self.inner;
+        let _ = unsafe {
+            send_status(
+                &inner.xconn,
+                &inner.atoms,
+                inner.window,
+                inner.source_window,
+                state,
+            )
+        };
+    }
+
+    pub fn set_time(&self, time: ffi::Time) {
+        *self.inner.time.lock().unwrap() = time;
+    }
+
+    pub fn set_dropped(&self) {
+        self.inner.dropped.store(true, Ordering::SeqCst);
+    }
+}
+
+impl fmt::Debug for DragOffer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("DragOffer")
+            .field("mime_types", &self.inner.mime_types)
+            .finish()
+    }
 }
 
-impl From<Utf8Error> for DndDataParseError {
-    fn from(e: Utf8Error) -> Self {
-        DndDataParseError::InvalidUtf8(e)
+impl PartialEq for DragOffer {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.inner, &other.inner)
     }
 }
 
-impl From<io::Error> for DndDataParseError {
-    fn from(e: io::Error) -> Self {
-        DndDataParseError::UnresolvablePath(e)
+impl Drop for DragOfferInner {
+    fn drop(&mut self) {
+        // The source may discard the data once the drop is finished, so it's only told so once
+        // the application can't request it anymore.
+        if self.dropped.load(Ordering::SeqCst) {
+            let state = *self.state.lock().unwrap();
+            let _ = unsafe {
+                send_finished(
+                    &self.xconn,
+                    &self.atoms,
+                    self.window,
+                    self.source_window,
+                    state,
+                )
+            };
+        }
     }
 }
 
 pub struct Dnd {
     xconn: Arc<XConnection>,
-    pub atoms: DndAtoms,
+    pub atoms: Arc<DndAtoms>,
+    requests: ::calloop::channel::Sender<DropRequest>,
     // Populated by XdndEnter event handler
     pub version: Option<c_long>,
     pub type_list: Option<Vec<c_ulong>>,
+    pub offer: Option<DragOffer>,
     // Populated by XdndPosition event handler
     pub source_window: Option<c_ulong>,
+    pub position: Option<LogicalPosition>,
     // Populated by SelectionNotify event handler (triggered by XdndPosition event handler)
     pub result: Option<Result<Vec<PathBuf>, DndDataParseError>>,
 }
 
 impl Dnd {
-    pub fn new(xconn: Arc<XConnection>) -> Result<Self, XError> {
-        let atoms = DndAtoms::new(&xconn)?;
+    pub fn new(
+        xconn: Arc<XConnection>,
+        requests: ::calloop::channel::Sender<DropRequest>,
+    ) -> Result<Self, XError> {
+        let atoms = Arc::new(DndAtoms::new(&xconn)?);
         Ok(Dnd {
             xconn,
             atoms,
+            requests,
             version: None,
             type_list: None,
+            offer: None,
             source_window: None,
+            position: None,
             result: None,
         })
     }
@@ -115,29 +231,44 @@ impl Dnd {
     pub fn reset(&mut self) {
         self.version = None;
         self.type_list = None;
+        self.offer = None;
         self.source_window = None;
+        self.position = None;
         self.result = None;
     }
 
+    // Creates the offer shared with the application once the type list is known.
+    pub fn create_offer(&mut self, this_window: c_ulong, source_window: c_ulong) {
+        let mime_types = match self.type_list {
+            Some(ref type_list) => type_list
+                .iter()
+                .filter(|&&atom| atom != 0)
+                .filter_map(|&atom| self.xconn.get_atom_name(atom).ok())
+                .collect(),
+            None => return,
+        };
+        self.offer = Some(DragOffer {
+            inner: Arc::new(DragOfferInner {
+                xconn: Arc::clone(&self.xconn),
+                atoms: Arc::clone(&self.atoms),
+                window: this_window,
+                source_window,
+                mime_types,
+                time: Mutex::new(ffi::CurrentTime),
+                state: Mutex::new(DndState::Rejected),
+                dropped: AtomicBool::new(false),
+                requests: self.requests.clone(),
+            }),
+        });
+    }
+
     pub unsafe fn send_status(
         &self,
         this_window: c_ulong,
         target_window: c_ulong,
         state: DndState,
     ) -> Result<(), XError> {
-        let (accepted, action) = match state {
-            DndState::Accepted => (1, self.atoms.action_private as c_long),
-            DndState::Rejected => (0, self.atoms.none as c_long),
-        };
-        self.xconn
-            .send_client_msg(
-                target_window,
-                target_window,
-                self.atoms.status,
-                None,
-                [this_window as c_long, accepted, 0, 0, action],
-            )
-            .flush()
+        send_status(&self.xconn, &self.atoms, this_window, target_window, state)
     }
 
     pub unsafe fn send_finished(
@@ -146,19 +277,7 @@ impl Dnd {
         target_window: c_ulong,
         state: DndState,
     ) -> Result<(), XError> {
-        let (accepted, action) = match state {
-            DndState::Accepted => (1, self.atoms.action_private as c_long),
-            DndState::Rejected => (0, self.atoms.none as c_long),
-        };
-        self.xconn
-            .send_client_msg(
-                target_window,
-                target_window,
-                self.atoms.finished,
-                None,
-                [this_window as c_long, accepted, action, 0, 0],
-            )
-            .flush()
+        send_finished(&self.xconn, &self.atoms, this_window, target_window, state)
     }
 
     pub unsafe fn get_type_list(
@@ -187,33 +306,42 @@ impl Dnd {
         self.xconn
             .get_property(window, self.atoms.selection, self.atoms.uri_list)
     }
+}
 
-    pub fn parse_data(&self, data: &mut Vec<c_uchar>) -> Result<Vec<PathBuf>, DndDataParseError> {
-        if !data.is_empty() {
-            let mut path_list = Vec::new();
-            let decoded = percent_decode(data).decode_utf8()?.into_owned();
-            for uri in decoded.split("\r\n").filter(|u| !u.is_empty()) {
-                // The format is specified as protocol://host/path
-                // However, it's typically simply protocol:///path
-                let path_str = if uri.starts_with("file://") {
-                    let path_str = uri.replace("file://", "");
-                    if !path_str.starts_with('/') {
-                        // A hostname is specified
-                        // Supporting this case is beyond the scope of my mental health
-                        return Err(DndDataParseError::HostnameSpecified(path_str));
-                    }
-                    path_str
-                } else {
-                    // Only the file protocol is supported
-                    return Err(DndDataParseError::UnexpectedProtocol(uri.to_owned()));
-                };
+unsafe fn send_status(
+    xconn: &XConnection,
+    atoms: &DndAtoms,
+    this_window: c_ulong,
+    target_window: c_ulong,
+    state: DndState,
+) -> Result<(), XError> {
+    let (accepted, action) = atoms.reply(state);
+    xconn
+        .send_client_msg(
+            target_window,
+            target_window,
+            atoms.status,
+            None,
+            [this_window as c_long, accepted, 0, 0, action],
+        )
+        .flush()
+}
 
-                let path = Path::new(&path_str).canonicalize()?;
-                path_list.push(path);
-            }
-            Ok(path_list)
-        } else {
-            Err(DndDataParseError::EmptyData)
-        }
-    }
+unsafe fn send_finished(
+    xconn: &XConnection,
+    atoms: &DndAtoms,
+    this_window: c_ulong,
+    target_window: c_ulong,
+    state: DndState,
+) -> Result<(), XError> {
+    let (accepted, action) = atoms.reply(state);
+    xconn
+        .send_client_msg(
+            target_window,
+            target_window,
+            atoms.finished,
+            None,
+            [this_window as c_long, accepted, action, 0, 0],
+        )
+        .flush()
 }
@@ -0,0 +1,340 @@
+use std::{os::raw::*, sync::Arc};
+
+use super::{dnd::DndAtoms, ffi, XConnection};
+use crate::{event::DragAction, window::Icon};
+
+// The latest version of the protocol, which we use with targets supporting it.
+const XDND_VERSION: c_long = 5;
+
+/// A request to start dragging data out of one of our windows, made through `Window::start_drag`.
+#[derive(Debug)]
+pub struct DragRequest {
+    pub window: ffi::Window,
+    pub data: Vec<(String, Vec<u8>)>,
+    pub icon: Option<Icon>,
+}
+
+// A window the data is being dragged over, which takes part in the XDND protocol.
+struct Target {
+    window: ffi::Window,
+    version: c_long,
+    // Only one `XdndPosition` message may be in flight, the latest position being sent once the
+    // target has replied.
+    waiting_status: bool,
+    pending_position: Option<(c_long, ffi::Time)>,
+    // The action the target last accepted the drop with, if any.
+    action: Option<DragAction>,
+}
+
+// A drag and drop operation started from one of our windows.
+struct Drag {
+    // The window the drag started from, which grabs the pointer for the length of the operation.
+    origin: ffi::Window,
+    // The window owning the `XdndSelection` selection, and receiving the target's replies.
+    source: ffi::Window,
+    types: Vec<ffi::Atom>,
+    cursor: ffi::Cursor,
+    target: Option<Target>,
+    // Set once the data has been dropped, while waiting for the target to be done with it.
+    dropped: bool,
+}
+
+/// The source side of the XDND protocol.
+pub struct DragSource {
+    xconn: Arc<XConnection>,
+    atoms: Arc<DndAtoms>,
+    root: ffi::Window,
+    current: Option<Drag>,
+}
+
+impl DragSource {
+    pub fn new(xconn: Arc<XConnection>, atoms: Arc<DndAtoms>, root: ffi::Window) -> Self {
+        DragSource {
+            xconn,
+            atoms,
+            root,
+            current: None,
+        }
+    }
+
+    /// Starts a drag from `origin`, once `source` has acquired the `XdndSelection` selection with
+    /// the dragged data in the given `types`.
+    pub fn start(
+        &mut self,
+        origin: ffi::Window,
+        source: ffi::Window,
+        types: Vec<ffi::Atom>,
+        icon: Option<&Icon>,
+    ) -> Result<(), &'static str> {
+        if self.current.is_some() {
+            return Err("A drag and drop operation is already in progress");
+        }
+        // Targets only find the types in the `XdndEnter` message when there are up to 3 of them.
+        self.xconn
+            .change_property(
+                source,
+                self.atoms.type_list,
+                ffi::XA_ATOM,
+                super::util::PropMode::Replace,
+                &types,
+            )
+            .queue();
+
+        let cursor = icon
+            .map(|icon| {
+                let hotspot = (icon.width / 2, icon.height / 2);
+                self.xconn.create_icon_cursor(icon, hotspot)
+            })
+            .unwrap_or(0);
+        let grab = unsafe {
+            (self.xconn.xlib.XGrabPointer)(
+                self.xconn.display,
+                origin,
+                ffi::False,
+                (ffi::ButtonReleaseMask | ffi::PointerMotionMask) as c_uint,
+                ffi::GrabModeAsync,
+                ffi::GrabModeAsync,
+                0,
+                cursor,
+                ffi::CurrentTime,
+            )
+        };
+        if grab != ffi::GrabSuccess {
+            unsafe { self.free_cursor(cursor) };
+            return Err("Failed to grab the pointer");
+        }
+        let _ = self.xconn.flush_requests();
+
+        self.current = Some(Drag {
+            origin,
+            source,
+            types,
+            cursor,
+            target: None,
+            dropped: false,
+        });
+        Ok(())
+    }
+
+    unsafe fn free_cursor(&self, cursor: ffi::Cursor) {
+        if cursor != 0 {
+            (self.xconn.xlib.XFreeCursor)(self.xconn.display, cursor);
+        }
+    }
+
+    // Returns the XDND version supported by `window`, if it supports the protocol at all.
+    fn aware_version(&self, window: ffi::Window) -> Option<c_long> {
+        let aware = self
+            .xconn
+            .get_property::<c_ulong>(window, self.atoms.aware, ffi::XA_ATOM)
+            .ok()?;
+        aware.first().map(|&version| version as c_long)
+    }
+
+    // Finds the XDND aware window under the pointer, descending from the top-level window
+    // through the window manager's frames.
+    fn find_target(&self, x_root: c_int, y_root: c_int) -> Option<(ffi::Window, c_long)> {
+        let mut window = self.root;
+        loop {
+            let mut child = 0;
+            let (mut x, mut y) = (0, 0);
+            let found = unsafe {
+                (self.xconn.xlib.XTranslateCoordinates)(
+                    self.xconn.display,
+                    self.root,
+                    window,
+                    x_root,
+                    y_root,
+                    &mut x,
+                    &mut y,
+                    &mut child,
+                )
+            };
+            if found == ffi::False || child == 0 {
+                return None;
+            }
+            window = child;
+            if let Some(version) = self.aware_version(window) {
+                return Some((window, version.min(XDND_VERSION)));
+            }
+        }
+    }
+
+    fn send(&self, target: ffi::Window, message_type: ffi::Atom, data: [c_long; 5]) {
+        let _ = self
+            .xconn
+            .send_client_msg(target, target, message_type, None, data)
+            .flush();
+    }
+
+    fn send_position(&self, drag: &mut Drag, position: c_long, time: ffi::Time) {
+        let atoms = &self.atoms;
+        if let Some(ref mut target) = drag.target {
+            if target.waiting_status {
+                target.pending_position = Some((position, time));
+            } else {
+                target.waiting_status = true;
+                // The action is only part of the message since version 2.
+                let action = if target.version >= 2 {
+                    atoms.action_copy as c_long
+                } else {
+                    0
+                };
+                let time = if target.version >= 1 { time } else { 0 };
+                let data = [drag.source as c_long, 0, position, time as c_long, action];
+                self.send(target.window, atoms.position, data);
+            }
+        }
+    }
+
+    /// Handles the pointer moving while dragging.
+    pub fn handle_motion(&mut self, x_root: c_int, y_root: c_int, time: ffi::Time) {
+        let mut drag = match self.current.take() {
+            Some(drag) => drag,
+            None => return,
+        };
+        if !drag.dropped {
+            let target = self.find_target(x_root, y_root);
+            let target_window = target.map(|(window, _)| window);
+            if drag.target.as_ref().map(|target| target.window) != target_window {
+                if let Some(previous) = drag.target.take() {
+                    self.send(
+                        previous.window,
+                        self.atoms.leave,
+                        [drag.source as c_long, 0, 0, 0, 0],
+                    );
+                }
+                if let Some((window, version)) = target {
+                    let more_types = (drag.types.len() > 3) as c_long;
+                    let mut data = [drag.source as c_long, (version << 24) | more_types, 0, 0, 0];
+                    for (slot, &atom) in data[2..].iter_mut().zip(&drag.types) {
+                        *slot = atom as c_long;
+                    }
+                    self.send(window, self.atoms.enter, data);
+                    drag.target = Some(Target {
+                        window,
+                        version,
+                        waiting_status: false,
+                        pending_position: None,
+                        action: None,
+                    });
+                }
+            }
+            let position = ((x_root as c_long) << 16) | (y_root as c_long & 0xffff);
+            self.send_position(&mut drag, position, time);
+        }
+        self.current = Some(drag);
+    }
+
+    /// Handles the pointer button being released, dropping the data. Returns the origin window
+    /// along with the outcome if the operation is over.
+    pub fn handle_release(&mut self, time: ffi::Time) -> Option<(ffi::Window, Option<DragAction>)> {
+        let mut drag = self.current.take()?;
+        if drag.dropped {
+            self.current = Some(drag);
+            return None;
+        }
+        // The pointer isn't needed anymore, even if the target has yet to finish.
+        unsafe {
+            (self.xconn.xlib.XUngrabPointer)(self.xconn.display, ffi::CurrentTime);
+        }
+        let _ = self.xconn.flush_requests();
+
+        let target = drag
+            .target
+            .as_ref()
+            .map(|target| (target.window, target.version, target.action));
+        match target {
+            Some((window, version, Some(action))) => {
+                let time = if version >= 1 { time } else { 0 };
+                let data = [drag.source as c_long, 0, time as c_long, 0, 0];
+                self.send(window, self.atoms.drop, data);
+                // `XdndFinished` only exists since version 2.
+                if version >= 2 {
+                    drag.dropped = true;
+                    self.current = Some(drag);
+                    return None;
+                }
+                Some(self.end(drag, Some(action)))
+            }
+            Some((window, _, None)) => {
+                self.send(
+                    window,
+                    self.atoms.leave,
+                    [drag.source as c_long, 0, 0, 0, 0],
+                );
+                Some(self.end(drag, None))
+            }
+            None => Some(self.end(drag, None)),
+        }
+    }
+
+    fn action_from_atom(&self, atom: ffi::Atom) -> DragAction {
+        if atom == self.atoms.action_move {
+            DragAction::Move
+        } else if atom == self.atoms.action_link {
+            DragAction::Link
+        } else {
+            DragAction::Copy
+        }
+    }
+
+    /// Handles the `XdndStatus` reply of a target to our `XdndPosition` messages.
+    pub fn handle_status(&mut self, client_msg: &ffi::XClientMessageEvent) {
+        let mut drag = match self.current.take() {
+            Some(drag) => drag,
+            None => return,
+        };
+        let window = client_msg.data.get_long(0) as ffi::Window;
+        let accepted = client_msg.data.get_long(1) & 1 == 1;
+        let action = self.action_from_atom(client_msg.data.get_long(4) as ffi::Atom);
+        let pending = match drag.target {
+            Some(ref mut target) if target.window == window => {
+                target.waiting_status = false;
+                target.action = if accepted { Some(action) } else { None };
+                target.pending_position.take()
+            }
+            _ => None,
+        };
+        if let (Some((position, time)), false) = (pending, drag.dropped) {
+            self.send_position(&mut drag, position, time);
+        }
+        self.current = Some(drag);
+    }
+
+    /// Handles the `XdndFinished` message of the target the data was dropped on. Returns the
+    /// origin window along with the outcome.
+    pub fn handle_finished(
+        &mut self,
+        client_msg: &ffi::XClientMessageEvent,
+    ) -> Option<(ffi::Window, Option<DragAction>)> {
+        let window = client_msg.data.get_long(0) as ffi::Window;
+        let drag = self.current.take()?;
+        let target = match drag.target {
+            Some(ref target) if drag.dropped && target.window == window => target,
+            _ => {
+                self.current = Some(drag);
+                return None;
+            }
+        };
+        // The outcome of the drop is only reported since version 5.
+        let action = if target.version >= 5 {
+            let accepted = client_msg.data.get_long(1) & 1 == 1;
+            let action = self.action_from_atom(client_msg.data.get_long(2) as ffi::Atom);
+            if accepted {
+                Some(action)
+            } else {
+                None
+            }
+        } else {
+            target.action
+        };
+        Some(self.end(drag, action))
+    }
+
+    fn end(&self, drag: Drag, action: Option<DragAction>) -> (ffi::Window, Option<DragAction>) {
+        unsafe { self.free_cursor(drag.cursor) };
+        let _ = self.xconn.flush_requests();
+        (drag.origin, action)
+    }
+}
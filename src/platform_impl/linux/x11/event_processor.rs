@@ -1,22 +1,52 @@
-use std::{cell::RefCell, collections::HashMap, ptr, rc::Rc, slice};
+use std::{
+    cell::{Cell, RefCell},
+    collections::{HashMap, HashSet},
+    rc::Rc,
+    slice,
+};
 
 use libc::{c_char, c_int, c_long, c_uint, c_ulong};
 
 use super::{
-    events, ffi, get_xtarget, mkdid, mkwid, monitor, util, Device, DeviceId, DeviceInfo, Dnd,
-    DndState, GenericEventCookie, ImeReceiver, ScrollOrientation, UnownedWindow, WindowId,
-    XExtension,
+    ffi, get_xtarget, mkdid, mkwid, monitor, util, Device, DeviceId, DeviceInfo, Dnd, DndState,
+    DragRequest, DragSource, GenericEventCookie, ImeEvent, ImeEventReceiver, ImeReceiver,
+    ImeRequest, Requester, ScrollOrientation, UnownedWindow, WindowId, XExtension,
 };
 
 use crate::{
     dpi::{LogicalPosition, LogicalSize},
-    event::{DeviceEvent, Event, KeyboardInput, ModifiersState, WindowEvent},
+    event::{
+        DeviceEvent, DragOffer, DragReply, ElementState, Event, Ime, KeyboardInput, ModifiersState,
+        SelectionEvent, WindowEvent,
+    },
     event_loop::EventLoopWindowTarget as RootELW,
+    keyboard::KeyboardLayout,
+    platform_impl::{
+        platform::{dnd::parse_uri_list, keyboard, xkb::KbdState},
+        DragOffer as PlatformDragOffer, DragReply as PlatformDragReply,
+    },
 };
 
 pub(super) struct EventProcessor<T: 'static> {
     pub(super) dnd: Dnd,
+    pub(super) drag_source: DragSource,
     pub(super) ime_receiver: ImeReceiver,
+    pub(super) ime_event_receiver: ImeEventReceiver,
+    // The windows whose input method is composing text.
+    pub(super) composing: RefCell<HashSet<ffi::Window>>,
+    // The keys pressed in each of our windows while it's focused, to tell the repeated presses
+    // apart.
+    pub(super) held_keys: RefCell<HashMap<ffi::Window, HashSet<c_uint>>>,
+    // The core keyboard, whose state is only tracked with libxkbcommon-x11.
+    pub(super) xkb_device_id: i32,
+    pub(super) xkb_event_offset: c_int,
+    // The window with the keyboard focus, and the modifiers and layout last sent to it.
+    pub(super) active_window: Cell<Option<ffi::Window>>,
+    pub(super) reported_modifiers: Cell<ModifiersState>,
+    pub(super) reported_layout: RefCell<Option<KeyboardLayout>>,
+    // The scales of the pinch gestures in progress on each device, as the server sends them
+    // relative to the beginning of the gestures.
+    pub(super) pinch_scales: RefCell<HashMap<c_int, f64>>,
     pub(super) randr_event_offset: c_int,
     pub(super) devices: RefCell<HashMap<DeviceId, Device>>,
     pub(super) xi2ext: XExtension,
@@ -62,6 +92,27 @@ impl<T: 'static> EventProcessor<T> {
         self.with_window(window_id, |_| ()).is_some()
     }
 
+    /// Starts dragging data out of one of our windows, as requested through `Window::start_drag`.
+    pub(super) fn start_drag<F>(&mut self, request: DragRequest, mut callback: F)
+    where
+        F: FnMut(Event<T>),
+    {
+        let DragRequest { window, data, icon } = request;
+        let (source, types) = {
+            let wt = get_xtarget(&self.target);
+            let mut selections = wt.selections.borrow_mut();
+            (selections.window, selections.set_drag(data))
+        };
+        let result =
+            types.and_then(|types| self.drag_source.start(window, source, types, icon.as_ref()));
+        if result.is_err() {
+            callback(Event::WindowEvent {
+                window_id: mkwid(window),
+                event: WindowEvent::DragEnded(None),
+            });
+        }
+    }
+
     pub(super) unsafe fn poll_one_event(&mut self, event_ptr: *mut ffi::XEvent) -> bool {
         let wt = get_xtarget(&self.target);
         // This function is used to poll and remove a single event
@@ -89,6 +140,82 @@ impl<T: 'static> EventProcessor<T> {
         result != 0
     }
 
+    fn process_xkb_event(&self, xev: &ffi::XEvent) {
+        let wt = get_xtarget(&self.target);
+        let mut kbd_state = wt.kbd_state.borrow_mut();
+        let kbd_state = match *kbd_state {
+            Some(ref mut kbd_state) => kbd_state,
+            None => return,
+        };
+        let xkb_event: &ffi::XkbAnyEvent = util::reinterpret(xev);
+        if xkb_event.device as i32 != self.xkb_device_id {
+            return;
+        }
+        match xkb_event.xkb_type {
+            ffi::XkbNewKeyboardNotify | ffi::XkbMapNotify => unsafe {
+                let connection = (wt.xconn.xlib_xcb.XGetXCBConnection)(wt.xconn.display);
+                kbd_state.set_keymap_from_x11(connection, self.xkb_device_id);
+            },
+            ffi::XkbStateNotify => {
+                let xkb_event: &ffi::XkbStateNotifyEvent = util::reinterpret(xev);
+                // The key changing the state is reported along with it, for the side of the
+                // modifier to change at once.
+                match xkb_event.event_type as c_int {
+                    ffi::KeyPress => kbd_state
+                        .update_modifier_key(xkb_event.keycode as u32, ElementState::Pressed),
+                    ffi::KeyRelease => kbd_state
+                        .update_modifier_key(xkb_event.keycode as u32, ElementState::Released),
+                    _ => (),
+                }
+                kbd_state.update_modifiers(
+                    xkb_event.base_mods,
+                    xkb_event.latched_mods,
+                    xkb_event.locked_mods,
+                    xkb_event.base_group as u32,
+                    xkb_event.latched_group as u32,
+                    xkb_event.locked_group as u32,
+                );
+            }
+            _ => (),
+        }
+    }
+
+    // Sends `ModifiersChanged` and `KeyboardLayoutChanged` to the focused window, for what changed
+    // since it was last told.
+    fn report_keyboard_state<F>(&self, window: ffi::Window, callback: &mut F)
+    where
+        F: FnMut(Event<T>),
+    {
+        let wt = get_xtarget(&self.target);
+        let (modifiers, layout) = match *wt.kbd_state.borrow() {
+            Some(ref kbd_state) => (kbd_state.mods_state(), kbd_state.layout()),
+            None => return,
+        };
+        self.report_modifiers(window, modifiers, callback);
+        if layout != *self.reported_layout.borrow() {
+            *self.reported_layout.borrow_mut() = layout.clone();
+            if let Some(layout) = layout {
+                callback(Event::WindowEvent {
+                    window_id: mkwid(window),
+                    event: WindowEvent::KeyboardLayoutChanged(layout),
+                });
+            }
+        }
+    }
+
+    fn report_modifiers<F>(&self, window: ffi::Window, modifiers: ModifiersState, callback: &mut F)
+    where
+        F: FnMut(Event<T>),
+    {
+        if modifiers != self.reported_modifiers.get() {
+            self.reported_modifiers.set(modifiers);
+            callback(Event::WindowEvent {
+                window_id: mkwid(window),
+                event: WindowEvent::ModifiersChanged(modifiers),
+            });
+        }
+    }
+
     pub(super) fn process_event<F>(&mut self, xev: &mut ffi::XEvent, mut callback: F)
     where
         F: FnMut(Event<T>),
@@ -106,6 +233,17 @@ impl<T: 'static> EventProcessor<T> {
                 })
             }
         {
+            // The keys the input method filters may be released without us being told, which
+            // would make their next presses look repeated.
+            let event_type = xev.get_type();
+            if event_type == ffi::KeyPress || event_type == ffi::KeyRelease {
+                let xkev: &ffi::XKeyEvent = xev.as_ref();
+                if let Some(keys) = self.held_keys.borrow_mut().get_mut(&xkev.window) {
+                    keys.remove(&xkev.keycode);
+                }
+            }
+            // The text being composed changes as the input method filters key presses.
+            self.process_ime_events(&mut callback);
             return;
         }
 
@@ -158,54 +296,74 @@ impl<T: 'static> EventProcessor<T> {
                     {
                         self.dnd.type_list = Some(more_types);
                     }
+                    self.dnd.create_offer(window, source_window);
                 } else if client_msg.message_type == self.dnd.atoms.position {
-                    // This event occurs every time the mouse moves while a file's being dragged
-                    // over our window. We emit HoveredFile in response; while the macOS backend
+                    // This event occurs every time the mouse moves while something's being dragged
+                    // over our window. We emit `DragHovered` with the new position in response,
+                    // and `HoveredFile` the first time files are dragged; while the macOS backend
                     // does that upon a drag entering, XDND doesn't have access to the actual drop
-                    // data until this event. For parity with other platforms, we only emit
-                    // `HoveredFile` the first time, though if winit's API is later extended to
-                    // supply position updates with `HoveredFile` or another event, implementing
-                    // that here would be trivial.
+                    // data until this event. The `XdndStatus` reply is left to the application.
 
                     let source_window = client_msg.data.get_long(0) as c_ulong;
 
-                    // Equivalent to `(x << shift) | y`
-                    // where `shift = mem::size_of::<c_short>() * 8`
+                    // Equivalent to `(x << 16) | y`
                     // Note that coordinates are in "desktop space", not "window space"
                     // (in X11 parlance, they're root window coordinates)
-                    //let packed_coordinates = client_msg.data.get_long(2);
-                    //let shift = mem::size_of::<libc::c_short>() * 8;
-                    //let x = packed_coordinates >> shift;
-                    //let y = packed_coordinates & !(x << shift);
+                    let packed_coordinates = client_msg.data.get_long(2);
+                    let x_root = (packed_coordinates >> 16) as i16;
+                    let y_root = packed_coordinates as i16;
 
                     // By our own state flow, `version` should never be `None` at this point.
                     let version = self.dnd.version.unwrap_or(5);
 
+                    let time = if version >= 1 {
+                        client_msg.data.get_long(3) as c_ulong
+                    } else {
+                        // In version 0, time isn't specified
+                        ffi::CurrentTime
+                    };
+
                     // Action is specified in versions 2 and up, though we don't need it anyway.
                     //let action = client_msg.data.get_long(4);
 
-                    let accepted = if let Some(ref type_list) = self.dnd.type_list {
+                    let has_paths = if let Some(ref type_list) = self.dnd.type_list {
                         type_list.contains(&self.dnd.atoms.uri_list)
                     } else {
                         false
                     };
 
-                    if accepted {
+                    if let Some(offer) = self.dnd.offer.clone() {
                         self.dnd.source_window = Some(source_window);
                         unsafe {
-                            if self.dnd.result.is_none() {
-                                let time = if version >= 1 {
-                                    client_msg.data.get_long(3) as c_ulong
-                                } else {
-                                    // In version 0, time isn't specified
-                                    ffi::CurrentTime
-                                };
+                            if has_paths && self.dnd.result.is_none() {
                                 // This results in the `SelectionNotify` event below
                                 self.dnd.convert_selection(window, time);
                             }
-                            self.dnd
-                                .send_status(window, source_window, DndState::Accepted)
-                                .expect("Failed to send `XdndStatus` message.");
+                        }
+                        // Replies with the default if the event can't be emitted.
+                        let reply =
+                            DragReply(PlatformDragReply::new(PlatformDragOffer::X(offer.clone())));
+
+                        let window_origin = wt.xconn.translate_coords(window, wt.root);
+                        let hidpi_factor = self.with_window(window, |window| window.hidpi_factor());
+                        if let (Ok(origin), Some(hidpi_factor)) = (window_origin, hidpi_factor) {
+                            let position = LogicalPosition::from_physical(
+                                (
+                                    x_root as i32 - origin.x_rel_root,
+                                    y_root as i32 - origin.y_rel_root,
+                                ),
+                                hidpi_factor,
+                            );
+                            self.dnd.position = Some(position);
+                            offer.set_time(time);
+                            callback(Event::WindowEvent {
+                                window_id,
+                                event: WindowEvent::DragHovered {
+                                    offer: DragOffer(PlatformDragOffer::X(offer)),
+                                    position,
+                                    reply,
+                                },
+                            });
                         }
                     } else {
                         unsafe {
@@ -216,8 +374,8 @@ impl<T: 'static> EventProcessor<T> {
                         self.dnd.reset();
                     }
                 } else if client_msg.message_type == self.dnd.atoms.drop {
-                    let (source_window, state) = if let Some(source_window) = self.dnd.source_window
-                    {
+                    let offer = self.dnd.offer.clone();
+                    if let (Some(_), Some(offer)) = (self.dnd.source_window, offer) {
                         if let Some(Ok(ref path_list)) = self.dnd.result {
                             for path in path_list {
                                 callback(Event::WindowEvent {
@@ -226,25 +384,72 @@ impl<T: 'static> EventProcessor<T> {
                                 });
                             }
                         }
-                        (source_window, DndState::Accepted)
+                        if self.dnd.version.unwrap_or(5) >= 1 {
+                            offer.set_time(client_msg.data.get_long(2) as c_ulong);
+                        }
+                        // `XdndFinished` is sent once the application is done with the offer.
+                        offer.set_dropped();
+                        if let Some(position) = self.dnd.position {
+                            callback(Event::WindowEvent {
+                                window_id,
+                                event: WindowEvent::DragDropped {
+                                    offer: DragOffer(PlatformDragOffer::X(offer)),
+                                    position,
+                                },
+                            });
+                        }
                     } else {
                         // `source_window` won't be part of our DND state if we already rejected the drop in our
                         // `XdndPosition` handler.
                         let source_window = client_msg.data.get_long(0) as c_ulong;
-                        (source_window, DndState::Rejected)
-                    };
-                    unsafe {
-                        self.dnd
-                            .send_finished(window, source_window, state)
-                            .expect("Failed to send `XdndFinished` message.");
+                        unsafe {
+                            self.dnd
+                                .send_finished(window, source_window, DndState::Rejected)
+                                .expect("Failed to send `XdndFinished` message.");
+                        }
                     }
                     self.dnd.reset();
+                } else if client_msg.message_type == self.dnd.atoms.status {
+                    // The replies of the targets of a drag started from one of our windows.
+                    self.drag_source.handle_status(client_msg);
+                } else if client_msg.message_type == self.dnd.atoms.finished {
+                    if let Some((origin, action)) = self.drag_source.handle_finished(client_msg) {
+                        callback(Event::WindowEvent {
+                            window_id: mkwid(origin),
+                            event: WindowEvent::DragEnded(action),
+                        });
+                    }
                 } else if client_msg.message_type == self.dnd.atoms.leave {
+                    let hovered = self.dnd.position.is_some();
                     self.dnd.reset();
                     callback(Event::WindowEvent {
                         window_id,
                         event: WindowEvent::HoveredFileCancelled,
                     });
+                    if hovered {
+                        callback(Event::WindowEvent {
+                            window_id,
+                            event: WindowEvent::DragLeft,
+                        });
+                    }
+                }
+            }
+
+            // The pointer is tracked through XInput2, except while dragging data out of one of our
+            // windows; it's then grabbed with the core protocol.
+            ffi::MotionNotify => {
+                let xmotion: &ffi::XMotionEvent = xev.as_ref();
+                self.drag_source
+                    .handle_motion(xmotion.x_root, xmotion.y_root, xmotion.time);
+            }
+
+            ffi::ButtonRelease => {
+                let xbutton: &ffi::XButtonEvent = xev.as_ref();
+                if let Some((origin, action)) = self.drag_source.handle_release(xbutton.time) {
+                    callback(Event::WindowEvent {
+                        window_id: mkwid(origin),
+                        event: WindowEvent::DragEnded(action),
+                    });
                 }
             }
 
@@ -254,12 +459,17 @@ impl<T: 'static> EventProcessor<T> {
                 let window = xsel.requestor;
                 let window_id = mkwid(window);
 
-                if xsel.property == self.dnd.atoms.selection {
+                if window == wt.selections.borrow().window {
+                    let result = wt.selections.borrow_mut().handle_notify(xsel);
+                    if let Some((requester, event)) = result {
+                        callback(transfer_event(requester, event));
+                    }
+                } else if xsel.property == self.dnd.atoms.selection {
                     let mut result = None;
 
                     // This is where we receive data from drag and drop
-                    if let Ok(mut data) = unsafe { self.dnd.read_data(window) } {
-                        let parse_result = self.dnd.parse_data(&mut data);
+                    if let Ok(data) = unsafe { self.dnd.read_data(window) } {
+                        let parse_result = parse_uri_list(&data);
                         if let Ok(ref path_list) = parse_result {
                             for path in path_list {
                                 callback(Event::WindowEvent {
@@ -275,6 +485,32 @@ impl<T: 'static> EventProcessor<T> {
                 }
             }
 
+            ffi::SelectionRequest => {
+                let xreq: &ffi::XSelectionRequestEvent = xev.as_ref();
+                wt.selections.borrow_mut().handle_request(xreq);
+            }
+
+            ffi::SelectionClear => {
+                let xclear: &ffi::XSelectionClearEvent = xev.as_ref();
+                let result = wt.selections.borrow_mut().handle_clear(xclear);
+                if let Some((selection, window)) = result {
+                    if self.window_exists(window) {
+                        callback(Event::WindowEvent {
+                            window_id: mkwid(window),
+                            event: WindowEvent::SelectionLost(selection),
+                        });
+                    }
+                }
+            }
+
+            ffi::PropertyNotify => {
+                let xprop: &ffi::XPropertyEvent = xev.as_ref();
+                let result = wt.selections.borrow_mut().handle_property_notify(xprop);
+                if let Some((requester, event)) = result {
+                    callback(transfer_event(requester, event));
+                }
+            }
+
             ffi::ConfigureNotify => {
                 #[derive(Debug, Default)]
                 struct Events {
@@ -424,6 +660,11 @@ impl<T: 'static> EventProcessor<T> {
                         events.resized = Some(WindowEvent::Resized(logical_size));
                     }
 
+                    if events.dpi_changed.is_some() {
+                        drop(shared_state_lock);
+                        window.refresh_cursor(monitor.hidpi_factor);
+                    }
+
                     events
                 });
 
@@ -465,6 +706,7 @@ impl<T: 'static> EventProcessor<T> {
                 // In the event that the window's been destroyed without being dropped first, we
                 // cleanup again here.
                 wt.windows.borrow_mut().remove(&WindowId(window));
+                self.held_keys.borrow_mut().remove(&window);
 
                 // Since all XIM stuff needs to happen from the same thread, we destroy the input
                 // context here instead of when dropping the window.
@@ -472,6 +714,7 @@ impl<T: 'static> EventProcessor<T> {
                     .borrow_mut()
                     .remove_context(window)
                     .expect("Failed to destroy input context");
+                self.process_ime_events(&mut callback);
 
                 callback(Event::WindowEvent {
                     window_id,
@@ -511,29 +754,49 @@ impl<T: 'static> EventProcessor<T> {
                 let device = util::VIRTUAL_CORE_KEYBOARD;
                 let device_id = mkdid(device);
 
+                // The input method gets the text of the key presses when the window has an input
+                // context.
+                let ime_text = match wt.ime.borrow().get_context(window) {
+                    Some(ic) if state == Pressed => Some(wt.xconn.lookup_utf8(ic, xkev)),
+                    _ => None,
+                };
+
                 // When a compose sequence or IME pre-edit is finished, it ends in a KeyPress with
                 // a keycode of 0.
-                if xkev.keycode != 0 {
-                    let modifiers = ModifiersState {
-                        alt: xkev.state & ffi::Mod1Mask != 0,
-                        shift: xkev.state & ffi::ShiftMask != 0,
-                        ctrl: xkev.state & ffi::ControlMask != 0,
-                        logo: xkev.state & ffi::Mod4Mask != 0,
+                let text = if xkev.keycode != 0 {
+                    let scancode = xkev.keycode - 8;
+
+                    // Window creation enables detectable auto-repeat, so repeated keys are
+                    // pressed again without being released in between.
+                    let repeat = {
+                        let mut held_keys = self.held_keys.borrow_mut();
+                        let keys = held_keys.entry(window).or_default();
+                        match state {
+                            Pressed => !keys.insert(xkev.keycode),
+                            Released => {
+                                keys.remove(&xkev.keycode);
+                                false
+                            }
+                        }
                     };
+                    if repeat && !self.with_window(window, |w| w.key_repeat()).unwrap_or(true) {
+                        return;
+                    }
 
-                    let keysym = unsafe {
-                        let mut keysym = 0;
-                        (wt.xconn.xlib.XLookupString)(
-                            xkev,
-                            ptr::null_mut(),
-                            0,
-                            &mut keysym,
-                            ptr::null_mut(),
-                        );
-                        wt.xconn.check_errors().expect("Failed to lookup keysym");
-                        keysym
+                    let has_kbd_state = wt.kbd_state.borrow().is_some();
+                    let (keysym, modifiers) = match *wt.kbd_state.borrow_mut() {
+                        Some(ref mut kbd_state) => {
+                            kbd_state.update_modifier_key(xkev.keycode, state);
+                            (kbd_state.keysym(xkev.keycode), kbd_state.mods_state())
+                        }
+                        None => (
+                            wt.xconn.lookup_keysym(xkev),
+                            util::modifiers_from_state(xkev.state),
+                        ),
                     };
-                    let virtual_keycode = events::keysym_to_element(keysym as c_uint);
+                    if has_kbd_state {
+                        self.report_keyboard_state(window, &mut callback);
+                    }
 
                     callback(Event::WindowEvent {
                         window_id,
@@ -541,27 +804,55 @@ impl<T: 'static> EventProcessor<T> {
                             device_id,
                             input: KeyboardInput {
                                 state,
-                                scancode: xkev.keycode - 8,
-                                virtual_keycode,
+                                scancode,
+                                virtual_keycode: keyboard::virtual_keycode(scancode, keysym),
                                 modifiers,
+                                is_repeat: repeat,
                             },
                         },
                     });
-                }
 
-                if state == Pressed {
-                    let written = if let Some(ic) = wt.ime.borrow().get_context(window) {
-                        wt.xconn.lookup_utf8(ic, xkev)
-                    } else {
-                        return;
+                    let event = match (wt.kbd_state.borrow_mut().as_mut(), ime_text) {
+                        (Some(kbd_state), None) => {
+                            kbd_state.process_key_event(xkev.keycode, state, repeat)
+                        }
+                        (_, ime_text) => {
+                            let text = match state {
+                                Pressed => ime_text.or_else(|| Some(wt.xconn.lookup_latin1(xkev))),
+                                Released => None,
+                            };
+                            keyboard::key_event(scancode, keysym, text, state, repeat)
+                        }
                     };
+                    let text = event.text.clone();
+                    callback(Event::WindowEvent {
+                        window_id,
+                        event: WindowEvent::Key { device_id, event },
+                    });
+                    text
+                } else {
+                    ime_text
+                };
 
-                    for chr in written.chars() {
-                        let event = Event::WindowEvent {
+                if let Some(text) = text {
+                    // The text composed with the input method is committed with a keycode of 0 too,
+                    // and only windows with an input context get text for those.
+                    if xkev.keycode == 0 && !text.is_empty() {
+                        // Some input methods end the composition while looking the text up, which
+                        // is reported before the commit.
+                        self.process_ime_events(&mut callback);
+                        callback(Event::WindowEvent {
                             window_id,
-                            event: WindowEvent::ReceivedCharacter(chr),
-                        };
-                        callback(event);
+                            event: WindowEvent::Ime(Ime::Commit(text)),
+                        });
+                    } else {
+                        for chr in text.chars() {
+                            let event = Event::WindowEvent {
+                                window_id,
+                                event: WindowEvent::ReceivedCharacter(chr),
+                            };
+                            callback(event);
+                        }
                     }
                 }
             }
@@ -581,10 +872,10 @@ impl<T: 'static> EventProcessor<T> {
                     ElementState::{Pressed, Released},
                     MouseButton::{Left, Middle, Other, Right},
                     MouseScrollDelta::LineDelta,
-                    Touch, TouchPhase,
+                    ScrollSource, TabletToolEvent, Touch, TouchContact, TouchPhase, WheelDelta,
                     WindowEvent::{
                         AxisMotion, CursorEntered, CursorLeft, CursorMoved, Focused, MouseInput,
-                        MouseWheel,
+                        MouseWheel, TabletTool, WheelInput,
                     },
                 };
 
@@ -605,6 +896,29 @@ impl<T: 'static> EventProcessor<T> {
                         } else {
                             Released
                         };
+
+                        let is_tablet_tool = self
+                            .devices
+                            .borrow()
+                            .get(&DeviceId(xev.sourceid))
+                            .and_then(|device| device.tablet)
+                            .is_some();
+                        if is_tablet_tool {
+                            // The tip of the tool is its first button.
+                            let event = match (xev.detail as u32, state) {
+                                (ffi::Button1, Pressed) => TabletToolEvent::Down,
+                                (ffi::Button1, Released) => TabletToolEvent::Up,
+                                (button, state) => TabletToolEvent::Button { button, state },
+                            };
+                            callback(Event::WindowEvent {
+                                window_id,
+                                event: TabletTool {
+                                    device_id: mkdid(xev.sourceid),
+                                    event,
+                                },
+                            });
+                        }
+
                         match xev.detail as u32 {
                             ffi::Button1 => callback(Event::WindowEvent {
                                 window_id,
@@ -639,17 +953,31 @@ impl<T: 'static> EventProcessor<T> {
                             // turn) as axis motion, so we don't otherwise special-case these button presses.
                             4 | 5 | 6 | 7 => {
                                 if xev.flags & ffi::XIPointerEmulated == 0 {
+                                    let (x, y) = match xev.detail {
+                                        4 => (0, 1),
+                                        5 => (0, -1),
+                                        6 => (-1, 0),
+                                        7 => (1, 0),
+                                        _ => unreachable!(),
+                                    };
                                     callback(Event::WindowEvent {
                                         window_id,
                                         event: MouseWheel {
                                             device_id,
-                                            delta: match xev.detail {
-                                                4 => LineDelta(0.0, 1.0),
-                                                5 => LineDelta(0.0, -1.0),
-                                                6 => LineDelta(-1.0, 0.0),
-                                                7 => LineDelta(1.0, 0.0),
-                                                _ => unreachable!(),
-                                            },
+                                            delta: LineDelta(x as f32, y as f32),
+                                            phase: TouchPhase::Moved,
+                                            modifiers,
+                                        },
+                                    });
+                                    // Only wheels are reported as buttons rather than as scroll
+                                    // valuators.
+                                    callback(Event::WindowEvent {
+                                        window_id,
+                                        event: WheelInput {
+                                            device_id,
+                                            detents: (x, y),
+                                            delta: WheelDelta::LineDelta(x.into(), y.into()),
+                                            source: Some(ScrollSource::Wheel),
                                             phase: TouchPhase::Moved,
                                             modifiers,
                                         },
@@ -729,19 +1057,36 @@ impl<T: 'static> EventProcessor<T> {
                                     {
                                         let delta = (x - info.position) / info.increment;
                                         info.position = x;
+                                        // High-resolution wheels move by fractions of an increment,
+                                        // so count the detents once they add up to whole ones.
+                                        info.detent_remainder += delta;
+                                        let detents = info.detent_remainder.trunc();
+                                        info.detent_remainder -= detents;
+                                        let (delta, detents) = match info.orientation {
+                                            ScrollOrientation::Horizontal => {
+                                                ((delta, 0.0), (detents as i32, 0))
+                                            }
+                                            // X11 vertical scroll coordinates are opposite to winit's
+                                            ScrollOrientation::Vertical => {
+                                                ((0.0, -delta), (0, -detents as i32))
+                                            }
+                                        };
                                         events.push(Event::WindowEvent {
                                             window_id,
                                             event: MouseWheel {
                                                 device_id,
-                                                delta: match info.orientation {
-                                                    ScrollOrientation::Horizontal => {
-                                                        LineDelta(delta as f32, 0.0)
-                                                    }
-                                                    // X11 vertical scroll coordinates are opposite to winit's
-                                                    ScrollOrientation::Vertical => {
-                                                        LineDelta(0.0, -delta as f32)
-                                                    }
-                                                },
+                                                delta: LineDelta(delta.0 as f32, delta.1 as f32),
+                                                phase: TouchPhase::Moved,
+                                                modifiers,
+                                            },
+                                        });
+                                        events.push(Event::WindowEvent {
+                                            window_id,
+                                            event: WheelInput {
+                                                device_id,
+                                                detents,
+                                                delta: WheelDelta::LineDelta(delta.0, delta.1),
+                                                source: None,
                                                 phase: TouchPhase::Moved,
                                                 modifiers,
                                             },
@@ -759,6 +1104,48 @@ impl<T: 'static> EventProcessor<T> {
                                     value = unsafe { value.offset(1) };
                                 }
                             }
+
+                            if let Some(ref mut tablet) = physical_device.tablet {
+                                let dpi_factor = match self
+                                    .with_window(xev.event, |window| window.hidpi_factor())
+                                {
+                                    Some(dpi_factor) => dpi_factor,
+                                    None => return,
+                                };
+                                let location = LogicalPosition::from_physical(
+                                    (xev.event_x, xev.event_y),
+                                    dpi_factor,
+                                );
+                                let device_id = mkdid(xev.sourceid);
+                                if tablet.window != Some(xev.event) {
+                                    if let Some(window) = tablet.window {
+                                        events.push(Event::WindowEvent {
+                                            window_id: mkwid(window),
+                                            event: TabletTool {
+                                                device_id,
+                                                event: TabletToolEvent::ProximityOut,
+                                            },
+                                        });
+                                    }
+                                    tablet.window = Some(xev.event);
+                                    events.push(Event::WindowEvent {
+                                        window_id,
+                                        event: TabletTool {
+                                            device_id,
+                                            event: TabletToolEvent::ProximityIn(tablet.tool),
+                                        },
+                                    });
+                                }
+                                events.push(Event::WindowEvent {
+                                    window_id,
+                                    event: TabletTool {
+                                        device_id,
+                                        event: TabletToolEvent::Moved(
+                                            tablet.state(&xev.valuators, location),
+                                        ),
+                                    },
+                                });
+                            }
                         }
                         for event in events {
                             callback(event);
@@ -838,6 +1225,28 @@ impl<T: 'static> EventProcessor<T> {
                                 },
                             });
                         }
+
+                        // The tablet tools over the window are leaving it as well.
+                        let mut tablet_tools = Vec::new();
+                        for (&device_id, device) in self.devices.borrow_mut().iter_mut() {
+                            if let Some(ref mut tablet) = device.tablet {
+                                if tablet.window == Some(xev.event) {
+                                    tablet.window = None;
+                                    tablet_tools.push(device_id);
+                                }
+                            }
+                        }
+                        if !window_closed {
+                            for DeviceId(device_id) in tablet_tools {
+                                callback(Event::WindowEvent {
+                                    window_id: mkwid(xev.event),
+                                    event: TabletTool {
+                                        device_id: mkdid(device_id),
+                                        event: TabletToolEvent::ProximityOut,
+                                    },
+                                });
+                            }
+                        }
                     }
                     ffi::XI_FocusIn => {
                         let xev: &ffi::XIFocusInEvent = unsafe { &*(xev.data as *const _) };
@@ -859,6 +1268,9 @@ impl<T: 'static> EventProcessor<T> {
                             event: Focused(true),
                         });
 
+                        self.active_window.set(Some(xev.event));
+                        self.report_keyboard_state(xev.event, &mut callback);
+
                         // The deviceid for this event is for a keyboard instead of a pointer,
                         // so we have to do a little extra work.
                         let pointer_id = self
@@ -890,6 +1302,15 @@ impl<T: 'static> EventProcessor<T> {
                             .borrow_mut()
                             .unfocus(xev.event)
                             .expect("Failed to unfocus input context");
+                        // The keys released while unfocused aren't reported.
+                        self.held_keys.borrow_mut().remove(&xev.event);
+                        if let Some(ref mut kbd_state) = *wt.kbd_state.borrow_mut() {
+                            kbd_state.clear_modifier_keys();
+                        }
+                        self.report_modifiers(xev.event, ModifiersState::default(), &mut callback);
+                        if self.active_window.get() == Some(xev.event) {
+                            self.active_window.set(None);
+                        }
                         callback(Event::WindowEvent {
                             window_id: mkwid(xev.event),
                             event: Focused(false),
@@ -912,18 +1333,113 @@ impl<T: 'static> EventProcessor<T> {
                                 (xev.event_x as f64, xev.event_y as f64),
                                 dpi_factor,
                             );
+                            let screen_width = unsafe {
+                                (wt.xconn.xlib.XDisplayWidth)(
+                                    wt.xconn.display,
+                                    (wt.xconn.xlib.XDefaultScreen)(wt.xconn.display),
+                                )
+                            };
+                            let (force, contact) = self
+                                .devices
+                                .borrow()
+                                .get(&DeviceId(xev.sourceid))
+                                .map(|device| {
+                                    device
+                                        .touch_axes
+                                        .touch_data(&xev.valuators, screen_width as f64)
+                                })
+                                .unwrap_or_default();
+                            let contact = contact.map(|contact| TouchContact {
+                                major: contact.major / dpi_factor,
+                                minor: contact.minor / dpi_factor,
+                                ..contact
+                            });
                             callback(Event::WindowEvent {
                                 window_id,
                                 event: WindowEvent::Touch(Touch {
                                     device_id: mkdid(xev.deviceid),
                                     phase,
                                     location,
+                                    force,
+                                    contact,
                                     id: xev.detail as u64,
                                 }),
                             })
                         }
                     }
 
+                    ffi::XI_GesturePinchBegin
+                    | ffi::XI_GesturePinchUpdate
+                    | ffi::XI_GesturePinchEnd => {
+                        let xev: &ffi::XIGesturePinchEvent = unsafe { &*(xev.data as *const _) };
+                        let phase = match xev.evtype {
+                            ffi::XI_GesturePinchBegin => TouchPhase::Started,
+                            ffi::XI_GesturePinchUpdate => TouchPhase::Moved,
+                            _ if xev.flags & ffi::XIGesturePinchEventCancelled != 0 => {
+                                TouchPhase::Cancelled
+                            }
+                            _ => TouchPhase::Ended,
+                        };
+                        let previous_scale = {
+                            let mut pinch_scales = self.pinch_scales.borrow_mut();
+                            let previous = pinch_scales
+                                .remove(&xev.deviceid)
+                                .filter(|_| phase != TouchPhase::Started);
+                            if phase == TouchPhase::Started || phase == TouchPhase::Moved {
+                                pinch_scales.insert(xev.deviceid, xev.scale);
+                            }
+                            previous.unwrap_or(1.0)
+                        };
+                        let window_id = mkwid(xev.event);
+                        let device_id = mkdid(xev.deviceid);
+                        callback(Event::WindowEvent {
+                            window_id,
+                            event: WindowEvent::PinchGesture {
+                                device_id,
+                                delta: xev.scale - previous_scale,
+                                phase,
+                            },
+                        });
+                        callback(Event::WindowEvent {
+                            window_id,
+                            event: WindowEvent::RotationGesture {
+                                device_id,
+                                delta: xev.delta_angle,
+                                phase,
+                            },
+                        });
+                    }
+
+                    ffi::XI_GestureSwipeBegin
+                    | ffi::XI_GestureSwipeUpdate
+                    | ffi::XI_GestureSwipeEnd => {
+                        let xev: &ffi::XIGestureSwipeEvent = unsafe { &*(xev.data as *const _) };
+                        let phase = match xev.evtype {
+                            ffi::XI_GestureSwipeBegin => TouchPhase::Started,
+                            ffi::XI_GestureSwipeUpdate => TouchPhase::Moved,
+                            _ if xev.flags & ffi::XIGestureSwipeEventCancelled != 0 => {
+                                TouchPhase::Cancelled
+                            }
+                            _ => TouchPhase::Ended,
+                        };
+                        let dpi_factor =
+                            self.with_window(xev.event, |window| window.hidpi_factor());
+                        if let Some(dpi_factor) = dpi_factor {
+                            callback(Event::WindowEvent {
+                                window_id: mkwid(xev.event),
+                                event: WindowEvent::SwipeGesture {
+                                    device_id: mkdid(xev.deviceid),
+                                    fingers: xev.detail as u32,
+                                    delta: LogicalPosition::from_physical(
+                                        (xev.delta_x, xev.delta_y),
+                                        dpi_factor,
+                                    ),
+                                    phase,
+                                },
+                            });
+                        }
+                    }
+
                     ffi::XI_RawButtonPress | ffi::XI_RawButtonRelease => {
                         let xev: &ffi::XIRawEvent = unsafe { &*(xev.data as *const _) };
                         if xev.flags & ffi::XIPointerEmulated == 0 {
@@ -1019,7 +1535,7 @@ impl<T: 'static> EventProcessor<T> {
                             .check_errors()
                             .expect("Failed to lookup raw keysym");
 
-                        let virtual_keycode = events::keysym_to_element(keysym as c_uint);
+                        let virtual_keycode = keyboard::virtual_keycode(scancode, keysym as u32);
 
                         callback(Event::DeviceEvent {
                             device_id: mkdid(device_id),
@@ -1027,12 +1543,14 @@ impl<T: 'static> EventProcessor<T> {
                                 scancode,
                                 virtual_keycode,
                                 state,
-                                // So, in an ideal world we can use libxkbcommon to get modifiers.
-                                // However, libxkbcommon-x11 isn't as commonly installed as one
-                                // would hope. We can still use the Xkb extension to get
-                                // comprehensive keyboard state updates, but interpreting that
-                                // info manually is going to be involved.
-                                modifiers: ModifiersState::default(),
+                                // Without libxkbcommon-x11, the modifiers are unknown.
+                                modifiers: wt
+                                    .kbd_state
+                                    .borrow()
+                                    .as_ref()
+                                    .map(KbdState::mods_state)
+                                    .unwrap_or_default(),
+                                is_repeat: xev.flags & ffi::XIKeyRepeat != 0,
                             }),
                         });
                     }
@@ -1064,7 +1582,12 @@ impl<T: 'static> EventProcessor<T> {
                 }
             }
             _ => {
-                if event_type == self.randr_event_offset {
+                if event_type == self.xkb_event_offset {
+                    self.process_xkb_event(xev);
+                    if let Some(window) = self.active_window.get() {
+                        self.report_keyboard_state(window, &mut callback);
+                    }
+                } else if event_type == self.randr_event_offset {
                     // In the future, it would be quite easy to emit monitor hotplug events.
                     let prev_list = monitor::invalidate_cached_monitor_list();
                     if let Some(prev_list) = prev_list {
@@ -1095,6 +1618,7 @@ impl<T: 'static> EventProcessor<T> {
                                                         height as f64,
                                                     );
                                                     flusher.queue();
+                                                    window.refresh_cursor(new_monitor.hidpi_factor);
                                                 }
                                             }
                                         }
@@ -1106,11 +1630,100 @@ impl<T: 'static> EventProcessor<T> {
             }
         }
 
-        match self.ime_receiver.try_recv() {
-            Ok((window_id, x, y)) => {
-                wt.ime.borrow_mut().send_xim_spot(window_id, x, y);
+        while let Ok(request) = self.ime_receiver.try_recv() {
+            match request {
+                ImeRequest::CursorArea(window, area) => {
+                    wt.ime.borrow_mut().send_xim_area(window, area);
+                }
+                ImeRequest::Allowed(window, allowed) => {
+                    let mut ime = wt.ime.borrow_mut();
+                    if allowed == ime.is_allowed(window) || !self.window_exists(window) {
+                        continue;
+                    }
+                    if allowed {
+                        ime.create_context(window)
+                            .expect("Failed to create input context");
+                        // The context of the focused window has to be focused as well.
+                        let focus = unsafe {
+                            let mut focus = 0;
+                            let mut revert_to = 0;
+                            (wt.xconn.xlib.XGetInputFocus)(
+                                wt.xconn.display,
+                                &mut focus,
+                                &mut revert_to,
+                            );
+                            focus
+                        };
+                        if focus == window {
+                            ime.focus(window).expect("Failed to focus input context");
+                        }
+                    } else {
+                        ime.remove_context(window)
+                            .expect("Failed to destroy input context");
+                    }
+                }
             }
-            Err(_) => (),
         }
+
+        self.process_ime_events(&mut callback);
+    }
+
+    // Sends the changes to the input method state of the windows, as reported by their contexts.
+    fn process_ime_events<F>(&self, callback: &mut F)
+    where
+        F: FnMut(Event<T>),
+    {
+        while let Ok((window, event)) = self.ime_event_receiver.try_recv() {
+            let event = match event {
+                ImeEvent::Enabled => Ime::Enabled,
+                ImeEvent::Start => {
+                    self.composing.borrow_mut().insert(window);
+                    Ime::Preedit(String::new(), None)
+                }
+                ImeEvent::Update(text, (start, end))
+                    if self.composing.borrow().contains(&window) =>
+                {
+                    // The range is given in characters, but in bytes to the application.
+                    let byte_index = |index| {
+                        text.char_indices()
+                            .nth(index)
+                            .map(|(index, _)| index)
+                            .unwrap_or_else(|| text.len())
+                    };
+                    let cursor = (byte_index(start), byte_index(end));
+                    Ime::Preedit(text, Some(cursor))
+                }
+                ImeEvent::Update(..) => continue,
+                ImeEvent::End => {
+                    self.composing.borrow_mut().remove(&window);
+                    Ime::Preedit(String::new(), None)
+                }
+                ImeEvent::Disabled => {
+                    // A composition in progress is abandoned along with the input method.
+                    if self.composing.borrow_mut().remove(&window) {
+                        callback(Event::WindowEvent {
+                            window_id: mkwid(window),
+                            event: WindowEvent::Ime(Ime::Preedit(String::new(), None)),
+                        });
+                    }
+                    Ime::Disabled
+                }
+            };
+            callback(Event::WindowEvent {
+                window_id: mkwid(window),
+                event: WindowEvent::Ime(event),
+            });
+        }
+    }
+}
+
+// Routes the outcome of a selection transfer to whoever requested it.
+pub(super) fn transfer_event<T>(requester: Requester, event: SelectionEvent) -> Event<T> {
+    match requester {
+        Requester::Application(selection) => Event::SelectionEvent { selection, event },
+        Requester::Drop { window, .. } => Event::WindowEvent {
+            window_id: mkwid(window),
+            event: WindowEvent::DragData(event),
+        },
     }
 }
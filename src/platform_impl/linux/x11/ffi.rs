@@ -1,4 +1,93 @@
+#![allow(non_upper_case_globals)]
+
+use std::os::raw::{c_double, c_int, c_ulong};
+
 pub use x11_dl::{
     error::OpenError, keysym::*, xcursor::*, xinput::*, xinput2::*, xlib::*, xlib_xcb::*,
     xrandr::*, xrender::*,
 };
+
+// The styles of the characters being composed with an input method, which x11-dl doesn't define.
+pub const XIMReverse: XIMFeedback = 1;
+pub const XIMHighlight: XIMFeedback = 1 << 2;
+
+// The touchpad gestures of XInput 2.4, which x11-dl doesn't define.
+pub const XI_2_4_Minor: c_int = 4;
+
+pub const XI_GesturePinchBegin: c_int = 27;
+pub const XI_GesturePinchUpdate: c_int = 28;
+pub const XI_GesturePinchEnd: c_int = 29;
+pub const XI_GestureSwipeBegin: c_int = 30;
+pub const XI_GestureSwipeUpdate: c_int = 31;
+pub const XI_GestureSwipeEnd: c_int = 32;
+
+// The masks of the gestures don't fit in the `c_int` of the other masks.
+pub const XI_GesturePinchBeginMask: i64 = 1 << XI_GesturePinchBegin;
+pub const XI_GesturePinchUpdateMask: i64 = 1 << XI_GesturePinchUpdate;
+pub const XI_GesturePinchEndMask: i64 = 1 << XI_GesturePinchEnd;
+pub const XI_GestureSwipeBeginMask: i64 = 1 << XI_GestureSwipeBegin;
+pub const XI_GestureSwipeUpdateMask: i64 = 1 << XI_GestureSwipeUpdate;
+pub const XI_GestureSwipeEndMask: i64 = 1 << XI_GestureSwipeEnd;
+
+pub const XIGesturePinchEventCancelled: c_int = 1 << 0;
+pub const XIGestureSwipeEventCancelled: c_int = 1 << 0;
+
+#[derive(Debug, Clone, Copy)]
+#[repr(C)]
+pub struct XIGesturePinchEvent {
+    pub _type: c_int,
+    pub serial: c_ulong,
+    pub send_event: Bool,
+    pub display: *mut Display,
+    pub extension: c_int,
+    pub evtype: c_int,
+    pub time: Time,
+    pub deviceid: c_int,
+    pub sourceid: c_int,
+    pub detail: c_int,
+    pub root: Window,
+    pub event: Window,
+    pub child: Window,
+    pub root_x: c_double,
+    pub root_y: c_double,
+    pub event_x: c_double,
+    pub event_y: c_double,
+    pub delta_x: c_double,
+    pub delta_y: c_double,
+    pub delta_unaccel_x: c_double,
+    pub delta_unaccel_y: c_double,
+    pub scale: c_double,
+    pub delta_angle: c_double,
+    pub flags: c_int,
+    pub mods: XIModifierState,
+    pub group: XIGroupState,
+}
+
+#[derive(Debug, Clone, Copy)]
+#[repr(C)]
+pub struct XIGestureSwipeEvent {
+    pub _type: c_int,
+    pub serial: c_ulong,
+    pub send_event: Bool,
+    pub display: *mut Display,
+    pub extension: c_int,
+    pub evtype: c_int,
+    pub time: Time,
+    pub deviceid: c_int,
+    pub sourceid: c_int,
+    pub detail: c_int,
+    pub root: Window,
+    pub event: Window,
+    pub child: Window,
+    pub root_x: c_double,
+    pub root_y: c_double,
+    pub event_x: c_double,
+    pub event_y: c_double,
+    pub delta_x: c_double,
+    pub delta_y: c_double,
+    pub delta_unaccel_x: c_double,
+    pub delta_unaccel_y: c_double,
+    pub flags: c_int,
+    pub mods: XIModifierState,
+    pub group: XIGroupState,
+}
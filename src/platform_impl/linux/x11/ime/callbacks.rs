@@ -1,6 +1,6 @@
-use std::{collections::HashMap, os::raw::c_char, ptr, sync::Arc};
+use std::{collections::HashMap, mem, os::raw::c_char, ptr, sync::Arc};
 
-use super::{ffi, XConnection, XError};
+use super::{ffi, ImeEvent, XConnection, XError};
 
 use super::{
     context::{ImeContext, ImeContextCreationError},
@@ -108,7 +108,8 @@ unsafe fn replace_im(inner: *mut ImeInner) -> Result<(), ReplaceImError> {
     for (window, old_context) in (*inner).contexts.iter() {
         let spot = old_context.as_ref().map(|old_context| old_context.ic_spot);
         let new_context = {
-            let result = ImeContext::new(xconn, new_im.im, *window, spot);
+            let event_sender = (*inner).event_sender.clone();
+            let result = ImeContext::new(xconn, new_im.im, *window, spot, event_sender);
             if result.is_err() {
                 let _ = close_im(xconn, new_im.im);
             }
@@ -121,9 +122,18 @@ unsafe fn replace_im(inner: *mut ImeInner) -> Result<(), ReplaceImError> {
     let _ = (*inner).destroy_all_contexts_if_necessary();
     let _ = (*inner).close_im_if_necessary();
     (*inner).im = new_im.im;
-    (*inner).contexts = new_contexts;
+    let old_contexts = mem::replace(&mut (*inner).contexts, new_contexts);
     (*inner).is_destroyed = false;
     (*inner).is_fallback = is_fallback;
+
+    // The windows lose the state of their input method, including the text being composed.
+    let event_sender = &(*inner).event_sender;
+    for (&window, old_context) in &old_contexts {
+        if old_context.is_some() {
+            let _ = event_sender.send((window, ImeEvent::Disabled));
+        }
+        let _ = event_sender.send((window, ImeEvent::Enabled));
+    }
     Ok(())
 }
 
@@ -1,10 +1,11 @@
 use std::{
+    ffi::CStr,
     os::raw::{c_short, c_void},
-    ptr,
+    ptr, slice,
     sync::Arc,
 };
 
-use super::{ffi, util, XConnection, XError};
+use super::{ffi, util, ImeEvent, ImeEventSender, XConnection, XError};
 
 #[derive(Debug)]
 pub enum ImeContextCreationError {
@@ -12,6 +13,180 @@ pub enum ImeContextCreationError {
     Null,
 }
 
+// The state of the text being composed, shared with the preedit callbacks of the context.
+struct ImeContextClientData {
+    window: ffi::Window,
+    event_sender: ImeEventSender,
+    text: Vec<char>,
+    // The style of each character of the text.
+    feedback: Vec<ffi::XIMFeedback>,
+    cursor_pos: usize,
+}
+
+impl ImeContextClientData {
+    fn send(&self, event: ImeEvent) {
+        let _ = self.event_sender.send((self.window, event));
+    }
+
+    fn clear(&mut self) {
+        self.text.clear();
+        self.feedback.clear();
+        self.cursor_pos = 0;
+    }
+
+    // The cursor covers the highlighted characters, which input methods use for the part of the
+    // text being converted, and is collapsed at the caret otherwise.
+    fn send_update(&self) {
+        let is_highlighted =
+            |feedback: &ffi::XIMFeedback| feedback & (ffi::XIMReverse | ffi::XIMHighlight) != 0;
+        let cursor = match self.feedback.iter().position(is_highlighted) {
+            Some(start) => {
+                let end = self.feedback.iter().rposition(is_highlighted).unwrap() + 1;
+                (start, end)
+            }
+            None => (self.cursor_pos, self.cursor_pos),
+        };
+        self.send(ImeEvent::Update(self.text.iter().collect(), cursor));
+    }
+}
+
+// The preedit callbacks of a context. Their client data must stay at the same place in memory for
+// as long as the context exists.
+struct PreeditCallbacks {
+    _client_data: Box<ImeContextClientData>,
+    start_callback: ffi::XICCallback,
+    done_callback: ffi::XICCallback,
+    draw_callback: ffi::XICCallback,
+    caret_callback: ffi::XICCallback,
+}
+
+impl PreeditCallbacks {
+    fn new(client_data: ImeContextClientData) -> Self {
+        let mut client_data = Box::new(client_data);
+        let client_data_ptr = &mut *client_data as *mut ImeContextClientData as ffi::XPointer;
+        let callback = |callback| ffi::XICCallback {
+            client_data: client_data_ptr,
+            callback: Some(callback),
+        };
+        PreeditCallbacks {
+            start_callback: callback(preedit_start_callback),
+            done_callback: callback(preedit_done_callback),
+            draw_callback: callback(preedit_draw_callback),
+            caret_callback: callback(preedit_caret_callback),
+            _client_data: client_data,
+        }
+    }
+}
+
+// The composition starts, with no text yet.
+unsafe extern "C" fn preedit_start_callback(
+    _ic: ffi::XIC,
+    client_data: ffi::XPointer,
+    _call_data: ffi::XPointer,
+) -> ffi::Bool {
+    let client_data = &mut *(client_data as *mut ImeContextClientData);
+    client_data.clear();
+    client_data.send(ImeEvent::Start);
+    // The length of the text isn't limited.
+    -1
+}
+
+unsafe extern "C" fn preedit_done_callback(
+    _ic: ffi::XIC,
+    client_data: ffi::XPointer,
+    _call_data: ffi::XPointer,
+) -> ffi::Bool {
+    let client_data = &mut *(client_data as *mut ImeContextClientData);
+    client_data.clear();
+    client_data.send(ImeEvent::End);
+    ffi::True
+}
+
+// Replaces part of the text, and moves the cursor.
+unsafe extern "C" fn preedit_draw_callback(
+    _ic: ffi::XIC,
+    client_data: ffi::XPointer,
+    call_data: ffi::XPointer,
+) -> ffi::Bool {
+    let client_data = &mut *(client_data as *mut ImeContextClientData);
+    let call_data = &*(call_data as *const ffi::XIMPreeditDrawCallbackStruct);
+
+    let start = call_data.chg_first as usize;
+    let end = start + call_data.chg_length as usize;
+    if call_data.chg_first < 0 || call_data.chg_length < 0 || end > client_data.text.len() {
+        return ffi::False;
+    }
+    if call_data.text.is_null() {
+        // The changed part of the text was deleted.
+        client_data.text.drain(start..end);
+        client_data.feedback.drain(start..end);
+    } else {
+        let text = &*call_data.text;
+        // Without a string, only the styling of the text changed.
+        let new_text: Option<Vec<char>> = if text.string.multi_byte.is_null() {
+            None
+        } else if text.encoding_is_wchar == ffi::True {
+            // x11-dl declares the wide string as a single character instead of a pointer, so we
+            // read the pointer through the other member of the union. `wchar_t` is UTF-32 on the
+            // platforms using X11.
+            let wide_char = text.string.multi_byte as *const u32;
+            let wide_text = slice::from_raw_parts(wide_char, text.length as usize);
+            Some(
+                wide_text
+                    .iter()
+                    .filter_map(|&c| char::from_u32(c))
+                    .collect(),
+            )
+        } else {
+            let string = CStr::from_ptr(text.string.multi_byte).to_string_lossy();
+            Some(string.chars().collect())
+        };
+        let new_feedback = if text.feedback.is_null() {
+            Vec::new()
+        } else {
+            slice::from_raw_parts(text.feedback, text.length as usize).to_vec()
+        };
+        match new_text {
+            Some(new_text) => {
+                let len = new_text.len();
+                client_data.text.splice(start..end, new_text);
+                client_data.feedback.splice(
+                    start..end,
+                    new_feedback
+                        .into_iter()
+                        .chain(std::iter::repeat(0))
+                        .take(len),
+                );
+            }
+            None => {
+                let feedback = &mut client_data.feedback[start..end];
+                for (old, new) in feedback.iter_mut().zip(new_feedback) {
+                    *old = new;
+                }
+            }
+        }
+    }
+    client_data.cursor_pos = (call_data.caret.max(0) as usize).min(client_data.text.len());
+    client_data.send_update();
+    ffi::True
+}
+
+unsafe extern "C" fn preedit_caret_callback(
+    _ic: ffi::XIC,
+    client_data: ffi::XPointer,
+    call_data: ffi::XPointer,
+) -> ffi::Bool {
+    let client_data = &mut *(client_data as *mut ImeContextClientData);
+    let call_data = &mut *(call_data as *mut ffi::XIMPreeditCaretCallbackStruct);
+
+    // Only absolute positions are given by the input methods out there.
+    if call_data.direction == ffi::XIMCaretDirection::XIMAbsolutePosition {
+        client_data.cursor_pos = (call_data.position.max(0) as usize).min(client_data.text.len());
+        client_data.send_update();
+    }
+    ffi::True
+}
+
 unsafe fn create_pre_edit_attr<'a>(
     xconn: &'a Arc<XConnection>,
     ic_spot: &'a ffi::XPoint,
@@ -28,14 +203,41 @@ unsafe fn create_pre_edit_attr<'a>(
     .expect("XVaCreateNestedList returned NULL")
 }
 
+unsafe fn create_pre_edit_attr_with_callbacks<'a>(
+    xconn: &'a Arc<XConnection>,
+    ic_spot: &'a ffi::XPoint,
+    callbacks: &'a PreeditCallbacks,
+) -> util::XSmartPointer<'a, c_void> {
+    util::XSmartPointer::new(
+        xconn,
+        (xconn.xlib.XVaCreateNestedList)(
+            0,
+            ffi::XNSpotLocation_0.as_ptr() as *const _,
+            ic_spot,
+            ffi::XNPreeditStartCallback_0.as_ptr() as *const _,
+            &callbacks.start_callback,
+            ffi::XNPreeditDoneCallback_0.as_ptr() as *const _,
+            &callbacks.done_callback,
+            ffi::XNPreeditDrawCallback_0.as_ptr() as *const _,
+            &callbacks.draw_callback,
+            ffi::XNPreeditCaretCallback_0.as_ptr() as *const _,
+            &callbacks.caret_callback,
+            ptr::null_mut::<()>(),
+        ),
+    )
+    .expect("XVaCreateNestedList returned NULL")
+}
+
 // WARNING: this struct doesn't destroy its XIC resource when dropped.
 // This is intentional, as it doesn't have enough information to know whether or not the context
 // still exists on the server. Since `ImeInner` has that awareness, destruction must be handled
 // through `ImeInner`.
-#[derive(Debug)]
 pub struct ImeContext {
     pub ic: ffi::XIC,
     pub ic_spot: ffi::XPoint,
+    ic_area: Option<ffi::XRectangle>,
+    // Only set if the input method lets us draw the text being composed ("on-the-spot" style).
+    _callbacks: Option<PreeditCallbacks>,
 }
 
 impl ImeContext {
@@ -44,12 +246,31 @@ impl ImeContext {
         im: ffi::XIM,
         window: ffi::Window,
         ic_spot: Option<ffi::XPoint>,
+        event_sender: ImeEventSender,
     ) -> Result<Self, ImeContextCreationError> {
-        let ic = if let Some(ic_spot) = ic_spot {
-            ImeContext::create_ic_with_spot(xconn, im, window, ic_spot)
-        } else {
-            ImeContext::create_ic(xconn, im, window)
-        };
+        let spot = ic_spot.unwrap_or_else(|| ffi::XPoint { x: 0, y: 0 });
+        let callbacks = PreeditCallbacks::new(ImeContextClientData {
+            window,
+            event_sender,
+            text: Vec::new(),
+            feedback: Vec::new(),
+            cursor_pos: 0,
+        });
+
+        // Not all input methods support drawing the text being composed ourselves, in which case
+        // they draw it instead.
+        let (ic, callbacks) =
+            match ImeContext::create_ic_with_callbacks(xconn, im, window, spot, &callbacks) {
+                Some(ic) => (Some(ic), Some(callbacks)),
+                None => {
+                    let ic = if let Some(ic_spot) = ic_spot {
+                        ImeContext::create_ic_with_spot(xconn, im, window, ic_spot)
+                    } else {
+                        ImeContext::create_ic(xconn, im, window)
+                    };
+                    (ic, None)
+                }
+            };
 
         let ic = ic.ok_or(ImeContextCreationError::Null)?;
         xconn
@@ -58,7 +279,9 @@ impl ImeContext {
 
         Ok(ImeContext {
             ic,
-            ic_spot: ic_spot.unwrap_or_else(|| ffi::XPoint { x: 0, y: 0 }),
+            ic_spot: spot,
+            ic_area: None,
+            _callbacks: callbacks,
         })
     }
 
@@ -106,6 +329,33 @@ impl ImeContext {
         }
     }
 
+    unsafe fn create_ic_with_callbacks(
+        xconn: &Arc<XConnection>,
+        im: ffi::XIM,
+        window: ffi::Window,
+        ic_spot: ffi::XPoint,
+        callbacks: &PreeditCallbacks,
+    ) -> Option<ffi::XIC> {
+        let pre_edit_attr = create_pre_edit_attr_with_callbacks(xconn, &ic_spot, callbacks);
+        let ic = (xconn.xlib.XCreateIC)(
+            im,
+            ffi::XNInputStyle_0.as_ptr() as *const _,
+            ffi::XIMPreeditCallbacks | ffi::XIMStatusNothing,
+            ffi::XNClientWindow_0.as_ptr() as *const _,
+            window,
+            ffi::XNPreeditAttributes_0.as_ptr() as *const _,
+            pre_edit_attr.ptr,
+            ptr::null_mut::<()>(),
+        );
+        if ic.is_null() {
+            // The failure is expected if the input method doesn't support this style.
+            let _ = xconn.check_errors();
+            None
+        } else {
+            Some(ic)
+        }
+    }
+
     pub fn focus(&self, xconn: &Arc<XConnection>) -> Result<(), XError> {
         unsafe {
             (xconn.xlib.XSetICFocus)(self.ic);
@@ -120,7 +370,38 @@ impl ImeContext {
         xconn.check_errors()
     }
 
-    pub fn set_spot(&mut self, xconn: &Arc<XConnection>, x: c_short, y: c_short) {
+    // The candidate window is placed below the spot, which is put at the bottom of the area, while
+    // only some input methods make use of the area itself.
+    pub fn set_area(&mut self, xconn: &Arc<XConnection>, area: ffi::XRectangle) {
+        let bottom = area.y.saturating_add(area.height.min(0x7fff) as c_short);
+        self.set_spot(xconn, area.x, bottom);
+
+        if self.ic_area == Some(area) {
+            return;
+        }
+        self.ic_area = Some(area);
+
+        unsafe {
+            let pre_edit_attr = util::XSmartPointer::new(
+                xconn,
+                (xconn.xlib.XVaCreateNestedList)(
+                    0,
+                    ffi::XNArea_0.as_ptr() as *const _,
+                    &area,
+                    ptr::null_mut::<()>(),
+                ),
+            )
+            .expect("XVaCreateNestedList returned NULL");
+            (xconn.xlib.XSetICValues)(
+                self.ic,
+                ffi::XNPreeditAttributes_0.as_ptr() as *const _,
+                pre_edit_attr.ptr,
+                ptr::null_mut::<()>(),
+            );
+        }
+    }
+
+    fn set_spot(&mut self, xconn: &Arc<XConnection>, x: c_short, y: c_short) {
         if self.ic_spot.x == x && self.ic_spot.y == y {
             return;
         }
@@ -137,3 +418,12 @@ impl ImeContext {
         }
     }
 }
+
+impl ::std::fmt::Debug for ImeContext {
+    fn fmt(&self, f: &mut ::std::fmt::Formatter<'_>) -> ::std::fmt::Result {
+        f.debug_struct("ImeContext")
+            .field("ic", &self.ic)
+            .field("ic_spot", &self.ic_spot)
+            .finish()
+    }
+}
@@ -20,8 +20,32 @@ use self::{
     input_method::PotentialInputMethods,
 };
 
-pub type ImeReceiver = Receiver<(ffi::Window, i16, i16)>;
-pub type ImeSender = Sender<(ffi::Window, i16, i16)>;
+pub type ImeReceiver = Receiver<ImeRequest>;
+pub type ImeSender = Sender<ImeRequest>;
+pub type ImeEventReceiver = Receiver<(ffi::Window, ImeEvent)>;
+pub type ImeEventSender = Sender<(ffi::Window, ImeEvent)>;
+
+/// The changes requested by the windows to their input contexts, which are applied from the event
+/// loop.
+#[derive(Debug, Clone, Copy)]
+pub enum ImeRequest {
+    // The area of the text cursor, in physical pixels.
+    CursorArea(ffi::Window, ffi::XRectangle),
+    // Whether an input method may be used in the window.
+    Allowed(ffi::Window, bool),
+}
+
+/// The changes to the input method state of a window.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ImeEvent {
+    Enabled,
+    Start,
+    // The text being composed, and the range of characters the cursor covers in it, which is
+    // collapsed at the caret unless the input method highlights part of the text.
+    Update(String, (usize, usize)),
+    End,
+    Disabled,
+}
 
 #[derive(Debug)]
 pub enum ImeCreationError {
@@ -37,11 +61,14 @@ pub struct Ime {
 }
 
 impl Ime {
-    pub fn new(xconn: Arc<XConnection>) -> Result<Self, ImeCreationError> {
+    pub fn new(
+        xconn: Arc<XConnection>,
+        event_sender: ImeEventSender,
+    ) -> Result<Self, ImeCreationError> {
         let potential_input_methods = PotentialInputMethods::new(&xconn);
 
         let (mut inner, client_data) = {
-            let mut inner = Box::new(ImeInner::new(xconn, potential_input_methods));
+            let mut inner = Box::new(ImeInner::new(xconn, potential_input_methods, event_sender));
             let inner_ptr = Box::into_raw(inner);
             let client_data = inner_ptr as _;
             let destroy_callback = ffi::XIMCallback {
@@ -93,12 +120,23 @@ impl Ime {
             // Create empty entry in map, so that when IME is rebuilt, this window has a context.
             None
         } else {
-            Some(unsafe { ImeContext::new(&self.inner.xconn, self.inner.im, window, None) }?)
+            let event_sender = self.inner.event_sender.clone();
+            Some(unsafe {
+                ImeContext::new(&self.inner.xconn, self.inner.im, window, None, event_sender)
+            }?)
         };
+        if context.is_some() {
+            let _ = self.inner.event_sender.send((window, ImeEvent::Enabled));
+        }
         self.inner.contexts.insert(window, context);
         Ok(!self.is_destroyed())
     }
 
+    // Whether the window has a context, or will have one once the input method is rebuilt.
+    pub fn is_allowed(&self, window: ffi::Window) -> bool {
+        self.inner.contexts.contains_key(&window)
+    }
+
     pub fn get_context(&self, window: ffi::Window) -> Option<ffi::XIC> {
         if self.is_destroyed() {
             return None;
@@ -112,6 +150,7 @@ impl Ime {
 
     pub fn remove_context(&mut self, window: ffi::Window) -> Result<bool, XError> {
         if let Some(Some(context)) = self.inner.contexts.remove(&window) {
+            let _ = self.inner.event_sender.send((window, ImeEvent::Disabled));
             unsafe {
                 self.inner.destroy_ic_if_necessary(context.ic)?;
             }
@@ -143,12 +182,12 @@ impl Ime {
         }
     }
 
-    pub fn send_xim_spot(&mut self, window: ffi::Window, x: i16, y: i16) {
+    pub fn send_xim_area(&mut self, window: ffi::Window, area: ffi::XRectangle) {
         if self.is_destroyed() {
             return;
         }
         if let Some(&mut Some(ref mut context)) = self.inner.contexts.get_mut(&window) {
-            context.set_spot(&self.xconn, x as _, y as _);
+            context.set_area(&self.xconn, area);
         }
     }
 }
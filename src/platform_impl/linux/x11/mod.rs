@@ -1,16 +1,18 @@
 #![cfg(any(target_os = "linux", target_os = "dragonfly", target_os = "freebsd", target_os = "netbsd", target_os = "openbsd"))]
 
 mod dnd;
+mod drag_source;
 mod event_processor;
-mod events;
 pub mod ffi;
 mod ime;
 mod monitor;
+mod selection;
 pub mod util;
 mod window;
 mod xdisplay;
 
 pub use self::{
+    dnd::DragOffer,
     monitor::{MonitorHandle, VideoMode},
     window::UnownedWindow,
     xdisplay::{XConnection, XError, XNotSupported},
@@ -26,20 +28,35 @@ use std::{
     rc::Rc,
     slice,
     sync::{mpsc, Arc, Mutex, Weak},
+    time::Duration,
 };
 
 use libc::{self, setlocale, LC_CTYPE};
 
 use self::{
-    dnd::{Dnd, DndState},
-    event_processor::EventProcessor,
-    ime::{Ime, ImeCreationError, ImeReceiver, ImeSender},
+    dnd::{Dnd, DndState, DropRequest},
+    drag_source::{DragRequest, DragSource},
+    event_processor::{transfer_event, EventProcessor},
+    ime::{Ime, ImeCreationError, ImeEvent, ImeEventReceiver, ImeReceiver, ImeRequest, ImeSender},
+    selection::{Requester, Selections},
 };
 use crate::{
-    error::OsError as RootOsError,
-    event::{Event, WindowEvent},
+    dpi::LogicalPosition,
+    error::{ExternalError, OsError as RootOsError},
+    event::{
+        Event, Selection, SelectionEvent, TabletTool, TabletToolState, TouchContact, WindowEvent,
+    },
     event_loop::{ControlFlow, EventLoopClosed, EventLoopWindowTarget as RootELW},
-    platform_impl::{platform::sticky_exit_callback, PlatformSpecificWindowBuilderAttributes},
+    keyboard::KeyRepeatInfo,
+    platform_impl::{
+        platform::{
+            gamepad::Gamepads,
+            sticky_exit_callback,
+            xkb::{self, KbdState},
+            CursorTheme,
+        },
+        OsError, PlatformSpecificWindowBuilderAttributes,
+    },
     window::WindowAttributes,
 };
 
@@ -50,8 +67,18 @@ pub struct EventLoopWindowTarget<T> {
     ime_sender: ImeSender,
     root: ffi::Window,
     ime: RefCell<Ime>,
+    // The keymap and the state of the core keyboard, which are only tracked with libxkbcommon-x11.
+    kbd_state: RefCell<Option<KbdState>>,
+    selections: RefCell<Selections>,
+    drag_requests: ::calloop::channel::Sender<DragRequest>,
     windows: RefCell<HashMap<WindowId, Weak<UnownedWindow>>>,
     pending_redraws: Arc<Mutex<HashSet<WindowId>>>,
+    cursor_theme: Arc<Mutex<CursorTheme>>,
+    // The cursor theme name and size set by the desktop, if any.
+    desktop_cursor_theme: (Option<String>, Option<u32>),
+    // Whether the server supports XInput 2.4, sending the touchpad gestures.
+    xinput_gestures: bool,
+    gamepads: Gamepads,
     _marker: ::std::marker::PhantomData<T>,
 }
 
@@ -59,6 +86,10 @@ pub struct EventLoop<T: 'static> {
     inner_loop: ::calloop::EventLoop<()>,
     _x11_source: ::calloop::Source<::calloop::generic::Generic<::calloop::generic::EventedRawFd>>,
     _user_source: ::calloop::Source<::calloop::channel::Channel<T>>,
+    _drop_request_source: ::calloop::Source<::calloop::channel::Channel<DropRequest>>,
+    _drag_request_source: ::calloop::Source<::calloop::channel::Channel<DragRequest>>,
+    _selection_failure_source:
+        ::calloop::Source<::calloop::channel::Channel<(Requester, SelectionEvent)>>,
     pending_user_events: Rc<RefCell<VecDeque<T>>>,
     event_processor: Rc<RefCell<EventProcessor<T>>>,
     user_sender: ::calloop::channel::Sender<T>,
@@ -86,17 +117,28 @@ impl<T: 'static> EventLoop<T> {
 
         let net_wm_ping = unsafe { xconn.get_atom_unchecked(b"_NET_WM_PING\0") };
 
-        let dnd = Dnd::new(Arc::clone(&xconn))
+        let (drop_request_sender, drop_request_channel) = ::calloop::channel::channel();
+        let dnd = Dnd::new(Arc::clone(&xconn), drop_request_sender)
             .expect("Failed to call XInternAtoms when initializing drag and drop");
 
+        let (drag_requests, drag_request_channel) = ::calloop::channel::channel();
+        let drag_source = DragSource::new(Arc::clone(&xconn), Arc::clone(&dnd.atoms), root);
+
+        let (selection_failure_sender, selection_failure_channel) = ::calloop::channel::channel();
+        let selections = RefCell::new(
+            Selections::new(Arc::clone(&xconn), root, selection_failure_sender)
+                .expect("Failed to create the window used for selection transfers"),
+        );
+
         let (ime_sender, ime_receiver) = mpsc::channel();
+        let (ime_event_sender, ime_event_receiver) = mpsc::channel();
         // Input methods will open successfully without setting the locale, but it won't be
         // possible to actually commit pre-edit sequences.
         unsafe {
             setlocale(LC_CTYPE, b"\0".as_ptr() as *const _);
         }
         let ime = RefCell::new({
-            let result = Ime::new(Arc::clone(&xconn));
+            let result = Ime::new(Arc::clone(&xconn), ime_event_sender);
             if let Err(ImeCreationError::OpenFailure(ref state)) = result {
                 panic!(format!("Failed to open input method: {:#?}", state));
             }
@@ -107,6 +149,14 @@ impl<T: 'static> EventLoop<T> {
             .select_xrandr_input(root)
             .expect("Failed to query XRandR extension");
 
+        // Without libxkbcommon-x11, keys are looked up with Xlib instead.
+        let (kbd_state, xkb_device_id, xkb_event_offset) = match init_kbd_state(&xconn) {
+            Some((kbd_state, device_id, event_offset)) => {
+                (Some(kbd_state), device_id, event_offset)
+            }
+            None => (None, -1, -1),
+        };
+
         let xi2ext = unsafe {
             let mut ext = XExtension::default();
 
@@ -125,9 +175,11 @@ impl<T: 'static> EventLoop<T> {
             ext
         };
 
-        unsafe {
+        // The touchpad gestures need XInput 2.4, while the server answers with the version it
+        // supports when it's older.
+        let xinput_gestures = unsafe {
             let mut xinput_major_ver = ffi::XI_2_Major;
-            let mut xinput_minor_ver = ffi::XI_2_Minor;
+            let mut xinput_minor_ver = ffi::XI_2_4_Minor;
             if (xconn.xinput2.XIQueryVersion)(
                 xconn.display,
                 &mut xinput_major_ver,
@@ -139,13 +191,63 @@ impl<T: 'static> EventLoop<T> {
                     xinput_major_ver, xinput_minor_ver,
                 );
             }
-        }
+            (xinput_major_ver, xinput_minor_ver) >= (2, ffi::XI_2_4_Minor)
+        };
 
         xconn.update_cached_wm_info(root);
 
+        let desktop_cursor_theme = {
+            let screen = unsafe { (xconn.xlib.XDefaultScreen)(xconn.display) };
+            let settings = xconn.get_xsettings(screen);
+            let name = match settings.get("Gtk/CursorThemeName") {
+                Some(util::XSetting::String(name)) => Some(name.clone()),
+                // The theme of the `Xcursor.theme` resource, owned by Xcursor.
+                _ => unsafe {
+                    let name = (xconn.xcursor.XcursorGetTheme)(xconn.display);
+                    if name.is_null() {
+                        None
+                    } else {
+                        Some(CStr::from_ptr(name).to_string_lossy().into_owned())
+                    }
+                },
+            };
+            let size = match settings.get("Gtk/CursorThemeSize") {
+                Some(&util::XSetting::Integer(size)) if size > 0 => Some(size as u32),
+                _ => None,
+            };
+            (name, size)
+        };
+        let cursor_theme = CursorTheme::new(
+            None,
+            None,
+            desktop_cursor_theme.0.clone(),
+            desktop_cursor_theme.1,
+        );
+
+        // A calloop event loop to drive us
+        let inner_loop = ::calloop::EventLoop::new().unwrap();
+
+        // The X11 events, and the gamepad events read from evdev.
+        let pending_events: Rc<RefCell<VecDeque<_>>> = Default::default();
+
+        let gamepads = Gamepads::new(inner_loop.handle(), {
+            let pending_events = pending_events.clone();
+            move |device_id, event| {
+                pending_events.borrow_mut().push_back(Event::DeviceEvent {
+                    device_id: crate::event::DeviceId(crate::platform_impl::DeviceId::Gamepad(
+                        device_id,
+                    )),
+                    event,
+                });
+            }
+        });
+
         let target = Rc::new(RootELW {
             p: super::EventLoopWindowTarget::X(EventLoopWindowTarget {
                 ime,
+                kbd_state: RefCell::new(kbd_state),
+                selections,
+                drag_requests,
                 root,
                 windows: Default::default(),
                 _marker: ::std::marker::PhantomData,
@@ -154,13 +256,14 @@ impl<T: 'static> EventLoop<T> {
                 wm_delete_window,
                 net_wm_ping,
                 pending_redraws: Default::default(),
+                cursor_theme: Arc::new(Mutex::new(cursor_theme)),
+                desktop_cursor_theme,
+                xinput_gestures,
+                gamepads,
             }),
             _marker: ::std::marker::PhantomData,
         });
 
-        // A calloop event loop to drive us
-        let inner_loop = ::calloop::EventLoop::new().unwrap();
-
         // Handle user events
         let pending_user_events = Rc::new(RefCell::new(VecDeque::new()));
         let pending_user_events2 = pending_user_events.clone();
@@ -176,15 +279,45 @@ impl<T: 'static> EventLoop<T> {
             })
             .unwrap();
 
-        // Handle X11 events
-        let pending_events: Rc<RefCell<VecDeque<_>>> = Default::default();
+        // Handle requests for the data of drag and drop operations, which can be made from any
+        // thread
+        let _drop_request_source = inner_loop
+            .handle()
+            .insert_source(drop_request_channel, {
+                let target = target.clone();
+                move |evt, &mut ()| {
+                    if let ::calloop::channel::Event::Msg(request) = evt {
+                        let DropRequest {
+                            window,
+                            time,
+                            mime_type,
+                        } = request;
+                        get_xtarget(&target)
+                            .selections
+                            .borrow_mut()
+                            .request(Requester::Drop { window, time }, mime_type);
+                    }
+                }
+            })
+            .unwrap();
 
+        // Handle X11 events
         let processor = EventProcessor {
             target: target.clone(),
             dnd,
+            drag_source,
             devices: Default::default(),
             randr_event_offset,
             ime_receiver,
+            ime_event_receiver,
+            composing: Default::default(),
+            held_keys: Default::default(),
+            xkb_device_id,
+            xkb_event_offset,
+            active_window: Default::default(),
+            reported_modifiers: Default::default(),
+            reported_layout: Default::default(),
+            pinch_scales: Default::default(),
             xi2ext,
         };
 
@@ -192,7 +325,7 @@ impl<T: 'static> EventLoop<T> {
         // (The request buffer is flushed during `init_device`)
         get_xtarget(&target)
             .xconn
-            .select_xinput_events(root, ffi::XIAllDevices, ffi::XI_HierarchyChangedMask)
+            .select_xinput_events(root, ffi::XIAllDevices, ffi::XI_HierarchyChangedMask.into())
             .queue();
 
         processor.init_device(ffi::XIAllDevices);
@@ -200,6 +333,38 @@ impl<T: 'static> EventLoop<T> {
         let processor = Rc::new(RefCell::new(processor));
         let event_processor = processor.clone();
 
+        // Handle requests to drag data out of our windows, which can be made from any thread
+        let _drag_request_source = inner_loop
+            .handle()
+            .insert_source(drag_request_channel, {
+                let processor = processor.clone();
+                let pending_events = pending_events.clone();
+                move |evt, &mut ()| {
+                    if let ::calloop::channel::Event::Msg(request) = evt {
+                        let mut pending_events = pending_events.borrow_mut();
+                        processor
+                            .borrow_mut()
+                            .start_drag(request, |event| pending_events.push_back(event));
+                    }
+                }
+            })
+            .unwrap();
+
+        // Deliver the failures of the selection transfers we couldn't even start
+        let _selection_failure_source = inner_loop
+            .handle()
+            .insert_source(selection_failure_channel, {
+                let pending_events = pending_events.clone();
+                move |evt, &mut ()| {
+                    if let ::calloop::channel::Event::Msg((requester, event)) = evt {
+                        pending_events
+                            .borrow_mut()
+                            .push_back(transfer_event(requester, event));
+                    }
+                }
+            })
+            .unwrap();
+
         // Setup the X11 event source
         let mut x11_events =
             ::calloop::generic::Generic::from_raw_fd(get_xtarget(&target).xconn.x11_fd);
@@ -223,6 +388,9 @@ impl<T: 'static> EventLoop<T> {
             pending_events,
             _x11_source,
             _user_source,
+            _drop_request_source,
+            _drag_request_source,
+            _selection_failure_source,
             user_sender,
             pending_user_events,
             event_processor,
@@ -420,6 +588,54 @@ impl<T> EventLoopWindowTarget<T> {
     pub fn x_connection(&self) -> &Arc<XConnection> {
         &self.xconn
     }
+
+    pub fn set_selection(
+        &self,
+        selection: Selection,
+        data: Vec<(String, Vec<u8>)>,
+    ) -> Result<(), ExternalError> {
+        self.selections
+            .borrow_mut()
+            .set(selection, data)
+            .map_err(|err| ExternalError::Os(os_error!(OsError::XMisc(err))))
+    }
+
+    pub fn set_cursor_theme(&self, name: Option<&str>, size: Option<u32>) {
+        let (ref desktop_name, desktop_size) = self.desktop_cursor_theme;
+        *self.cursor_theme.lock().unwrap() =
+            CursorTheme::new(name, size, desktop_name.clone(), desktop_size);
+        for window in self.windows.borrow().values().filter_map(Weak::upgrade) {
+            window.refresh_cursor(window.hidpi_factor());
+        }
+    }
+
+    pub fn request_selection(&self, selection: Selection, mime_type: String) {
+        self.selections
+            .borrow_mut()
+            .request(Requester::Application(selection), mime_type)
+    }
+
+    pub fn with_kbd_state<F, R>(&self, f: F) -> Option<R>
+    where
+        F: FnOnce(&KbdState) -> R,
+    {
+        self.kbd_state.borrow().as_ref().map(f)
+    }
+
+    pub fn key_repeat_info(&self) -> Option<KeyRepeatInfo> {
+        let (delay, interval) = self.xconn.auto_repeat_rate()?;
+        if interval == 0 {
+            return None;
+        }
+        Some(KeyRepeatInfo {
+            rate: 1000 / interval,
+            delay: Duration::from_millis(delay as u64),
+        })
+    }
+
+    pub fn gamepads(&self) -> &Gamepads {
+        &self.gamepads
+    }
 }
 
 impl<T: 'static> EventLoopProxy<T> {
@@ -561,6 +777,42 @@ struct XExtension {
     first_error_id: c_int,
 }
 
+// Loads the keymap of the core keyboard with xkbcommon-x11 and selects the XKB events telling when
+// it or its state changes. Returns the keyboard state along with the ID of the keyboard and the
+// offset of the XKB events.
+fn init_kbd_state(xconn: &XConnection) -> Option<(KbdState, c_int, c_int)> {
+    unsafe {
+        let connection = (xconn.xlib_xcb.XGetXCBConnection)(xconn.display);
+        let device_id = xkb::setup_x11(connection)?;
+        let mut kbd_state = KbdState::new()?;
+        if !kbd_state.set_keymap_from_x11(connection, device_id) {
+            return None;
+        }
+
+        let mut opcode = 0;
+        let mut event_offset = 0;
+        let mut error_offset = 0;
+        let mut major = 1;
+        let mut minor = 0;
+        let supported = (xconn.xlib.XkbQueryExtension)(
+            xconn.display,
+            &mut opcode,
+            &mut event_offset,
+            &mut error_offset,
+            &mut major,
+            &mut minor,
+        );
+        if supported != ffi::True {
+            return None;
+        }
+
+        let mask = ffi::XkbNewKeyboardNotifyMask | ffi::XkbMapNotifyMask | ffi::XkbStateNotifyMask;
+        xconn.select_xkb_events(device_id as c_uint, mask)?.queue();
+
+        Some((kbd_state, device_id, event_offset))
+    }
+}
+
 fn mkwid(w: ffi::Window) -> crate::window::WindowId {
     crate::window::WindowId(crate::platform_impl::WindowId::X(WindowId(w)))
 }
@@ -570,8 +822,10 @@ fn mkdid(w: c_int) -> crate::event::DeviceId {
 
 #[derive(Debug)]
 struct Device {
-    name: String,
+    _name: String,
     scroll_axes: Vec<(i32, ScrollAxis)>,
+    touch_axes: TouchAxes,
+    tablet: Option<TabletAxes>,
     // For master devices, this is the paired device (pointer <-> keyboard).
     // For slave devices, this is the master.
     attachment: c_int,
@@ -582,6 +836,8 @@ struct ScrollAxis {
     increment: f64,
     orientation: ScrollOrientation,
     position: f64,
+    // The fraction of a detent scrolled since the last whole one.
+    detent_remainder: f64,
 }
 
 #[derive(Debug, Copy, Clone)]
@@ -590,10 +846,135 @@ enum ScrollOrientation {
     Horizontal,
 }
 
+// The valuators of a touch device describing its touches, besides their position.
+#[derive(Debug, Default, Copy, Clone)]
+struct TouchAxes {
+    pressure: Option<Valuator>,
+    major: Option<Valuator>,
+    minor: Option<Valuator>,
+    orientation: Option<Valuator>,
+    // The x position of the touches, whose range covers the width of the screen.
+    position_x: Option<Valuator>,
+}
+
+// The valuators of a graphics tablet tool, which is told apart from a mouse by its pressure.
+#[derive(Debug, Copy, Clone)]
+struct TabletAxes {
+    tool: TabletTool,
+    pressure: Valuator,
+    distance: Option<Valuator>,
+    tilt_x: Option<Valuator>,
+    tilt_y: Option<Valuator>,
+    rotation: Option<Valuator>,
+    // The window the tool was last reported over, as XInput2 has no proximity events.
+    window: Option<ffi::Window>,
+}
+
+#[derive(Debug, Copy, Clone)]
+struct Valuator {
+    number: i32,
+    min: f64,
+    max: f64,
+}
+
+impl Valuator {
+    // The value of the valuator, if it's part of the event.
+    fn value(&self, valuators: &ffi::XIValuatorState) -> Option<f64> {
+        let mask = unsafe { slice::from_raw_parts(valuators.mask, valuators.mask_len as usize) };
+        if self.number >= valuators.mask_len * 8 || !ffi::XIMaskIsSet(mask, self.number) {
+            return None;
+        }
+        // The values are only given for the valuators set in the mask.
+        let index = (0..self.number)
+            .filter(|&i| ffi::XIMaskIsSet(mask, i))
+            .count();
+        Some(unsafe { *valuators.values.add(index) })
+    }
+
+    // The value of the valuator, from 0 to 1 over its range.
+    fn normalized(&self, valuators: &ffi::XIValuatorState) -> Option<f64> {
+        if self.max > self.min {
+            Some((self.value(valuators)? - self.min) / (self.max - self.min))
+        } else {
+            None
+        }
+    }
+}
+
+impl TouchAxes {
+    // The pressure and contact of a touch, with the size of the contact in physical pixels.
+    fn touch_data(
+        &self,
+        valuators: &ffi::XIValuatorState,
+        screen_width: f64,
+    ) -> (Option<f64>, Option<TouchContact>) {
+        let value = |valuator: Option<Valuator>| valuator?.value(valuators);
+        let force = self
+            .pressure
+            .and_then(|pressure| pressure.normalized(valuators));
+
+        let scale = match self.position_x {
+            Some(valuator) if valuator.max > valuator.min => {
+                screen_width / (valuator.max - valuator.min)
+            }
+            _ => return (force, None),
+        };
+        let contact = value(self.major).map(|major| {
+            // Without a minor axis, the contact is a circle.
+            let minor = value(self.minor).unwrap_or(major);
+            // The orientation ranges over a quarter or a half turn, depending on the device.
+            let orientation = match self.orientation {
+                Some(valuator) if valuator.max > 0.0 => value(self.orientation)
+                    .map_or(0.0, |orientation| orientation / valuator.max * 90.0),
+                _ => 0.0,
+            };
+            TouchContact {
+                major: major * scale,
+                minor: minor * scale,
+                orientation,
+            }
+        });
+        (force, contact)
+    }
+}
+
+impl TabletAxes {
+    fn state(
+        &self,
+        valuators: &ffi::XIValuatorState,
+        location: LogicalPosition,
+    ) -> TabletToolState {
+        let value = |valuator: Option<Valuator>| valuator?.value(valuators);
+        TabletToolState {
+            location,
+            pressure: self.pressure.normalized(valuators),
+            distance: self
+                .distance
+                .and_then(|distance| distance.normalized(valuators)),
+            // The drivers report the tilt in degrees.
+            tilt: match (value(self.tilt_x), value(self.tilt_y)) {
+                (Some(x), Some(y)) => Some((x, y)),
+                _ => None,
+            },
+            rotation: self
+                .rotation
+                .and_then(|rotation| rotation.normalized(valuators))
+                .map(|rotation| rotation * 360.0),
+        }
+    }
+}
+
 impl Device {
     fn new<T: 'static>(el: &EventProcessor<T>, info: &ffi::XIDeviceInfo) -> Self {
         let name = unsafe { CStr::from_ptr(info.name).to_string_lossy() };
         let mut scroll_axes = Vec::new();
+        let mut touch_axes = TouchAxes::default();
+        let mut is_touch_device = false;
+        let mut tablet_pressure = None;
+        let mut tablet_distance = None;
+        let mut tablet_tilt_x = None;
+        let mut tablet_tilt_y = None;
+        let mut tablet_rotation = None;
 
         let wt = get_xtarget(&el.target);
 
@@ -606,7 +987,7 @@ impl Device {
                 | ffi::XI_RawKeyReleaseMask;
             // The request buffer is flushed when we poll for events
             wt.xconn
-                .select_xinput_events(wt.root, info.deviceid, mask)
+                .select_xinput_events(wt.root, info.deviceid, mask.into())
                 .queue();
 
             // Identify scroll axes
@@ -627,17 +1008,78 @@ impl Device {
                                     _ => unreachable!(),
                                 },
                                 position: 0.0,
+                                detent_remainder: 0.0,
                             },
                         ));
                     }
+                    ffi::XIValuatorClass => {
+                        let info = unsafe {
+                            mem::transmute::<&ffi::XIAnyClassInfo, &ffi::XIValuatorClassInfo>(class)
+                        };
+                        let valuator = Some(Valuator {
+                            number: info.number,
+                            min: info.min,
+                            max: info.max,
+                        });
+                        // The valuators are labelled after the evdev axes of the device.
+                        let label = |name: &[u8]| unsafe { wt.xconn.get_atom_unchecked(name) };
+                        if info.label == label(b"Abs MT Pressure\0") {
+                            touch_axes.pressure = valuator;
+                        } else if info.label == label(b"Abs MT Touch Major\0") {
+                            touch_axes.major = valuator;
+                        } else if info.label == label(b"Abs MT Touch Minor\0") {
+                            touch_axes.minor = valuator;
+                        } else if info.label == label(b"Abs MT Orientation\0") {
+                            touch_axes.orientation = valuator;
+                        } else if info.label == label(b"Abs MT Position X\0") {
+                            touch_axes.position_x = valuator;
+                        } else if info.label == label(b"Abs Pressure\0") {
+                            tablet_pressure = valuator;
+                        } else if info.label == label(b"Abs Distance\0") {
+                            tablet_distance = valuator;
+                        } else if info.label == label(b"Abs Tilt X\0") {
+                            tablet_tilt_x = valuator;
+                        } else if info.label == label(b"Abs Tilt Y\0") {
+                            tablet_tilt_y = valuator;
+                        } else if info.label == label(b"Abs Rotary Z\0") {
+                            tablet_rotation = valuator;
+                        }
+                    }
+                    ffi::XITouchClass => is_touch_device = true,
                     _ => {}
                 }
             }
         }
 
+        let tablet = match tablet_pressure {
+            Some(pressure) if !is_touch_device => {
+                // The tools of a tablet are separate devices, only named after the tool.
+                let lowercase_name = name.to_lowercase();
+                let tool = if lowercase_name.contains("eraser") {
+                    TabletTool::Eraser
+                } else if lowercase_name.contains("cursor") {
+                    TabletTool::Mouse
+                } else {
+                    TabletTool::Pen
+                };
+                Some(TabletAxes {
+                    tool,
+                    pressure,
+                    distance: tablet_distance,
+                    tilt_x: tablet_tilt_x,
+                    tilt_y: tablet_tilt_y,
+                    rotation: tablet_rotation,
+                    window: None,
+                })
+            }
+            _ => None,
+        };
+
         let mut device = Device {
-            name: name.into_owned(),
+            _name: name.into_owned(),
             scroll_axes,
+            touch_axes,
+            tablet,
             attachment: info.attachment,
         };
         device.reset_scroll_position(info);
@@ -659,6 +1101,7 @@ impl Device {
                             .find(|&&mut (axis, _)| axis == info.number)
                         {
                             axis.position = info.value;
+                            axis.detent_remainder = 0.0;
                         }
                     }
                     _ => {}
@@ -684,3 +1127,74 @@ impl Device {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // The state of the valuators of an event, with the values of the given valuators set.
+    struct Valuators {
+        mask: Vec<u8>,
+        values: Vec<f64>,
+    }
+
+    impl Valuators {
+        fn new(values: &[(i32, f64)]) -> Self {
+            let mut mask = vec![0; 4];
+            let mut values = values.to_vec();
+            values.sort_by_key(|&(number, _)| number);
+            for &(number, _) in &values {
+                ffi::XISetMask(&mut mask, number);
+            }
+            Valuators {
+                mask,
+                values: values.into_iter().map(|(_, value)| value).collect(),
+            }
+        }
+
+        fn state(&mut self) -> ffi::XIValuatorState {
+            ffi::XIValuatorState {
+                mask_len: self.mask.len() as c_int,
+                mask: self.mask.as_mut_ptr(),
+                values: self.values.as_mut_ptr(),
+            }
+        }
+    }
+
+    fn valuator(number: i32, min: f64, max: f64) -> Option<Valuator> {
+        Some(Valuator { number, min, max })
+    }
+
+    #[test]
+    fn touch_data_scaling() {
+        let axes = TouchAxes {
+            pressure: valuator(2, 0.0, 255.0),
+            major: valuator(3, 0.0, 4096.0),
+            minor: valuator(4, 0.0, 4096.0),
+            orientation: valuator(5, -1.0, 1.0),
+            position_x: valuator(0, 0.0, 4096.0),
+        };
+        let mut valuators =
+            Valuators::new(&[(0, 100.0), (2, 51.0), (3, 64.0), (4, 32.0), (5, 0.5)]);
+        let (force, contact) = axes.touch_data(&valuators.state(), 1024.0);
+        assert_eq!(force, Some(0.2));
+        assert_eq!(
+            contact,
+            Some(TouchContact {
+                major: 16.0,
+                minor: 8.0,
+                orientation: 45.0,
+            })
+        );
+
+        // The size of the contact can't be scaled without the range of the position.
+        let axes = TouchAxes {
+            position_x: None,
+            ..axes
+        };
+        let mut valuators = Valuators::new(&[(2, 255.0), (3, 64.0)]);
+        let (force, contact) = axes.touch_data(&valuators.state(), 1024.0);
+        assert_eq!(force, Some(1.0));
+        assert_eq!(contact, None);
+    }
+}
@@ -0,0 +1,718 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    ffi::{CStr, CString},
+    mem::MaybeUninit,
+    os::raw::*,
+    ptr, slice,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use super::{ffi, util, XConnection, XError};
+
+use crate::event::{Selection, SelectionEvent};
+
+// The MIME type most applications use for text, and the X11 target it's traditionally offered as.
+const TEXT_MIME_TYPE: &str = "text/plain;charset=utf-8";
+
+// How long a requestor can take to read a chunk of an INCR transfer before we give up on it.
+const TRANSFER_TIMEOUT: Duration = Duration::from_secs(5);
+
+#[derive(Debug)]
+pub struct SelectionAtoms {
+    pub clipboard: ffi::Atom,
+    pub targets: ffi::Atom,
+    pub incr: ffi::Atom,
+    pub utf8_string: ffi::Atom,
+    pub text: ffi::Atom,
+    pub property: ffi::Atom,
+    pub timestamp: ffi::Atom,
+    pub xdnd_selection: ffi::Atom,
+}
+
+impl SelectionAtoms {
+    pub fn new(xconn: &Arc<XConnection>) -> Result<Self, XError> {
+        let names = [
+            b"CLIPBOARD\0".as_ptr() as *mut c_char,
+            b"TARGETS\0".as_ptr() as *mut c_char,
+            b"INCR\0".as_ptr() as *mut c_char,
+            b"UTF8_STRING\0".as_ptr() as *mut c_char,
+            b"TEXT\0".as_ptr() as *mut c_char,
+            b"_WINIT_SELECTION\0".as_ptr() as *mut c_char,
+            b"_WINIT_TIMESTAMP\0".as_ptr() as *mut c_char,
+            b"XdndSelection\0".as_ptr() as *mut c_char,
+        ];
+        let atoms = unsafe { xconn.get_atoms(&names) }?;
+        Ok(SelectionAtoms {
+            clipboard: atoms[0],
+            targets: atoms[1],
+            incr: atoms[2],
+            utf8_string: atoms[3],
+            text: atoms[4],
+            property: atoms[5],
+            timestamp: atoms[6],
+            xdnd_selection: atoms[7],
+        })
+    }
+}
+
+// The targets we offer for a selection we own, with the matching data.
+type Content = Vec<(ffi::Atom, Arc<Vec<u8>>)>;
+
+/// Who the content of a selection is requested for.
+#[derive(Debug, Clone, Copy)]
+pub enum Requester {
+    Application(Selection),
+    // The data of a drag and drop operation over one of our windows, which is exchanged through
+    // the `XdndSelection` selection at the time of the operation.
+    Drop {
+        window: ffi::Window,
+        time: ffi::Time,
+    },
+}
+
+// A conversion we asked the selection owner for.
+struct Request {
+    requester: Requester,
+    mime_type: String,
+    // Whether the content is text, which we convert to UTF-8 whatever the target it came as.
+    is_text: bool,
+    // The targets left to ask the content as, by order of preference. The first one is the
+    // conversion in flight, and the next ones are tried when the owner refuses it.
+    targets: VecDeque<ffi::Atom>,
+    // Set once the owner announced an INCR transfer; accumulates the received chunks along with
+    // their type.
+    incr_data: Option<(ffi::Atom, Vec<u8>)>,
+}
+
+// An INCR transfer of our own data to another client.
+struct Transfer {
+    requestor: ffi::Window,
+    // The events we selected on the requestor before watching its properties, restored once its
+    // transfers are over.
+    requestor_mask: c_long,
+    property: ffi::Atom,
+    target: ffi::Atom,
+    data: Arc<Vec<u8>>,
+    offset: usize,
+    // When the requestor last read a chunk, or when the transfer started.
+    last_read: Instant,
+}
+
+pub struct Selections {
+    xconn: Arc<XConnection>,
+    pub atoms: SelectionAtoms,
+    // Hidden window owning our selections and receiving the data we request.
+    pub window: ffi::Window,
+    // Maximum amount of bytes sent in a single property change before falling back to INCR.
+    max_chunk_size: usize,
+    // The content of the selections we own, along with the window focused when they were set and
+    // the time we took their ownership at.
+    owned: HashMap<ffi::Atom, (ffi::Window, ffi::Time, Content)>,
+    // Only the request at the front of the queue is in flight, as they all share one property.
+    requests: VecDeque<Request>,
+    transfers: Vec<Transfer>,
+    // Requests can be made outside of the processing of X events, so the ones we fail to even
+    // send are completed through the event loop.
+    failures: ::calloop::channel::Sender<(Requester, SelectionEvent)>,
+}
+
+impl Selections {
+    pub fn new(
+        xconn: Arc<XConnection>,
+        root: ffi::Window,
+        failures: ::calloop::channel::Sender<(Requester, SelectionEvent)>,
+    ) -> Result<Self, XError> {
+        let atoms = SelectionAtoms::new(&xconn)?;
+        let (window, max_request_size) = unsafe {
+            let window = (xconn.xlib.XCreateSimpleWindow)(xconn.display, root, 0, 0, 1, 1, 0, 0, 0);
+            (xconn.xlib.XSelectInput)(xconn.display, window, ffi::PropertyChangeMask);
+            let max_request_size = (xconn.xlib.XMaxRequestSize)(xconn.display);
+            (window, max_request_size)
+        };
+        xconn.check_errors()?;
+        // The request size is given in 4-byte units, and the request header needs some room too.
+        let max_chunk_size = (max_request_size as usize * 4)
+            .saturating_sub(100)
+            .max(4096);
+        Ok(Selections {
+            xconn,
+            atoms,
+            window,
+            max_chunk_size,
+            owned: HashMap::new(),
+            requests: VecDeque::new(),
+            transfers: Vec::new(),
+            failures,
+        })
+    }
+
+    pub fn selection_atom(&self, selection: Selection) -> ffi::Atom {
+        match selection {
+            Selection::Clipboard => self.atoms.clipboard,
+            Selection::Primary => ffi::XA_PRIMARY,
+        }
+    }
+
+    fn selection_from_atom(&self, atom: ffi::Atom) -> Option<Selection> {
+        if atom == self.atoms.clipboard {
+            Some(Selection::Clipboard)
+        } else if atom == ffi::XA_PRIMARY {
+            Some(Selection::Primary)
+        } else {
+            None
+        }
+    }
+
+    // MIME types are used as target names by all modern toolkits.
+    fn mime_type_atom(&self, mime_type: &str) -> ffi::Atom {
+        // Atom names can't contain nul bytes, and no valid MIME type does either.
+        let name = CString::new(mime_type.replace('\0', "")).unwrap();
+        self.xconn.get_atom(name)
+    }
+
+    pub fn set(
+        &mut self,
+        selection: Selection,
+        data: Vec<(String, Vec<u8>)>,
+    ) -> Result<(), &'static str> {
+        let selection_atom = self.selection_atom(selection);
+        self.own(selection_atom, data).map(|_| ())
+    }
+
+    /// Takes ownership of the `XdndSelection` selection for a drag and drop operation started
+    /// from one of our windows, returning the targets the data is offered as.
+    pub fn set_drag(
+        &mut self,
+        data: Vec<(String, Vec<u8>)>,
+    ) -> Result<Vec<ffi::Atom>, &'static str> {
+        self.own(self.atoms.xdnd_selection, data)
+    }
+
+    fn own(
+        &mut self,
+        selection_atom: ffi::Atom,
+        data: Vec<(String, Vec<u8>)>,
+    ) -> Result<Vec<ffi::Atom>, &'static str> {
+        let mut content = Vec::with_capacity(data.len());
+        for (mime_type, bytes) in data {
+            let bytes = Arc::new(bytes);
+            if mime_type == TEXT_MIME_TYPE {
+                // Older clients only know about the traditional text targets.
+                content.push((self.atoms.utf8_string, bytes.clone()));
+            }
+            content.push((self.mime_type_atom(&mime_type), bytes));
+        }
+
+        let time = self.server_time();
+        let (owner, focus) = unsafe {
+            let mut focus = 0;
+            let mut revert_to = 0;
+            (self.xconn.xlib.XGetInputFocus)(self.xconn.display, &mut focus, &mut revert_to);
+            (self.xconn.xlib.XSetSelectionOwner)(
+                self.xconn.display,
+                selection_atom,
+                self.window,
+                time,
+            );
+            let owner = (self.xconn.xlib.XGetSelectionOwner)(self.xconn.display, selection_atom);
+            (owner, focus)
+        };
+        if owner != self.window {
+            return Err("Failed to acquire the selection ownership");
+        }
+        let targets = content.iter().map(|&(target, _)| target).collect();
+        self.owned.insert(selection_atom, (focus, time, content));
+        Ok(targets)
+    }
+
+    // ICCCM forbids taking the ownership of a selection or asking for its content at
+    // `CurrentTime`, so we get a timestamp from the server by appending nothing to a property of
+    // our window, which still generates a `PropertyNotify` event.
+    fn server_time(&self) -> ffi::Time {
+        unsafe extern "C" fn is_timestamp_notify(
+            _: *mut ffi::Display,
+            event: *mut ffi::XEvent,
+            arg: *mut c_char,
+        ) -> c_int {
+            let (window, atom) = *(arg as *const (ffi::Window, ffi::Atom));
+            let event = &*event;
+            (event.get_type() == ffi::PropertyNotify
+                && event.property.window == window
+                && event.property.atom == atom) as c_int
+        }
+
+        let mut arg = (self.window, self.atoms.timestamp);
+        unsafe {
+            (self.xconn.xlib.XChangeProperty)(
+                self.xconn.display,
+                self.window,
+                self.atoms.timestamp,
+                ffi::XA_INTEGER,
+                32,
+                ffi::PropModeAppend,
+                ptr::null(),
+                0,
+            );
+            let mut event = MaybeUninit::uninit();
+            (self.xconn.xlib.XIfEvent)(
+                self.xconn.display,
+                event.as_mut_ptr(),
+                Some(is_timestamp_notify),
+                &mut arg as *mut _ as *mut c_char,
+            );
+            event.assume_init().property.time
+        }
+    }
+
+    pub fn request(&mut self, requester: Requester, mime_type: String) {
+        // Drag and drop sources offer the exact MIME types they announced as targets, but the
+        // owners of the other selections may only know about the traditional text targets.
+        let is_text = match requester {
+            Requester::Application(_) => mime_type == TEXT_MIME_TYPE,
+            Requester::Drop { .. } => false,
+        };
+        let targets = if is_text {
+            vec![
+                self.atoms.utf8_string,
+                self.mime_type_atom(&mime_type),
+                self.atoms.text,
+                ffi::XA_STRING,
+            ]
+        } else {
+            vec![self.mime_type_atom(&mime_type)]
+        };
+        self.requests.push_back(Request {
+            requester,
+            mime_type,
+            is_text,
+            targets: targets.into(),
+            incr_data: None,
+        });
+        if self.requests.len() == 1 {
+            self.convert_front();
+        }
+    }
+
+    fn requested_selection(&self, requester: Requester) -> ffi::Atom {
+        match requester {
+            Requester::Application(selection) => self.selection_atom(selection),
+            Requester::Drop { .. } => self.atoms.xdnd_selection,
+        }
+    }
+
+    fn convert_front(&mut self) {
+        while let Some(request) = self.requests.front() {
+            let time = match request.requester {
+                Requester::Application(_) => self.server_time(),
+                Requester::Drop { time, .. } => time,
+            };
+            unsafe {
+                (self.xconn.xlib.XConvertSelection)(
+                    self.xconn.display,
+                    self.requested_selection(request.requester),
+                    request.targets[0],
+                    self.atoms.property,
+                    self.window,
+                    time,
+                );
+            }
+            if self.xconn.flush_requests().is_ok() {
+                return;
+            }
+            let request = self.requests.pop_front().unwrap();
+            let _ = self.failures.send((
+                request.requester,
+                SelectionEvent::Failed {
+                    mime_type: request.mime_type,
+                },
+            ));
+        }
+    }
+
+    fn complete_front(
+        &mut self,
+        data: Option<(ffi::Atom, Vec<u8>)>,
+    ) -> Option<(Requester, SelectionEvent)> {
+        let is_text = self.requests.front()?.is_text;
+        let data = match data {
+            Some((data_type, data)) if is_text => self.decode_text(data_type, data),
+            data => data.map(|(_, data)| data),
+        };
+        if data.is_none() {
+            let request = self.requests.front_mut()?;
+            if request.targets.len() > 1 {
+                request.targets.pop_front();
+                request.incr_data = None;
+                self.convert_front();
+                return None;
+            }
+        }
+
+        let request = self.requests.pop_front()?;
+        self.convert_front();
+        let event = match data {
+            Some(data) => SelectionEvent::Received {
+                mime_type: request.mime_type,
+                data,
+            },
+            None => SelectionEvent::Failed {
+                mime_type: request.mime_type,
+            },
+        };
+        Some((request.requester, event))
+    }
+
+    // Reads and deletes our property, returning its type along with its content.
+    unsafe fn take_property(&self) -> Option<(ffi::Atom, Vec<u8>)> {
+        let mut actual_type = 0;
+        let mut actual_format = 0;
+        let mut quantity_returned = 0;
+        let mut bytes_after = 0;
+        let mut buf: *mut c_uchar = ptr::null_mut();
+        (self.xconn.xlib.XGetWindowProperty)(
+            self.xconn.display,
+            self.window,
+            self.atoms.property,
+            0,
+            // The length is in 32-bit units, so we're asking for everything.
+            c_long::MAX / 4,
+            ffi::True,
+            ffi::AnyPropertyType as ffi::Atom,
+            &mut actual_type,
+            &mut actual_format,
+            &mut quantity_returned,
+            &mut bytes_after,
+            &mut buf,
+        );
+        if self.xconn.check_errors().is_err() || buf.is_null() {
+            return None;
+        }
+        // Items of format 32 are stored as longs by Xlib.
+        let item_size = match util::Format::from_format(actual_format as usize) {
+            Some(format) => format.get_actual_size(),
+            None => 1,
+        };
+        let data = slice::from_raw_parts(buf, quantity_returned as usize * item_size).to_vec();
+        (self.xconn.xlib.XFree)(buf as _);
+        Some((actual_type, data))
+    }
+
+    // Converts text received as one of the traditional text targets to UTF-8.
+    fn decode_text(&self, data_type: ffi::Atom, data: Vec<u8>) -> Option<Vec<u8>> {
+        if data_type == self.atoms.utf8_string || data_type == self.mime_type_atom(TEXT_MIME_TYPE) {
+            return Some(data);
+        }
+        if data_type == ffi::XA_STRING {
+            // `STRING` is Latin-1, whose code points are the first ones of Unicode.
+            let text: String = data.iter().map(|&byte| byte as char).collect();
+            return Some(text.into_bytes());
+        }
+
+        // Owners answering `TEXT` may pick any encoding, usually `COMPOUND_TEXT`.
+        let property = ffi::XTextProperty {
+            value: data.as_ptr() as *mut c_uchar,
+            encoding: data_type,
+            format: 8,
+            nitems: data.len() as c_ulong,
+        };
+        let mut list = ptr::null_mut();
+        let mut count = 0;
+        let status = unsafe {
+            (self.xconn.xlib.Xutf8TextPropertyToTextList)(
+                self.xconn.display,
+                &property,
+                &mut list,
+                &mut count,
+            )
+        };
+        if status != c_int::from(ffi::Success) || list.is_null() {
+            return None;
+        }
+        let mut text = Vec::new();
+        unsafe {
+            for &item in slice::from_raw_parts(list, count as usize) {
+                text.extend_from_slice(CStr::from_ptr(item).to_bytes());
+            }
+            (self.xconn.xlib.XFreeStringList)(list);
+        }
+        Some(text)
+    }
+
+    /// Handles a `SelectionNotify` event sent to our window in response to `convert_front`.
+    pub fn handle_notify(
+        &mut self,
+        xsel: &ffi::XSelectionEvent,
+    ) -> Option<(Requester, SelectionEvent)> {
+        // Late answers to the conversions we gave up on don't concern the one in flight.
+        let expected = match self.requests.front() {
+            Some(request) => {
+                xsel.selection == self.requested_selection(request.requester)
+                    && xsel.target == request.targets[0]
+                    && (xsel.property == 0 || xsel.property == self.atoms.property)
+            }
+            None => false,
+        };
+        if !expected {
+            return None;
+        }
+        if xsel.property == 0 {
+            // The owner refused the conversion, or there's no owner at all.
+            return self.complete_front(None);
+        }
+        match unsafe { self.take_property() } {
+            Some((actual_type, _)) if actual_type == self.atoms.incr => {
+                // Deleting the property (which `take_property` did) tells the owner to start
+                // sending chunks, which we receive through `PropertyNotify`.
+                if self.xconn.flush_requests().is_err() {
+                    return self.complete_front(None);
+                }
+                if let Some(request) = self.requests.front_mut() {
+                    request.incr_data = Some((0, Vec::new()));
+                }
+                None
+            }
+            data => self.complete_front(data),
+        }
+    }
+
+    /// Handles `PropertyNotify` events driving the INCR transfers in both directions.
+    pub fn handle_property_notify(
+        &mut self,
+        xprop: &ffi::XPropertyEvent,
+    ) -> Option<(Requester, SelectionEvent)> {
+        self.expire_transfers();
+        if xprop.window == self.window
+            && xprop.atom == self.atoms.property
+            && xprop.state == ffi::PropertyNewValue
+        {
+            let receiving = self
+                .requests
+                .front()
+                .map(|request| request.incr_data.is_some())
+                .unwrap_or(false);
+            if !receiving {
+                return None;
+            }
+            return match unsafe { self.take_property() } {
+                Some((_, ref chunk)) if chunk.is_empty() => {
+                    let data = self.requests.front_mut().and_then(|r| r.incr_data.take());
+                    self.complete_front(data)
+                }
+                Some((chunk_type, chunk)) => {
+                    if self.xconn.flush_requests().is_err() {
+                        return self.complete_front(None);
+                    }
+                    if let Some((data_type, data)) =
+                        self.requests.front_mut().and_then(|r| r.incr_data.as_mut())
+                    {
+                        *data_type = chunk_type;
+                        data.extend_from_slice(&chunk);
+                    }
+                    None
+                }
+                None => self.complete_front(None),
+            };
+        }
+
+        if xprop.state == ffi::PropertyDelete {
+            let position = self
+                .transfers
+                .iter()
+                .position(|t| t.requestor == xprop.window && t.property == xprop.atom);
+            if let Some(index) = position {
+                let finished = {
+                    let transfer = &mut self.transfers[index];
+                    let end = (transfer.offset + self.max_chunk_size).min(transfer.data.len());
+                    // The last chunk is the empty one, marking the end of the transfer.
+                    self.xconn
+                        .change_property(
+                            transfer.requestor,
+                            transfer.property,
+                            transfer.target,
+                            util::PropMode::Replace,
+                            &transfer.data[transfer.offset..end],
+                        )
+                        .flush()
+                        .is_err()
+                        || transfer.offset == end
+                };
+                if finished {
+                    self.end_transfer(index);
+                } else {
+                    let transfer = &mut self.transfers[index];
+                    transfer.offset =
+                        (transfer.offset + self.max_chunk_size).min(transfer.data.len());
+                    transfer.last_read = Instant::now();
+                }
+            }
+        }
+        None
+    }
+
+    // Watches the properties of the requestor of an INCR transfer, to know when it read a chunk.
+    // Returns the events we selected on it before, or `None` if it's gone.
+    fn watch_requestor(&self, requestor: ffi::Window) -> Option<c_long> {
+        if let Some(transfer) = self.transfers.iter().find(|t| t.requestor == requestor) {
+            return Some(transfer.requestor_mask);
+        }
+        unsafe {
+            let mut attributes = MaybeUninit::uninit();
+            let status = (self.xconn.xlib.XGetWindowAttributes)(
+                self.xconn.display,
+                requestor,
+                attributes.as_mut_ptr(),
+            );
+            if status == 0 || self.xconn.check_errors().is_err() {
+                return None;
+            }
+            let mask = attributes.assume_init().your_event_mask;
+            (self.xconn.xlib.XSelectInput)(
+                self.xconn.display,
+                requestor,
+                mask | ffi::PropertyChangeMask,
+            );
+            Some(mask)
+        }
+    }
+
+    // Removes a transfer, giving the requestor back the events it had once it has no other one.
+    fn end_transfer(&mut self, index: usize) {
+        let transfer = self.transfers.swap_remove(index);
+        if self
+            .transfers
+            .iter()
+            .all(|t| t.requestor != transfer.requestor)
+        {
+            unsafe {
+                (self.xconn.xlib.XSelectInput)(
+                    self.xconn.display,
+                    transfer.requestor,
+                    transfer.requestor_mask,
+                );
+            }
+            // The requestor may be gone already, in which case there's nothing to restore.
+            let _ = self.xconn.sync_with_server();
+        }
+    }
+
+    // Gives up on the transfers whose requestors stopped reading the chunks.
+    fn expire_transfers(&mut self) {
+        while let Some(index) = self
+            .transfers
+            .iter()
+            .position(|t| t.last_read.elapsed() > TRANSFER_TIMEOUT)
+        {
+            self.end_transfer(index);
+        }
+    }
+
+    /// Answers another client asking for the content of one of our selections.
+    pub fn handle_request(&mut self, xreq: &ffi::XSelectionRequestEvent) {
+        self.expire_transfers();
+        // Obsolete clients don't specify a property, and expect the target to be used instead.
+        let property = if xreq.property == 0 {
+            xreq.target
+        } else {
+            xreq.property
+        };
+        let mut reply = ffi::XSelectionEvent {
+            type_: ffi::SelectionNotify,
+            serial: 0,
+            send_event: ffi::True,
+            display: self.xconn.display,
+            requestor: xreq.requestor,
+            selection: xreq.selection,
+            target: xreq.target,
+            property: 0,
+            time: xreq.time,
+        };
+
+        // Requests made before we took the ownership are meant for the previous owner.
+        let owned = self
+            .owned
+            .get(&xreq.selection)
+            .filter(|&&(_, time, _)| xreq.time == ffi::CurrentTime || xreq.time >= time);
+        if let Some((_, _, content)) = owned {
+            if xreq.target == self.atoms.targets {
+                let mut targets = vec![self.atoms.targets as c_ulong];
+                targets.extend(content.iter().map(|&(target, _)| target as c_ulong));
+                self.xconn
+                    .change_property(
+                        xreq.requestor,
+                        property,
+                        ffi::XA_ATOM,
+                        util::PropMode::Replace,
+                        &targets,
+                    )
+                    .queue();
+                reply.property = property;
+            } else if let Some(&(target, ref data)) =
+                content.iter().find(|&&(target, _)| target == xreq.target)
+            {
+                if data.len() > self.max_chunk_size {
+                    let requestor_mask = match self.watch_requestor(xreq.requestor) {
+                        Some(mask) => mask,
+                        // Nobody is left to answer.
+                        None => return,
+                    };
+                    self.xconn
+                        .change_property(
+                            xreq.requestor,
+                            property,
+                            self.atoms.incr,
+                            util::PropMode::Replace,
+                            &[data.len() as c_long],
+                        )
+                        .queue();
+                    self.transfers
+                        .retain(|t| t.requestor != xreq.requestor || t.property != property);
+                    self.transfers.push(Transfer {
+                        requestor: xreq.requestor,
+                        requestor_mask,
+                        property,
+                        target,
+                        data: data.clone(),
+                        offset: 0,
+                        last_read: Instant::now(),
+                    });
+                } else {
+                    self.xconn
+                        .change_property(
+                            xreq.requestor,
+                            property,
+                            target,
+                            util::PropMode::Replace,
+                            &data[..],
+                        )
+                        .queue();
+                }
+                reply.property = property;
+            }
+        }
+
+        // The requestor may be gone already, which isn't our problem.
+        if let Err(err) = self.xconn.send_event(xreq.requestor, None, reply).flush() {
+            warn!("Failed to answer a selection request: {}", err);
+        }
+    }
+
+    /// Handles the loss of one of our selections, returning which one it was along with the
+    /// window that was focused when it was set.
+    pub fn handle_clear(
+        &mut self,
+        xclear: &ffi::XSelectionClearEvent,
+    ) -> Option<(Selection, ffi::Window)> {
+        let (focus, _, _) = self.owned.remove(&xclear.selection)?;
+        self.selection_from_atom(xclear.selection)
+            .map(|selection| (selection, focus))
+    }
+}
+
+impl Drop for Selections {
+    fn drop(&mut self) {
+        unsafe {
+            (self.xconn.xlib.XDestroyWindow)(self.xconn.display, self.window);
+        }
+    }
+}
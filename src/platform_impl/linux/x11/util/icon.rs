@@ -1,5 +1,7 @@
+use std::slice;
+
 use super::*;
-use crate::window::{Icon, Pixel, PIXEL_SIZE};
+use crate::window::{CustomCursor, Icon, Pixel, PIXEL_SIZE};
 
 impl Pixel {
     pub fn to_packed_argb(&self) -> Cardinal {
@@ -32,3 +34,71 @@ impl Icon {
         data
     }
 }
+
+impl XConnection {
+    // Creates a cursor showing `icon`, with its hotspot at the given pixel.
+    pub fn create_icon_cursor(&self, icon: &Icon, hotspot: (u32, u32)) -> ffi::Cursor {
+        unsafe {
+            let image = self.create_cursor_image(icon, hotspot, 0);
+            if image.is_null() {
+                return 0;
+            }
+            let cursor = (self.xcursor.XcursorImageLoadCursor)(self.display, image);
+            (self.xcursor.XcursorImageDestroy)(image);
+            cursor
+        }
+    }
+
+    // Creates a cursor showing the frames of `cursor`, which the server animates if it supports
+    // it.
+    pub fn create_custom_cursor(&self, cursor: &CustomCursor) -> ffi::Cursor {
+        unsafe {
+            let images = (self.xcursor.XcursorImagesCreate)(cursor.frames.len() as c_int);
+            if images.is_null() {
+                return 0;
+            }
+            for frame in &cursor.frames {
+                let delay = frame.delay.as_millis().min(u128::from(c_uint::MAX)) as c_uint;
+                let hotspot = (frame.hotspot_x, frame.hotspot_y);
+                let image = self.create_cursor_image(&frame.image, hotspot, delay);
+                if image.is_null() {
+                    (self.xcursor.XcursorImagesDestroy)(images);
+                    return 0;
+                }
+                // The images are destroyed along with the list.
+                *(*images).images.offset((*images).nimage as isize) = image;
+                (*images).nimage += 1;
+            }
+            let x_cursor = (self.xcursor.XcursorImagesLoadCursor)(self.display, images);
+            (self.xcursor.XcursorImagesDestroy)(images);
+            x_cursor
+        }
+    }
+
+    unsafe fn create_cursor_image(
+        &self,
+        icon: &Icon,
+        (xhot, yhot): (u32, u32),
+        delay: c_uint,
+    ) -> *mut ffi::XcursorImage {
+        let image = (self.xcursor.XcursorImageCreate)(icon.width as c_int, icon.height as c_int);
+        if image.is_null() {
+            return image;
+        }
+        (*image).xhot = xhot.min(icon.width.saturating_sub(1));
+        (*image).yhot = yhot.min(icon.height.saturating_sub(1));
+        (*image).delay = delay;
+        let pixel_count = (icon.width * icon.height) as usize;
+        let pixels = slice::from_raw_parts_mut((*image).pixels, pixel_count);
+        for (pixel, rgba) in pixels.iter_mut().zip(icon.rgba.chunks(PIXEL_SIZE)) {
+            // Xcursor expects premultiplied alpha.
+            let alpha = rgba[3] as u32;
+            let premultiply = |channel: u8| channel as u32 * alpha / 255;
+            *pixel = alpha << 24
+                | premultiply(rgba[0]) << 16
+                | premultiply(rgba[1]) << 8
+                | premultiply(rgba[2]);
+        }
+        image
+    }
+}
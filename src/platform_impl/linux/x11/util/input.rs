@@ -1,10 +1,12 @@
-use std::{slice, str};
+use std::{ptr, slice, str};
 
 use super::*;
 use crate::event::ModifiersState;
 
 pub const VIRTUAL_CORE_POINTER: c_int = 2;
 pub const VIRTUAL_CORE_KEYBOARD: c_int = 3;
+// The device spec of the core keyboard in XKB requests.
+const XKB_USE_CORE_KBD: c_uint = 0x0100;
 
 // A base buffer size of 1kB uses a negligible amount of RAM while preventing us from having to
 // re-allocate (and make another round-trip) in the *vast* majority of cases.
@@ -13,13 +15,20 @@ const TEXT_BUFFER_SIZE: usize = 1024;
 
 impl From<ffi::XIModifierState> for ModifiersState {
     fn from(mods: ffi::XIModifierState) -> Self {
-        let state = mods.effective as c_uint;
-        ModifiersState {
-            alt: state & ffi::Mod1Mask != 0,
-            shift: state & ffi::ShiftMask != 0,
-            ctrl: state & ffi::ControlMask != 0,
-            logo: state & ffi::Mod4Mask != 0,
-        }
+        modifiers_from_state(mods.effective as c_uint)
+    }
+}
+
+/// The modifiers of a core X11 modifier mask, with the usual mapping of the modifiers.
+pub fn modifiers_from_state(state: c_uint) -> ModifiersState {
+    ModifiersState {
+        alt: state & ffi::Mod1Mask != 0,
+        shift: state & ffi::ShiftMask != 0,
+        ctrl: state & ffi::ControlMask != 0,
+        logo: state & ffi::Mod4Mask != 0,
+        caps_lock: state & ffi::LockMask != 0,
+        num_lock: state & ffi::Mod2Mask != 0,
+        ..Default::default()
     }
 }
 
@@ -60,7 +69,7 @@ impl XConnection {
         &self,
         window: c_ulong,
         device_id: c_int,
-        mask: i32,
+        mask: i64,
     ) -> Flusher<'_> {
         let mut event_mask = ffi::XIEventMask {
             deviceid: device_id,
@@ -78,7 +87,6 @@ impl XConnection {
         Flusher::new(self)
     }
 
-    #[allow(dead_code)]
     pub fn select_xkb_events(&self, device_id: c_uint, mask: c_ulong) -> Option<Flusher<'_>> {
         let status = unsafe { (self.xlib.XkbSelectEvents)(self.display, device_id, mask, mask) };
         if status == ffi::True {
@@ -184,4 +192,57 @@ impl XConnection {
 
         str::from_utf8(bytes).unwrap_or("").to_string()
     }
+
+    // The delay and interval of the key repeat in milliseconds, unless it's turned off.
+    pub fn auto_repeat_rate(&self) -> Option<(u32, u32)> {
+        unsafe {
+            let mut keyboard_state = MaybeUninit::uninit();
+            (self.xlib.XGetKeyboardControl)(self.display, keyboard_state.as_mut_ptr());
+            if keyboard_state.assume_init().global_auto_repeat != ffi::AutoRepeatModeOn {
+                return None;
+            }
+
+            let mut delay = 0;
+            let mut interval = 0;
+            let status = (self.xlib.XkbGetAutoRepeatRate)(
+                self.display,
+                XKB_USE_CORE_KBD,
+                &mut delay,
+                &mut interval,
+            );
+            if status == ffi::True {
+                Some((delay, interval))
+            } else {
+                None
+            }
+        }
+    }
+
+    // The keysym of a key press, with the modifiers of the event.
+    pub fn lookup_keysym(&self, key_event: &mut ffi::XKeyEvent) -> u32 {
+        let mut keysym = 0;
+        unsafe {
+            (self.xlib.XLookupString)(key_event, ptr::null_mut(), 0, &mut keysym, ptr::null_mut());
+        }
+        self.check_errors().expect("Failed to lookup keysym");
+        keysym as u32
+    }
+
+    // Without an input context, the text of a key press can only be looked up in Latin-1.
+    pub fn lookup_latin1(&self, key_event: &mut ffi::XKeyEvent) -> String {
+        let mut buffer = [0u8; TEXT_BUFFER_SIZE];
+        let count = unsafe {
+            (self.xlib.XLookupString)(
+                key_event,
+                buffer.as_mut_ptr() as *mut c_char,
+                buffer.len() as c_int,
+                ptr::null_mut(),
+                ptr::null_mut(),
+            )
+        };
+        buffer[..count.max(0) as usize]
+            .iter()
+            .map(|&byte| byte as char)
+            .collect()
+    }
 }
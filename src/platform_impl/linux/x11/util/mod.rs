@@ -12,10 +12,11 @@ mod memory;
 mod randr;
 mod window_property;
 mod wm;
+mod xsettings;
 
 pub use self::{
     atom::*, client_msg::*, format::*, geometry::*, hint::*, icon::*, input::*, memory::*,
-    randr::*, window_property::*, wm::*,
+    randr::*, window_property::*, wm::*, xsettings::*,
 };
 
 use std::{
@@ -0,0 +1,215 @@
+use std::{collections::HashMap, ffi::CString};
+
+use super::*;
+
+/// A setting shared by the desktop through XSETTINGS, colors aside.
+#[derive(Debug, Clone, PartialEq)]
+pub enum XSetting {
+    Integer(i32),
+    String(String),
+}
+
+impl XConnection {
+    /// Reads the XSETTINGS of `screen`, which are empty if no settings manager is running.
+    pub fn get_xsettings(&self, screen: c_int) -> HashMap<String, XSetting> {
+        let selection = self.get_atom(CString::new(format!("_XSETTINGS_S{}", screen)).unwrap());
+        let owner = unsafe { (self.xlib.XGetSelectionOwner)(self.display, selection) };
+        if owner == 0 {
+            return HashMap::new();
+        }
+        let settings_atom = unsafe { self.get_atom_unchecked(b"_XSETTINGS_SETTINGS\0") };
+        self.get_property::<c_uchar>(owner, settings_atom, settings_atom)
+            .ok()
+            .and_then(|data| parse_xsettings(&data))
+            .unwrap_or_default()
+    }
+}
+
+// Reads the values of the `_XSETTINGS_SETTINGS` property, in the byte order it states.
+struct Reader<'a> {
+    data: &'a [u8],
+    offset: usize,
+    big_endian: bool,
+}
+
+impl<'a> Reader<'a> {
+    fn bytes(&mut self, len: usize) -> Option<&'a [u8]> {
+        let bytes = self.data.get(self.offset..self.offset.checked_add(len)?)?;
+        self.offset += len;
+        Some(bytes)
+    }
+
+    // Strings are padded to a multiple of 4 bytes.
+    fn padded_bytes(&mut self, len: usize) -> Option<&'a [u8]> {
+        let bytes = self.bytes(len)?;
+        self.bytes((4 - len % 4) % 4)?;
+        Some(bytes)
+    }
+
+    fn card8(&mut self) -> Option<u8> {
+        self.bytes(1).map(|bytes| bytes[0])
+    }
+
+    fn card16(&mut self) -> Option<u16> {
+        let bytes = [self.card8()?, self.card8()?];
+        Some(if self.big_endian {
+            u16::from_be_bytes(bytes)
+        } else {
+            u16::from_le_bytes(bytes)
+        })
+    }
+
+    fn card32(&mut self) -> Option<u32> {
+        let bytes = [self.card8()?, self.card8()?, self.card8()?, self.card8()?];
+        Some(if self.big_endian {
+            u32::from_be_bytes(bytes)
+        } else {
+            u32::from_le_bytes(bytes)
+        })
+    }
+
+    fn string(&mut self, len: usize) -> Option<String> {
+        self.padded_bytes(len)
+            .map(|bytes| String::from_utf8_lossy(bytes).into_owned())
+    }
+}
+
+fn parse_xsettings(data: &[u8]) -> Option<HashMap<String, XSetting>> {
+    let mut reader = Reader {
+        data,
+        offset: 0,
+        big_endian: *data.first()? == ffi::MSBFirst as u8,
+    };
+    // The byte order is followed by 3 bytes of padding, and the serial of the settings.
+    reader.bytes(4)?;
+    reader.card32()?;
+    let count = reader.card32()?;
+    let mut settings = HashMap::new();
+    for _ in 0..count {
+        let kind = reader.card8()?;
+        reader.bytes(1)?;
+        let name_len = reader.card16()? as usize;
+        let name = reader.string(name_len)?;
+        // The serial the setting last changed at.
+        reader.card32()?;
+        let value = match kind {
+            0 => XSetting::Integer(reader.card32()? as i32),
+            1 => {
+                let len = reader.card32()? as usize;
+                XSetting::String(reader.string(len)?)
+            }
+            // Colors aren't of any use to us.
+            2 => {
+                reader.bytes(8)?;
+                continue;
+            }
+            _ => return None,
+        };
+        settings.insert(name, value);
+    }
+    Some(settings)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Builds a `_XSETTINGS_SETTINGS` property holding `settings`, in the given byte order.
+    fn property(big_endian: bool, settings: &[(u8, &str, &[u8])]) -> Vec<u8> {
+        let card16 = |value: u16| {
+            if big_endian {
+                value.to_be_bytes()
+            } else {
+                value.to_le_bytes()
+            }
+        };
+        let card32 = |value: u32| {
+            if big_endian {
+                value.to_be_bytes()
+            } else {
+                value.to_le_bytes()
+            }
+        };
+        let pad = |data: &mut Vec<u8>| {
+            while data.len() % 4 != 0 {
+                data.push(0);
+            }
+        };
+
+        let byte_order = if big_endian {
+            ffi::MSBFirst
+        } else {
+            ffi::LSBFirst
+        };
+        let mut data = vec![byte_order as u8, 0, 0, 0];
+        data.extend_from_slice(&card32(7));
+        data.extend_from_slice(&card32(settings.len() as u32));
+        for &(kind, name, value) in settings {
+            data.extend_from_slice(&[kind, 0]);
+            data.extend_from_slice(&card16(name.len() as u16));
+            data.extend_from_slice(name.as_bytes());
+            pad(&mut data);
+            data.extend_from_slice(&card32(3));
+            data.extend_from_slice(value);
+        }
+        data
+    }
+
+    fn integer(big_endian: bool, value: i32) -> Vec<u8> {
+        if big_endian {
+            value.to_be_bytes().to_vec()
+        } else {
+            value.to_le_bytes().to_vec()
+        }
+    }
+
+    fn string(big_endian: bool, value: &str) -> Vec<u8> {
+        let mut data = integer(big_endian, value.len() as i32);
+        data.extend_from_slice(value.as_bytes());
+        while data.len() % 4 != 0 {
+            data.push(0);
+        }
+        data
+    }
+
+    #[test]
+    fn parse_both_byte_orders() {
+        for &big_endian in &[false, true] {
+            let data = property(
+                big_endian,
+                &[
+                    (0, "Gtk/CursorThemeSize", &integer(big_endian, 32)),
+                    (1, "Gtk/CursorThemeName", &string(big_endian, "Adwaita")),
+                    (2, "Net/Color", &[0; 8]),
+                    (0, "Xft/DPI", &integer(big_endian, -1)),
+                ],
+            );
+            let settings = parse_xsettings(&data).unwrap();
+            assert_eq!(settings.len(), 3);
+            assert_eq!(
+                settings.get("Gtk/CursorThemeSize"),
+                Some(&XSetting::Integer(32))
+            );
+            assert_eq!(
+                settings.get("Gtk/CursorThemeName"),
+                Some(&XSetting::String("Adwaita".to_owned()))
+            );
+            assert_eq!(settings.get("Xft/DPI"), Some(&XSetting::Integer(-1)));
+        }
+    }
+
+    #[test]
+    fn parse_invalid() {
+        assert_eq!(parse_xsettings(&[]), None);
+        // An unknown setting type.
+        let data = property(false, &[(3, "Net/Unknown", &integer(false, 0))]);
+        assert_eq!(parse_xsettings(&data), None);
+        // A truncated property.
+        let data = property(false, &[(1, "Net/ThemeName", &string(false, "Adwaita"))]);
+        assert_eq!(parse_xsettings(&data[..data.len() - 4]), None);
+        // More settings announced than present.
+        let mut data = property(false, &[]);
+        data[8] = 1;
+        assert_eq!(parse_xsettings(&data), None);
+    }
+}
@@ -19,13 +19,18 @@ use crate::{
     monitor::{MonitorHandle as RootMonitorHandle, VideoMode as RootVideoMode},
     platform_impl::{
         x11::{ime::ImeContextCreationError, MonitorHandle as X11MonitorHandle},
-        MonitorHandle as PlatformMonitorHandle, OsError, PlatformSpecificWindowBuilderAttributes,
-        VideoMode as PlatformVideoMode,
+        Cursor, CursorTheme, MonitorHandle as PlatformMonitorHandle, OsError,
+        PlatformSpecificWindowBuilderAttributes, VideoMode as PlatformVideoMode,
+    },
+    window::{
+        CursorGrabMode, CursorIcon, CustomCursor, Fullscreen, Icon, ImePurpose, WindowAttributes,
     },
-    window::{CursorIcon, Fullscreen, Icon, WindowAttributes},
 };
 
-use super::{ffi, util, EventLoopWindowTarget, ImeSender, WindowId, XConnection, XError};
+use super::{
+    drag_source::DragRequest, ffi, util, EventLoopWindowTarget, ImeRequest, ImeSender, WindowId,
+    XConnection, XError,
+};
 
 unsafe extern "C" fn visibility_predicate(
     _display: *mut ffi::Display,
@@ -73,12 +78,19 @@ pub struct UnownedWindow {
     xwindow: ffi::Window,        // never changes
     root: ffi::Window,           // never changes
     screen_id: i32,              // never changes
-    cursor: Mutex<CursorIcon>,
-    cursor_grabbed: Mutex<bool>,
+    cursor: Mutex<Cursor>,
+    cursor_grabbed_mode: Mutex<CursorGrabMode>,
     cursor_visible: Mutex<bool>,
+    key_repeat: Mutex<bool>,
+    // Whether the application allows input methods, and the kind of text it expects, which only
+    // matters to X11 for passwords.
+    ime_allowed: Mutex<bool>,
+    ime_purpose: Mutex<ImePurpose>,
     ime_sender: Mutex<ImeSender>,
+    drag_requests: Mutex<::calloop::channel::Sender<DragRequest>>,
     pub shared_state: Mutex<SharedState>,
     pending_redraws: Arc<::std::sync::Mutex<HashSet<WindowId>>>,
+    cursor_theme: Arc<::std::sync::Mutex<CursorTheme>>,
 }
 
 impl UnownedWindow {
@@ -226,11 +238,16 @@ impl UnownedWindow {
             root,
             screen_id,
             cursor: Default::default(),
-            cursor_grabbed: Mutex::new(false),
+            cursor_grabbed_mode: Mutex::new(CursorGrabMode::None),
             cursor_visible: Mutex::new(true),
+            key_repeat: Mutex::new(true),
+            ime_allowed: Mutex::new(true),
+            ime_purpose: Mutex::new(ImePurpose::Normal),
             ime_sender: Mutex::new(event_loop.ime_sender.clone()),
+            drag_requests: Mutex::new(event_loop.drag_requests.clone()),
             shared_state: SharedState::new(dpi_factor),
             pending_redraws: event_loop.pending_redraws.clone(),
+            cursor_theme: event_loop.cursor_theme.clone(),
         };
 
         // Title must be set before mapping. Some tiling window managers (i.e. i3) use the window
@@ -385,7 +402,17 @@ impl UnownedWindow {
                     | ffi::XI_TouchBeginMask
                     | ffi::XI_TouchUpdateMask
                     | ffi::XI_TouchEndMask;
-                mask
+                let gesture_mask = ffi::XI_GesturePinchBeginMask
+                    | ffi::XI_GesturePinchUpdateMask
+                    | ffi::XI_GesturePinchEndMask
+                    | ffi::XI_GestureSwipeBeginMask
+                    | ffi::XI_GestureSwipeUpdateMask
+                    | ffi::XI_GestureSwipeEndMask;
+                if event_loop.xinput_gestures {
+                    i64::from(mask) | gesture_mask
+                } else {
+                    i64::from(mask)
+                }
             };
             xconn
                 .select_xinput_events(window.xwindow, ffi::XIAllMasterDevices, mask)
@@ -541,6 +568,16 @@ impl UnownedWindow {
             .expect("Failed to set urgency hint");
     }
 
+    #[inline]
+    pub fn set_key_repeat(&self, enabled: bool) {
+        *self.key_repeat.lock() = enabled;
+    }
+
+    #[inline]
+    pub fn key_repeat(&self) -> bool {
+        *self.key_repeat.lock()
+    }
+
     fn set_netwm(
         &self,
         operation: util::StateOperation,
@@ -1123,18 +1160,31 @@ impl UnownedWindow {
         unsafe { (self.xconn.xlib_xcb.XGetXCBConnection)(self.xconn.display) as *mut _ }
     }
 
-    fn load_cursor(&self, name: &[u8]) -> ffi::Cursor {
+    fn load_cursor(&self, name: &[u8], theme: &Option<CString>, size: c_int) -> ffi::Cursor {
+        let theme = theme.as_ref().map_or(ptr::null(), |theme| theme.as_ptr());
         unsafe {
-            (self.xconn.xcursor.XcursorLibraryLoadCursor)(
-                self.xconn.display,
+            let images = (self.xconn.xcursor.XcursorLibraryLoadImages)(
                 name.as_ptr() as *const c_char,
-            )
+                theme,
+                size,
+            );
+            if images.is_null() {
+                return 0;
+            }
+            let cursor = (self.xconn.xcursor.XcursorImagesLoadCursor)(self.xconn.display, images);
+            (self.xconn.xcursor.XcursorImagesDestroy)(images);
+            cursor
         }
     }
 
-    fn load_first_existing_cursor(&self, names: &[&[u8]]) -> ffi::Cursor {
+    fn load_first_existing_cursor(
+        &self,
+        names: &[&[u8]],
+        theme: &Option<CString>,
+        size: c_int,
+    ) -> ffi::Cursor {
         for name in names.iter() {
-            let xcursor = self.load_cursor(name);
+            let xcursor = self.load_cursor(name, theme, size);
             if xcursor != 0 {
                 return xcursor;
             }
@@ -1142,10 +1192,27 @@ impl UnownedWindow {
         0
     }
 
-    fn get_cursor(&self, cursor: CursorIcon) -> ffi::Cursor {
-        let load = |name: &[u8]| self.load_cursor(name);
+    fn get_cursor(&self, cursor: &Cursor, hidpi_factor: f64) -> ffi::Cursor {
+        match cursor {
+            Cursor::Icon(icon) => self.get_icon_cursor(*icon, hidpi_factor),
+            Cursor::Custom(custom) => self.xconn.create_custom_cursor(custom),
+        }
+    }
+
+    fn get_icon_cursor(&self, cursor: CursorIcon, hidpi_factor: f64) -> ffi::Cursor {
+        let (theme, size) = {
+            let cursor_theme = self.cursor_theme.lock().unwrap();
+            let theme = cursor_theme
+                .name
+                .as_ref()
+                .and_then(|name| CString::new(name.as_str()).ok());
+            let size = (f64::from(cursor_theme.size) * hidpi_factor).round() as c_int;
+            (theme, size)
+        };
+
+        let load = |name: &[u8]| self.load_cursor(name, &theme, size);
 
-        let loadn = |names: &[&[u8]]| self.load_first_existing_cursor(names);
+        let loadn = |names: &[&[u8]]| self.load_first_existing_cursor(names, &theme, size);
 
         // Try multiple names in some cases where the name
         // differs on the desktop environments or themes.
@@ -1210,9 +1277,25 @@ impl UnownedWindow {
 
     #[inline]
     pub fn set_cursor_icon(&self, cursor: CursorIcon) {
+        self.set_cursor(Cursor::Icon(cursor));
+    }
+
+    #[inline]
+    pub fn set_custom_cursor(&self, cursor: CustomCursor) {
+        self.set_cursor(Cursor::Custom(cursor));
+    }
+
+    fn set_cursor(&self, cursor: Cursor) {
+        if *self.cursor_visible.lock() {
+            self.update_cursor(self.get_cursor(&cursor, self.hidpi_factor()));
+        }
         *self.cursor.lock() = cursor;
+    }
+
+    /// Loads the cursor of the window again, after the cursor theme or the hidpi factor changed.
+    pub fn refresh_cursor(&self, hidpi_factor: f64) {
         if *self.cursor_visible.lock() {
-            self.update_cursor(self.get_cursor(cursor));
+            self.update_cursor(self.get_cursor(&self.cursor.lock(), hidpi_factor));
         }
     }
 
@@ -1249,17 +1332,21 @@ impl UnownedWindow {
     }
 
     #[inline]
-    pub fn set_cursor_grab(&self, grab: bool) -> Result<(), ExternalError> {
-        let mut grabbed_lock = self.cursor_grabbed.lock();
-        if grab == *grabbed_lock {
+    pub fn set_cursor_grab(&self, mode: CursorGrabMode) -> Result<(), ExternalError> {
+        let mut grabbed_lock = self.cursor_grabbed_mode.lock();
+        if mode == *grabbed_lock {
             return Ok(());
         }
+        // X11 has no way to keep the cursor in place.
+        if mode == CursorGrabMode::Locked {
+            return Err(ExternalError::NotSupported(NotSupportedError::new()));
+        }
         unsafe {
             // We ungrab before grabbing to prevent passive grabs from causing `AlreadyGrabbed`.
             // Therefore, this is common to both codepaths.
             (self.xconn.xlib.XUngrabPointer)(self.xconn.display, ffi::CurrentTime);
         }
-        let result = if grab {
+        let result = if mode == CursorGrabMode::Confined {
             let result = unsafe {
                 (self.xconn.xlib.XGrabPointer)(
                     self.xconn.display,
@@ -1305,7 +1392,7 @@ impl UnownedWindow {
                 .map_err(|err| ExternalError::Os(os_error!(OsError::XError(err))))
         };
         if result.is_ok() {
-            *grabbed_lock = grab;
+            *grabbed_lock = mode;
         }
         result
     }
@@ -1317,7 +1404,7 @@ impl UnownedWindow {
             return;
         }
         let cursor = if visible {
-            self.get_cursor(*self.cursor.lock())
+            self.get_cursor(&self.cursor.lock(), self.hidpi_factor())
         } else {
             self.create_empty_cursor()
                 .expect("Failed to create empty cursor")
@@ -1350,17 +1437,63 @@ impl UnownedWindow {
         self.set_cursor_position_physical(x, y)
     }
 
-    pub(crate) fn set_ime_position_physical(&self, x: i32, y: i32) {
+    pub(crate) fn set_ime_cursor_area_physical(&self, x: i32, y: i32, width: u32, height: u32) {
+        let _ = self.ime_sender.lock().send(ImeRequest::CursorArea(
+            self.xwindow,
+            ffi::XRectangle {
+                x: x as _,
+                y: y as _,
+                width: width as _,
+                height: height as _,
+            },
+        ));
+    }
+
+    #[inline]
+    pub fn set_ime_cursor_area(&self, position: LogicalPosition, size: LogicalSize) {
+        let dpi_factor = self.hidpi_factor();
+        let (x, y) = position.to_physical(dpi_factor).into();
+        let (width, height) = size.to_physical(dpi_factor).into();
+        self.set_ime_cursor_area_physical(x, y, width, height);
+    }
+
+    #[inline]
+    pub fn set_ime_allowed(&self, allowed: bool) {
+        *self.ime_allowed.lock() = allowed;
+        self.update_ime_allowed();
+    }
+
+    #[inline]
+    pub fn set_ime_purpose(&self, purpose: ImePurpose) {
+        *self.ime_purpose.lock() = purpose;
+        self.update_ime_allowed();
+    }
+
+    // XIM has no notion of purpose, so passwords are kept away from the input method by
+    // disabling it.
+    fn update_ime_allowed(&self) {
+        let allowed = *self.ime_allowed.lock() && *self.ime_purpose.lock() != ImePurpose::Password;
         let _ = self
             .ime_sender
             .lock()
-            .send((self.xwindow, x as i16, y as i16));
+            .send(ImeRequest::Allowed(self.xwindow, allowed));
     }
 
     #[inline]
-    pub fn set_ime_position(&self, logical_spot: LogicalPosition) {
-        let (x, y) = logical_spot.to_physical(self.hidpi_factor()).into();
-        self.set_ime_position_physical(x, y);
+    pub fn start_drag(
+        &self,
+        data: Vec<(String, Vec<u8>)>,
+        icon: Option<Icon>,
+    ) -> Result<(), ExternalError> {
+        let request = DragRequest {
+            window: self.xwindow,
+            data,
+            icon,
+        };
+        self.drag_requests
+            .lock()
+            .send(request)
+            .map_err(|_| ExternalError::Os(os_error!(OsError::XMisc("The event loop is gone"))))
     }
 
     #[inline]
@@ -0,0 +1,141 @@
+#![allow(dead_code, non_camel_case_types)]
+
+use std::os::raw::{c_char, c_int, c_void};
+
+pub const XKB_MOD_NAME_SHIFT: &[u8] = b"Shift\0";
+pub const XKB_MOD_NAME_CAPS: &[u8] = b"Lock\0";
+pub const XKB_MOD_NAME_CTRL: &[u8] = b"Control\0";
+pub const XKB_MOD_NAME_ALT: &[u8] = b"Mod1\0";
+pub const XKB_MOD_NAME_NUM: &[u8] = b"Mod2\0";
+pub const XKB_MOD_NAME_LOGO: &[u8] = b"Mod4\0";
+
+pub enum xkb_context {}
+pub enum xkb_keymap {}
+pub enum xkb_state {}
+pub enum xkb_compose_table {}
+pub enum xkb_compose_state {}
+pub type xcb_connection_t = c_void;
+
+pub type xkb_keycode_t = u32;
+pub type xkb_keysym_t = u32;
+pub type xkb_layout_index_t = u32;
+pub type xkb_mod_mask_t = u32;
+
+pub type xkb_context_flags = c_int;
+pub const XKB_CONTEXT_NO_FLAGS: xkb_context_flags = 0;
+
+pub type xkb_keymap_format = c_int;
+pub const XKB_KEYMAP_FORMAT_TEXT_V1: xkb_keymap_format = 1;
+
+pub type xkb_keymap_compile_flags = c_int;
+pub const XKB_KEYMAP_COMPILE_NO_FLAGS: xkb_keymap_compile_flags = 0;
+
+pub type xkb_state_component = c_int;
+pub const XKB_STATE_MODS_DEPRESSED: xkb_state_component = 1 << 0;
+pub const XKB_STATE_MODS_LATCHED: xkb_state_component = 1 << 1;
+pub const XKB_STATE_MODS_LOCKED: xkb_state_component = 1 << 2;
+pub const XKB_STATE_MODS_EFFECTIVE: xkb_state_component = 1 << 3;
+pub const XKB_STATE_LAYOUT_EFFECTIVE: xkb_state_component = 1 << 7;
+
+pub type xkb_compose_compile_flags = c_int;
+pub const XKB_COMPOSE_COMPILE_NO_FLAGS: xkb_compose_compile_flags = 0;
+
+pub type xkb_compose_state_flags = c_int;
+pub const XKB_COMPOSE_STATE_NO_FLAGS: xkb_compose_state_flags = 0;
+
+pub type xkb_compose_status = c_int;
+pub const XKB_COMPOSE_NOTHING: xkb_compose_status = 0;
+pub const XKB_COMPOSE_COMPOSING: xkb_compose_status = 1;
+pub const XKB_COMPOSE_COMPOSED: xkb_compose_status = 2;
+pub const XKB_COMPOSE_CANCELLED: xkb_compose_status = 3;
+
+pub type xkb_compose_feed_result = c_int;
+pub const XKB_COMPOSE_FEED_IGNORED: xkb_compose_feed_result = 0;
+pub const XKB_COMPOSE_FEED_ACCEPTED: xkb_compose_feed_result = 1;
+
+pub type xkb_x11_setup_xkb_extension_flags = c_int;
+pub const XKB_X11_SETUP_XKB_EXTENSION_NO_FLAGS: xkb_x11_setup_xkb_extension_flags = 0;
+pub const XKB_X11_MIN_MAJOR_XKB_VERSION: u16 = 1;
+pub const XKB_X11_MIN_MINOR_XKB_VERSION: u16 = 0;
+
+dlopen_external_library!(XkbCommon,
+functions:
+    fn xkb_context_new(xkb_context_flags) -> *mut xkb_context,
+    fn xkb_context_unref(*mut xkb_context) -> (),
+    fn xkb_keymap_new_from_string(
+        *mut xkb_context,
+        *const c_char,
+        xkb_keymap_format,
+        xkb_keymap_compile_flags
+    ) -> *mut xkb_keymap,
+    fn xkb_keymap_unref(*mut xkb_keymap) -> (),
+    fn xkb_keymap_key_repeats(*mut xkb_keymap, xkb_keycode_t) -> c_int,
+    fn xkb_keymap_num_layouts(*mut xkb_keymap) -> xkb_layout_index_t,
+    fn xkb_keymap_layout_get_name(*mut xkb_keymap, xkb_layout_index_t) -> *const c_char,
+    fn xkb_state_new(*mut xkb_keymap) -> *mut xkb_state,
+    fn xkb_state_unref(*mut xkb_state) -> (),
+    fn xkb_state_update_mask(
+        *mut xkb_state,
+        xkb_mod_mask_t,
+        xkb_mod_mask_t,
+        xkb_mod_mask_t,
+        xkb_layout_index_t,
+        xkb_layout_index_t,
+        xkb_layout_index_t
+    ) -> xkb_state_component,
+    fn xkb_state_serialize_layout(*mut xkb_state, xkb_state_component) -> xkb_layout_index_t,
+    fn xkb_state_key_get_one_sym(*mut xkb_state, xkb_keycode_t) -> xkb_keysym_t,
+    fn xkb_state_key_get_utf8(*mut xkb_state, xkb_keycode_t, *mut c_char, usize) -> c_int,
+    fn xkb_state_mod_name_is_active(
+        *mut xkb_state,
+        *const c_char,
+        xkb_state_component
+    ) -> c_int,
+    fn xkb_compose_table_new_from_locale(
+        *mut xkb_context,
+        *const c_char,
+        xkb_compose_compile_flags
+    ) -> *mut xkb_compose_table,
+    fn xkb_compose_table_unref(*mut xkb_compose_table) -> (),
+    fn xkb_compose_state_new(
+        *mut xkb_compose_table,
+        xkb_compose_state_flags
+    ) -> *mut xkb_compose_state,
+    fn xkb_compose_state_unref(*mut xkb_compose_state) -> (),
+    fn xkb_compose_state_feed(*mut xkb_compose_state, xkb_keysym_t) -> xkb_compose_feed_result,
+    fn xkb_compose_state_reset(*mut xkb_compose_state) -> (),
+    fn xkb_compose_state_get_status(*mut xkb_compose_state) -> xkb_compose_status,
+    fn xkb_compose_state_get_utf8(*mut xkb_compose_state, *mut c_char, usize) -> c_int,
+);
+
+dlopen_external_library!(XkbCommonX11,
+functions:
+    fn xkb_x11_setup_xkb_extension(
+        *mut xcb_connection_t,
+        u16,
+        u16,
+        xkb_x11_setup_xkb_extension_flags,
+        *mut u16,
+        *mut u16,
+        *mut u8,
+        *mut u8
+    ) -> c_int,
+    fn xkb_x11_get_core_keyboard_device_id(*mut xcb_connection_t) -> i32,
+    fn xkb_x11_keymap_new_from_device(
+        *mut xkb_context,
+        *mut xcb_connection_t,
+        i32,
+        xkb_keymap_compile_flags
+    ) -> *mut xkb_keymap,
+    fn xkb_x11_state_new_from_device(*mut xkb_keymap, *mut xcb_connection_t, i32) -> *mut xkb_state,
+);
+
+lazy_static! {
+    pub static ref XKBCOMMON_OPTION: Option<XkbCommon> = XkbCommon::open("libxkbcommon.so.0")
+        .or_else(|_| XkbCommon::open("libxkbcommon.so"))
+        .ok();
+    pub static ref XKBCOMMON_X11_OPTION: Option<XkbCommonX11> =
+        XkbCommonX11::open("libxkbcommon-x11.so.0")
+            .or_else(|_| XkbCommonX11::open("libxkbcommon-x11.so"))
+            .ok();
+}
@@ -0,0 +1,441 @@
+//! The keymap and the keyboard state, tracked with xkbcommon on both X11 and Wayland.
+//!
+//! The keymap is loaded from the X server with xkbcommon-x11 on X11, and from the file descriptor
+//! of `wl_keyboard.keymap` on Wayland. The state of the modifiers and layout is then set from the
+//! XKB state notifications of the X server, or from `wl_keyboard.modifiers`.
+use std::{
+    collections::HashSet,
+    env,
+    ffi::{CStr, CString},
+    os::raw::c_char,
+    os::unix::ffi::OsStringExt,
+    os::unix::io::RawFd,
+    ptr,
+};
+
+use self::ffi::{XkbCommon, XKBCOMMON_OPTION, XKBCOMMON_X11_OPTION};
+use super::keyboard;
+use crate::{
+    event::{ElementState, KeyEvent, ModifiersState},
+    keyboard::{Key, KeyCode, KeyboardLayout},
+};
+
+mod ffi;
+
+pub use self::ffi::xcb_connection_t;
+
+/// The keymap of a keyboard and the state of its modifiers and layout.
+pub struct KbdState {
+    xkbh: &'static XkbCommon,
+    context: *mut ffi::xkb_context,
+    // Both null until a keymap is set.
+    keymap: *mut ffi::xkb_keymap,
+    state: *mut ffi::xkb_state,
+    // Both null if there's no compose table for the locale.
+    compose_table: *mut ffi::xkb_compose_table,
+    compose_state: *mut ffi::xkb_compose_state,
+    mods_state: ModifiersState,
+    // The modifier keys held, to tell the left and right keys apart.
+    modifier_keys: HashSet<KeyCode>,
+}
+
+impl KbdState {
+    /// Returns `None` if xkbcommon isn't available.
+    pub fn new() -> Option<KbdState> {
+        let xkbh = XKBCOMMON_OPTION.as_ref()?;
+        let context = unsafe { (xkbh.xkb_context_new)(ffi::XKB_CONTEXT_NO_FLAGS) };
+        if context.is_null() {
+            return None;
+        }
+        let mut kbd_state = KbdState {
+            xkbh,
+            context,
+            keymap: ptr::null_mut(),
+            state: ptr::null_mut(),
+            compose_table: ptr::null_mut(),
+            compose_state: ptr::null_mut(),
+            mods_state: ModifiersState::default(),
+            modifier_keys: HashSet::new(),
+        };
+        unsafe { kbd_state.init_compose() };
+        Some(kbd_state)
+    }
+
+    unsafe fn init_compose(&mut self) {
+        // The compose sequences depend on the locale, chosen like `setlocale` does.
+        let locale = ["LC_ALL", "LC_CTYPE", "LANG"]
+            .iter()
+            .filter_map(env::var_os)
+            .find(|locale| !locale.is_empty())
+            .unwrap_or_else(|| "C".into());
+        let locale = match CString::new(locale.into_vec()) {
+            Ok(locale) => locale,
+            Err(_) => return,
+        };
+
+        let compose_table = (self.xkbh.xkb_compose_table_new_from_locale)(
+            self.context,
+            locale.as_ptr(),
+            ffi::XKB_COMPOSE_COMPILE_NO_FLAGS,
+        );
+        if compose_table.is_null() {
+            // Keys are then processed without compose sequences.
+            return;
+        }
+        let compose_state =
+            (self.xkbh.xkb_compose_state_new)(compose_table, ffi::XKB_COMPOSE_STATE_NO_FLAGS);
+        if compose_state.is_null() {
+            (self.xkbh.xkb_compose_table_unref)(compose_table);
+            return;
+        }
+        self.compose_table = compose_table;
+        self.compose_state = compose_state;
+    }
+
+    unsafe fn set_keymap(&mut self, keymap: *mut ffi::xkb_keymap, state: *mut ffi::xkb_state) {
+        (self.xkbh.xkb_state_unref)(self.state);
+        (self.xkbh.xkb_keymap_unref)(self.keymap);
+        self.keymap = keymap;
+        self.state = state;
+        self.update_mods_state();
+    }
+
+    /// Loads the keymap given by a Wayland compositor, as a file descriptor to a string in the
+    /// XKB text format. The file descriptor is closed.
+    pub unsafe fn set_keymap_from_fd(&mut self, fd: RawFd, size: usize) {
+        let map = libc::mmap(
+            ptr::null_mut(),
+            size,
+            libc::PROT_READ,
+            libc::MAP_PRIVATE,
+            fd,
+            0,
+        );
+        libc::close(fd);
+        if map == libc::MAP_FAILED {
+            warn!("Failed to map the keymap of the compositor");
+            return;
+        }
+        let keymap = (self.xkbh.xkb_keymap_new_from_string)(
+            self.context,
+            map as *const c_char,
+            ffi::XKB_KEYMAP_FORMAT_TEXT_V1,
+            ffi::XKB_KEYMAP_COMPILE_NO_FLAGS,
+        );
+        libc::munmap(map, size);
+        if keymap.is_null() {
+            warn!("Received an invalid keymap from the compositor");
+            return;
+        }
+        let state = (self.xkbh.xkb_state_new)(keymap);
+        self.set_keymap(keymap, state);
+    }
+
+    /// Loads the keymap and state of an X11 keyboard, which must be called again whenever the X
+    /// server notifies that the keymap changed. Returns `false` if xkbcommon-x11 isn't available.
+    pub unsafe fn set_keymap_from_x11(
+        &mut self,
+        connection: *mut xcb_connection_t,
+        device_id: i32,
+    ) -> bool {
+        let xkbh_x11 = match XKBCOMMON_X11_OPTION.as_ref() {
+            Some(xkbh_x11) => xkbh_x11,
+            None => return false,
+        };
+        let keymap = (xkbh_x11.xkb_x11_keymap_new_from_device)(
+            self.context,
+            connection,
+            device_id,
+            ffi::XKB_KEYMAP_COMPILE_NO_FLAGS,
+        );
+        if keymap.is_null() {
+            return false;
+        }
+        let state = (xkbh_x11.xkb_x11_state_new_from_device)(keymap, connection, device_id);
+        self.set_keymap(keymap, state);
+        true
+    }
+
+    #[inline]
+    fn ready(&self) -> bool {
+        !self.state.is_null()
+    }
+
+    pub fn update_modifiers(
+        &mut self,
+        mods_depressed: u32,
+        mods_latched: u32,
+        mods_locked: u32,
+        depressed_group: u32,
+        latched_group: u32,
+        locked_group: u32,
+    ) {
+        if !self.ready() {
+            return;
+        }
+        let changed = unsafe {
+            (self.xkbh.xkb_state_update_mask)(
+                self.state,
+                mods_depressed,
+                mods_latched,
+                mods_locked,
+                depressed_group,
+                latched_group,
+                locked_group,
+            )
+        };
+        if changed & ffi::XKB_STATE_MODS_EFFECTIVE != 0 {
+            self.update_mods_state();
+        }
+    }
+
+    fn update_mods_state(&mut self) {
+        let (xkbh, state) = (self.xkbh, self.state);
+        let is_active = |name: &[u8]| unsafe {
+            (xkbh.xkb_state_mod_name_is_active)(
+                state,
+                name.as_ptr() as *const c_char,
+                ffi::XKB_STATE_MODS_EFFECTIVE,
+            ) > 0
+        };
+        self.mods_state = ModifiersState {
+            shift: is_active(ffi::XKB_MOD_NAME_SHIFT),
+            ctrl: is_active(ffi::XKB_MOD_NAME_CTRL),
+            alt: is_active(ffi::XKB_MOD_NAME_ALT),
+            logo: is_active(ffi::XKB_MOD_NAME_LOGO),
+            caps_lock: is_active(ffi::XKB_MOD_NAME_CAPS),
+            num_lock: is_active(ffi::XKB_MOD_NAME_NUM),
+            ..Default::default()
+        };
+        self.update_mods_sides();
+    }
+
+    // Only the sides of the active modifiers are set, as a modifier key may be bound to another
+    // modifier by the layout.
+    fn update_mods_sides(&mut self) {
+        let keys = &self.modifier_keys;
+        let mods = &mut self.mods_state;
+        mods.lshift = mods.shift && keys.contains(&KeyCode::ShiftLeft);
+        mods.rshift = mods.shift && keys.contains(&KeyCode::ShiftRight);
+        mods.lctrl = mods.ctrl && keys.contains(&KeyCode::ControlLeft);
+        mods.rctrl = mods.ctrl && keys.contains(&KeyCode::ControlRight);
+        mods.lalt = mods.alt && keys.contains(&KeyCode::AltLeft);
+        mods.ralt = mods.alt && keys.contains(&KeyCode::AltRight);
+        mods.llogo = mods.logo && keys.contains(&KeyCode::SuperLeft);
+        mods.rlogo = mods.logo && keys.contains(&KeyCode::SuperRight);
+    }
+
+    /// Tracks which modifier keys are held, for the modifiers to tell the left and right keys
+    /// apart. Keycodes are the evdev scan codes plus 8, as on X11.
+    pub fn update_modifier_key(&mut self, keycode: u32, state: ElementState) {
+        let key = keyboard::scancode_to_keycode(keycode.wrapping_sub(8));
+        let other_key = match key {
+            KeyCode::ShiftLeft => KeyCode::ShiftRight,
+            KeyCode::ShiftRight => KeyCode::ShiftLeft,
+            KeyCode::ControlLeft => KeyCode::ControlRight,
+            KeyCode::ControlRight => KeyCode::ControlLeft,
+            KeyCode::AltLeft => KeyCode::AltRight,
+            KeyCode::AltRight => KeyCode::AltLeft,
+            KeyCode::SuperLeft => KeyCode::SuperRight,
+            KeyCode::SuperRight => KeyCode::SuperLeft,
+            _ => return,
+        };
+        match state {
+            ElementState::Pressed => {
+                self.modifier_keys.insert(key);
+            }
+            ElementState::Released => {
+                self.modifier_keys.remove(&key);
+                // Releasing the last key of a modifier is followed by a modifiers update, which
+                // changes both the modifier and its side at once.
+                if !self.modifier_keys.contains(&other_key) {
+                    return;
+                }
+            }
+        }
+        self.update_mods_sides();
+    }
+
+    /// Forgets the modifier keys held, whose release isn't reported once the window loses the
+    /// focus.
+    pub fn clear_modifier_keys(&mut self) {
+        self.modifier_keys.clear();
+        self.update_mods_sides();
+    }
+
+    /// The modifiers currently active.
+    pub fn mods_state(&self) -> ModifiersState {
+        self.mods_state
+    }
+
+    /// The layouts of the keymap.
+    pub fn layouts(&self) -> Vec<KeyboardLayout> {
+        if !self.ready() {
+            return Vec::new();
+        }
+        let num_layouts = unsafe { (self.xkbh.xkb_keymap_num_layouts)(self.keymap) };
+        (0..num_layouts)
+            .map(|index| self.layout_at(index))
+            .collect()
+    }
+
+    /// The active layout.
+    pub fn layout(&self) -> Option<KeyboardLayout> {
+        if !self.ready() {
+            return None;
+        }
+        let index = unsafe {
+            (self.xkbh.xkb_state_serialize_layout)(self.state, ffi::XKB_STATE_LAYOUT_EFFECTIVE)
+        };
+        Some(self.layout_at(index))
+    }
+
+    fn layout_at(&self, index: u32) -> KeyboardLayout {
+        let name = unsafe { (self.xkbh.xkb_keymap_layout_get_name)(self.keymap, index) };
+        let name = if name.is_null() {
+            String::new()
+        } else {
+            unsafe { CStr::from_ptr(name) }
+                .to_string_lossy()
+                .into_owned()
+        };
+        KeyboardLayout { index, name }
+    }
+
+    /// Returns whether holding down a key repeats it. Keycodes are the evdev scan codes plus 8,
+    /// as on X11.
+    pub fn key_repeats(&self, keycode: u32) -> bool {
+        self.ready() && unsafe { (self.xkbh.xkb_keymap_key_repeats)(self.keymap, keycode) == 1 }
+    }
+
+    /// The keysym a key produces with the current modifiers and layout.
+    pub fn keysym(&self, keycode: u32) -> u32 {
+        if !self.ready() {
+            return 0;
+        }
+        unsafe { (self.xkbh.xkb_state_key_get_one_sym)(self.state, keycode) }
+    }
+
+    /// The text a key produces with the current modifiers and layout, outside of compose
+    /// sequences.
+    pub fn text(&self, keycode: u32) -> Option<String> {
+        if !self.ready() {
+            return None;
+        }
+        unsafe {
+            get_string(|buffer, size| {
+                (self.xkbh.xkb_state_key_get_utf8)(self.state, keycode, buffer, size)
+            })
+        }
+    }
+
+    /// The meaning of a key with the current modifiers and layout, as if it was pressed now.
+    pub fn key(&self, keycode: u32) -> Key {
+        let text = self.text(keycode);
+        keyboard::keysym_to_key(self.keysym(keycode), text.as_deref())
+    }
+
+    // Feeds a key press to the compose state, returning the text it produces.
+    fn compose(&mut self, keycode: u32, keysym: u32) -> Option<String> {
+        if self.compose_state.is_null() {
+            return self.text(keycode);
+        }
+        let xkbh = self.xkbh;
+        let compose_state = self.compose_state;
+        unsafe {
+            if (xkbh.xkb_compose_state_feed)(compose_state, keysym) == ffi::XKB_COMPOSE_FEED_IGNORED
+            {
+                // Modifiers don't take part in compose sequences.
+                return self.text(keycode);
+            }
+            match (xkbh.xkb_compose_state_get_status)(compose_state) {
+                ffi::XKB_COMPOSE_COMPOSING => None,
+                ffi::XKB_COMPOSE_COMPOSED => {
+                    let text = get_string(|buffer, size| {
+                        (xkbh.xkb_compose_state_get_utf8)(compose_state, buffer, size)
+                    });
+                    (xkbh.xkb_compose_state_reset)(compose_state);
+                    text
+                }
+                ffi::XKB_COMPOSE_CANCELLED => {
+                    (xkbh.xkb_compose_state_reset)(compose_state);
+                    None
+                }
+                _ => self.text(keycode),
+            }
+        }
+    }
+
+    /// Builds the event of a key press or release, going through compose sequences.
+    pub fn process_key_event(
+        &mut self,
+        keycode: u32,
+        state: ElementState,
+        repeat: bool,
+    ) -> KeyEvent {
+        let keysym = self.keysym(keycode);
+        let text = match state {
+            ElementState::Pressed if self.ready() => self.compose(keycode, keysym),
+            _ => None,
+        };
+        keyboard::key_event(keycode - 8, keysym, text, state, repeat)
+    }
+}
+
+// The xkbcommon objects are only ever used by one thread at a time, as Wayland repeats keys from
+// another thread.
+unsafe impl Send for KbdState {}
+
+impl Drop for KbdState {
+    fn drop(&mut self) {
+        unsafe {
+            (self.xkbh.xkb_compose_state_unref)(self.compose_state);
+            (self.xkbh.xkb_compose_table_unref)(self.compose_table);
+            (self.xkbh.xkb_state_unref)(self.state);
+            (self.xkbh.xkb_keymap_unref)(self.keymap);
+            (self.xkbh.xkb_context_unref)(self.context);
+        }
+    }
+}
+
+// Reads a string from one of the xkbcommon functions that write it to a buffer, and return the
+// size it needs.
+unsafe fn get_string<F>(mut get: F) -> Option<String>
+where
+    F: FnMut(*mut c_char, usize) -> i32,
+{
+    let size = get(ptr::null_mut(), 0);
+    if size <= 0 {
+        return None;
+    }
+    // The string is followed by a null byte.
+    let mut buffer = vec![0u8; size as usize + 1];
+    get(buffer.as_mut_ptr() as *mut c_char, buffer.len());
+    buffer.pop();
+    String::from_utf8(buffer).ok()
+}
+
+/// Sets up the XKB extension for xkbcommon-x11, returning the ID of the core keyboard. Returns
+/// `None` if xkbcommon-x11 isn't available.
+pub unsafe fn setup_x11(connection: *mut xcb_connection_t) -> Option<i32> {
+    let xkbh_x11 = XKBCOMMON_X11_OPTION.as_ref()?;
+    let ok = (xkbh_x11.xkb_x11_setup_xkb_extension)(
+        connection,
+        ffi::XKB_X11_MIN_MAJOR_XKB_VERSION,
+        ffi::XKB_X11_MIN_MINOR_XKB_VERSION,
+        ffi::XKB_X11_SETUP_XKB_EXTENSION_NO_FLAGS,
+        ptr::null_mut(),
+        ptr::null_mut(),
+        ptr::null_mut(),
+        ptr::null_mut(),
+    );
+    if ok != 1 {
+        return None;
+    }
+    let device_id = (xkbh_x11.xkb_x11_get_core_keyboard_device_id)(connection);
+    if device_id == -1 {
+        None
+    } else {
+        Some(device_id)
+    }
+}
@@ -33,6 +33,28 @@ impl DeviceId {
     }
 }
 
+#[derive(Clone, Debug, PartialEq)]
+pub enum DragOffer {}
+
+impl DragOffer {
+    pub fn mime_types(&self) -> &[String] {
+        match *self {}
+    }
+
+    pub fn request_data(&self, _mime_type: String) {
+        match *self {}
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum DragReply {}
+
+impl DragReply {
+    pub fn reply(&self, _action: Option<crate::event::DragAction>) {
+        match *self {}
+    }
+}
+
 // Constant device ID; to be removed when if backend is updated to report real device IDs.
 pub(crate) const DEVICE_ID: RootDeviceId = RootDeviceId(DeviceId);
 
@@ -46,7 +46,8 @@ struct Modifiers {
 struct ViewState {
     ns_window: id,
     pub cursor: Arc<Mutex<util::Cursor>>,
-    ime_spot: Option<(f64, f64)>,
+    // The area of the IME cursor in screen coordinates, from its bottom left corner.
+    ime_area: Option<(f64, f64, f64, f64)>,
     raw_characters: Option<String>,
     is_key_down: bool,
     modifiers: Modifiers,
@@ -58,7 +59,7 @@ pub fn new_view(ns_window: id) -> (IdRef, Weak<Mutex<util::Cursor>>) {
     let state = ViewState {
         ns_window,
         cursor,
-        ime_spot: None,
+        ime_area: None,
         raw_characters: None,
         is_key_down: false,
         modifiers: Default::default(),
@@ -74,14 +75,21 @@ pub fn new_view(ns_window: id) -> (IdRef, Weak<Mutex<util::Cursor>>) {
     }
 }
 
-pub unsafe fn set_ime_position(ns_view: id, input_context: id, x: f64, y: f64) {
+pub unsafe fn set_ime_cursor_area(
+    ns_view: id,
+    input_context: id,
+    x: f64,
+    y: f64,
+    width: f64,
+    height: f64,
+) {
     let state_ptr: *mut c_void = *(*ns_view).get_mut_ivar("winitState");
     let state = &mut *(state_ptr as *mut ViewState);
     let content_rect =
         NSWindow::contentRectForFrameRect_(state.ns_window, NSWindow::frame(state.ns_window));
     let base_x = content_rect.origin.x as f64;
     let base_y = (content_rect.origin.y + content_rect.size.height) as f64;
-    state.ime_spot = Some((base_x + x, base_y - y));
+    state.ime_area = Some((base_x + x, base_y - y - height, width, height));
     let _: () = msg_send![input_context, invalidateCharacterCoordinates];
 }
 
@@ -414,17 +422,20 @@ extern "C" fn first_rect_for_character_range(
         trace!("Triggered `firstRectForCharacterRange`");
         let state_ptr: *mut c_void = *this.get_ivar("winitState");
         let state = &mut *(state_ptr as *mut ViewState);
-        let (x, y) = state.ime_spot.unwrap_or_else(|| {
+        let (x, y, width, height) = state.ime_area.unwrap_or_else(|| {
             let content_rect = NSWindow::contentRectForFrameRect_(
                 state.ns_window,
                 NSWindow::frame(state.ns_window),
             );
             let x = content_rect.origin.x;
             let y = util::bottom_left_to_top_left(content_rect);
-            (x, y)
+            (x, y, 0.0, 0.0)
         });
         trace!("Completed `firstRectForCharacterRange`");
-        NSRect::new(NSPoint::new(x as _, y as _), NSSize::new(0.0, 0.0))
+        NSRect::new(
+            NSPoint::new(x as _, y as _),
+            NSSize::new(width as _, height as _),
+        )
     }
 }
 
@@ -566,6 +577,7 @@ extern "C" fn key_down(this: &Object, _sel: Sel, event: id) {
                     scancode,
                     virtual_keycode,
                     modifiers: event_mods(event),
+                    is_repeat,
                 },
             },
         };
@@ -617,6 +629,7 @@ extern "C" fn key_up(this: &Object, _sel: Sel, event: id) {
                     scancode,
                     virtual_keycode,
                     modifiers: event_mods(event),
+                    is_repeat: false,
                 },
             },
         };
@@ -725,6 +738,7 @@ extern "C" fn cancel_operation(this: &Object, _sel: Sel, _sender: id) {
                     scancode: scancode as _,
                     virtual_keycode,
                     modifiers: event_mods(event),
+                    is_repeat: false,
                 },
             },
         };
@@ -23,7 +23,10 @@ use crate::{
         window_delegate::new_delegate,
         OsError,
     },
-    window::{CursorIcon, Fullscreen, WindowAttributes, WindowId as RootWindowId},
+    window::{
+        CursorGrabMode, CursorIcon, CustomCursor, Fullscreen, ImePurpose, WindowAttributes,
+        WindowId as RootWindowId,
+    },
 };
 use cocoa::{
     appkit::{
@@ -519,8 +522,20 @@ impl UnownedWindow {
     }
 
     #[inline]
-    pub fn set_cursor_grab(&self, grab: bool) -> Result<(), ExternalError> {
-        // TODO: Do this for real https://stackoverflow.com/a/40922095/5435443
+    pub fn set_custom_cursor(&self, _cursor: CustomCursor) {
+        // N/A
+    }
+
+    #[inline]
+    pub fn set_cursor_grab(&self, mode: CursorGrabMode) -> Result<(), ExternalError> {
+        let grab = match mode {
+            CursorGrabMode::None => false,
+            CursorGrabMode::Locked => true,
+            // TODO: Do this for real https://stackoverflow.com/a/40922095/5435443
+            CursorGrabMode::Confined => {
+                return Err(ExternalError::NotSupported(NotSupportedError::new()))
+            }
+        };
         CGDisplay::associate_mouse_and_mouse_cursor_position(!grab)
             .map_err(|status| ExternalError::Os(os_error!(OsError::CGError(status))))
     }
@@ -870,17 +885,34 @@ impl UnownedWindow {
     }
 
     #[inline]
-    pub fn set_ime_position(&self, logical_spot: LogicalPosition) {
+    pub fn set_ime_cursor_area(&self, position: LogicalPosition, size: LogicalSize) {
         unsafe {
-            view::set_ime_position(
+            view::set_ime_cursor_area(
                 *self.ns_view,
                 *self.input_context,
-                logical_spot.x,
-                logical_spot.y,
+                position.x,
+                position.y,
+                size.width,
+                size.height,
             );
         }
     }
 
+    #[inline]
+    pub fn set_ime_allowed(&self, _allowed: bool) {}
+
+    #[inline]
+    pub fn set_ime_purpose(&self, _purpose: ImePurpose) {}
+
+    #[inline]
+    pub fn start_drag(
+        &self,
+        _data: Vec<(String, Vec<u8>)>,
+        _icon: Option<Icon>,
+    ) -> Result<(), ExternalError> {
+        Err(ExternalError::NotSupported(NotSupportedError::new()))
+    }
+
     #[inline]
     pub fn current_monitor(&self) -> RootMonitorHandle {
         unsafe {
@@ -1159,6 +1159,8 @@ unsafe extern "system" fn public_window_callback<T>(
                                 scancode,
                                 virtual_keycode: vkey,
                                 modifiers: event::get_key_mods(),
+                                // Bit 30 is set if the key was already down.
+                                is_repeat: lparam & (1 << 30) != 0,
                             },
                         },
                     });
@@ -1187,6 +1189,7 @@ unsafe extern "system" fn public_window_callback<T>(
                             scancode,
                             virtual_keycode: vkey,
                             modifiers: event::get_key_mods(),
+                            is_repeat: false,
                         },
                     },
                 });
@@ -1450,6 +1453,7 @@ unsafe extern "system" fn public_window_callback<T>(
                                     state,
                                     virtual_keycode,
                                     modifiers: event::get_key_mods(),
+                                    is_repeat: false,
                                 }),
                             });
                         }
@@ -1499,6 +1503,8 @@ unsafe extern "system" fn public_window_callback<T>(
                                 continue;
                             },
                             location,
+                            force: None,
+                            contact: None,
                             id: input.dwID as u64,
                             device_id: DEVICE_ID,
                         }),
@@ -1603,6 +1609,8 @@ unsafe extern "system" fn public_window_callback<T>(
                                 continue;
                             },
                             location,
+                            force: None,
+                            contact: None,
                             id: pointer_info.pointerId as u64,
                             device_id: DEVICE_ID,
                         }),
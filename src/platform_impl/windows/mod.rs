@@ -48,6 +48,28 @@ impl DeviceId {
 // Constant device ID, to be removed when this backend is updated to report real device IDs.
 const DEVICE_ID: RootDeviceId = RootDeviceId(DeviceId(0));
 
+#[derive(Clone, Debug, PartialEq)]
+pub enum DragOffer {}
+
+impl DragOffer {
+    pub fn mime_types(&self) -> &[String] {
+        match *self {}
+    }
+
+    pub fn request_data(&self, _mime_type: String) {
+        match *self {}
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum DragReply {}
+
+impl DragReply {
+    pub fn reply(&self, _action: Option<crate::event::DragAction>) {
+        match *self {}
+    }
+}
+
 fn wrap_device_id(id: u32) -> RootDeviceId {
     RootDeviceId(DeviceId(id))
 }
@@ -616,12 +616,85 @@ impl Window {
 
     /// Sets location of IME candidate box in client area coordinates relative to the top left.
     ///
+    /// This is the same as `set_ime_cursor_area` with an empty size.
+    ///
     /// ## Platform-specific
     ///
     /// **iOS:** Has no effect.
     #[inline]
     pub fn set_ime_position(&self, position: LogicalPosition) {
-        self.window.set_ime_position(position)
+        self.set_ime_cursor_area(position, LogicalSize::new(0.0, 0.0))
+    }
+
+    /// Sets the area of the text cursor in client area coordinates relative to the top left, for
+    /// the IME candidate box to be placed next to it without covering the text being composed.
+    ///
+    /// `position` is the top left corner of the area, usually the text cursor, and `size` is
+    /// usually the size of the cursor, or the height of the text line it's on.
+    ///
+    /// ## Platform-specific
+    ///
+    /// - **X11:** The candidate box is placed below the area.
+    /// - **iOS / Android / Emscripten:** Has no effect.
+    #[inline]
+    pub fn set_ime_cursor_area(&self, position: LogicalPosition, size: LogicalSize) {
+        self.window.set_ime_cursor_area(position, size)
+    }
+
+    /// Sets whether an input method may be used to compose text in the window.
+    ///
+    /// Input methods are allowed by default. Disallowing them is useful when the window has no
+    /// text field focused, for keys to reach the application instead of the input method, which
+    /// ends any composition in progress and emits `WindowEvent::Ime(Ime::Disabled)`.
+    ///
+    /// ## Platform-specific
+    ///
+    /// - **X11:** Without an input method, `WindowEvent::ReceivedCharacter` is limited to the
+    ///   Latin-1 characters.
+    /// - **Windows / macOS / iOS / Android / Emscripten:** Has no effect.
+    #[inline]
+    pub fn set_ime_allowed(&self, allowed: bool) {
+        self.window.set_ime_allowed(allowed)
+    }
+
+    /// Sets the kind of text expected by the window, for input methods and on-screen keyboards to
+    /// adapt to it.
+    ///
+    /// ## Platform-specific
+    ///
+    /// - **Wayland:** Requires the compositor to support the text input protocol.
+    /// - **X11:** Only `ImePurpose::Password` has an effect, disabling the input method as
+    ///   `set_ime_allowed(false)` would until another purpose is set.
+    /// - **Windows / macOS / iOS / Android / Emscripten:** Has no effect.
+    #[inline]
+    pub fn set_ime_purpose(&self, purpose: ImePurpose) {
+        self.window.set_ime_purpose(purpose)
+    }
+
+    /// Starts dragging data out of the window, to be dropped on other windows or applications.
+    ///
+    /// `data` lists the same content in one or more formats, each identified by its MIME type
+    /// (for example `text/uri-list` for files). The `icon` is shown under the cursor during the
+    /// drag.
+    ///
+    /// This should be called while a mouse button is held down over the window, typically when
+    /// the cursor moves; the data is dropped where the button gets released. The outcome of the
+    /// operation is reported through `WindowEvent::DragEnded`, which is also emitted right away
+    /// if the drag can't be started.
+    ///
+    /// ## Platform-specific
+    ///
+    /// - **X11:** The icon replaces the cursor during the drag.
+    /// - **Wayland:** The drag can't be started unless a mouse button is held down over the
+    ///   window.
+    /// - **Windows / macOS / iOS / Android / Emscripten:** Always returns an `Err`.
+    #[inline]
+    pub fn start_drag(
+        &self,
+        data: Vec<(String, Vec<u8>)>,
+        icon: Option<Icon>,
+    ) -> Result<(), ExternalError> {
+        self.window.start_drag(data, icon)
     }
 }
 
@@ -638,6 +711,21 @@ impl Window {
         self.window.set_cursor_icon(cursor);
     }
 
+    /// Replaces the cursor of the window with a custom image.
+    ///
+    /// The cursor is shown until it's replaced again, including by a call to `set_cursor_icon`.
+    /// Cursors made of several frames with `CustomCursor::from_frames` are animated, looping
+    /// through their frames.
+    ///
+    /// ## Platform-specific
+    ///
+    /// - **X11:** Animations are played by the X server.
+    /// - **Windows / macOS / iOS / Android / Emscripten:** Has no effect.
+    #[inline]
+    pub fn set_custom_cursor(&self, cursor: CustomCursor) {
+        self.window.set_custom_cursor(cursor);
+    }
+
     /// Changes the position of the cursor in window coordinates.
     ///
     /// ## Platform-specific
@@ -648,17 +736,21 @@ impl Window {
         self.window.set_cursor_position(position)
     }
 
-    /// Grabs the cursor, preventing it from leaving the window.
+    /// Grabs the cursor, either confining it to the window or locking it in place, or releases
+    /// it with `CursorGrabMode::None`.
+    ///
+    /// Returns an `ExternalError::NotSupported` error if the mode isn't supported.
     ///
     /// ## Platform-specific
     ///
-    /// - **macOS:** This presently merely locks the cursor in a fixed location, which looks visually
-    ///   awkward.
-    /// - **Android:** Has no effect.
-    /// - **iOS:** Always returns an Err.
+    /// - **X11 / Windows:** `CursorGrabMode::Locked` isn't supported.
+    /// - **Wayland:** Requires the pointer constraints protocol. The cursor is only grabbed while
+    ///   it's over the window.
+    /// - **macOS / Emscripten:** `CursorGrabMode::Confined` isn't supported.
+    /// - **iOS / Android:** Always returns an Err.
     #[inline]
-    pub fn set_cursor_grab(&self, grab: bool) -> Result<(), ExternalError> {
-        self.window.set_cursor_grab(grab)
+    pub fn set_cursor_grab(&self, mode: CursorGrabMode) -> Result<(), ExternalError> {
+        self.window.set_cursor_grab(mode)
     }
 
     /// Modifies the cursor's visibility.
@@ -784,6 +876,39 @@ impl Default for CursorIcon {
     }
 }
 
+/// Describes how the cursor is grabbed by a window, with `Window::set_cursor_grab`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum CursorGrabMode {
+    /// The cursor moves freely.
+    None,
+    /// The cursor is kept inside the window.
+    Confined,
+    /// The cursor is kept in place, its motion still being reported by
+    /// `DeviceEvent::MouseMotion`.
+    Locked,
+}
+
+/// Describes the kind of text entered with an input method, with `Window::set_ime_purpose`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum ImePurpose {
+    /// Any kind of text.
+    Normal,
+    /// A password, which the input method shouldn't show or remember.
+    Password,
+    /// An email address.
+    Email,
+    /// A number.
+    Number,
+    /// A phone number.
+    Phone,
+    /// A URL.
+    Url,
+    /// Commands typed in a terminal, which the input method shouldn't correct.
+    Terminal,
+}
+
 #[derive(Clone, Debug, PartialEq)]
 pub enum Fullscreen {
     Exclusive(VideoMode),
@@ -4,10 +4,12 @@ use serde::{Deserialize, Serialize};
 use winit::{
     dpi::{LogicalPosition, LogicalSize, PhysicalPosition, PhysicalSize},
     event::{
-        ElementState, KeyboardInput, ModifiersState, MouseButton, MouseScrollDelta, TouchPhase,
-        VirtualKeyCode,
+        DragAction, ElementState, GamepadAxis, GamepadButton, Ime, KeyEvent, KeyboardInput,
+        ModifiersState, MouseButton, MouseScrollDelta, ScrollSource, Selection, TabletTool,
+        TabletToolEvent, TabletToolState, TouchContact, TouchPhase, VirtualKeyCode, WheelDelta,
     },
-    window::CursorIcon,
+    keyboard::{Key, KeyCode, KeyLocation, KeyRepeatInfo, KeyboardLayout, NamedKey},
+    window::{CursorIcon, ImePurpose},
 };
 
 #[allow(dead_code)]
@@ -16,17 +18,40 @@ fn needs_serde<S: Serialize + Deserialize<'static>>() {}
 #[test]
 fn window_serde() {
     needs_serde::<CursorIcon>();
+    needs_serde::<ImePurpose>();
 }
 
 #[test]
 fn events_serde() {
     needs_serde::<KeyboardInput>();
     needs_serde::<TouchPhase>();
+    needs_serde::<TouchContact>();
+    needs_serde::<TabletToolEvent>();
+    needs_serde::<TabletTool>();
+    needs_serde::<TabletToolState>();
     needs_serde::<ElementState>();
     needs_serde::<MouseButton>();
+    needs_serde::<GamepadButton>();
+    needs_serde::<GamepadAxis>();
     needs_serde::<MouseScrollDelta>();
+    needs_serde::<WheelDelta>();
+    needs_serde::<ScrollSource>();
     needs_serde::<VirtualKeyCode>();
     needs_serde::<ModifiersState>();
+    needs_serde::<Selection>();
+    needs_serde::<DragAction>();
+    needs_serde::<Ime>();
+    needs_serde::<KeyEvent>();
+}
+
+#[test]
+fn keyboard_serde() {
+    needs_serde::<KeyCode>();
+    needs_serde::<Key>();
+    needs_serde::<NamedKey>();
+    needs_serde::<KeyLocation>();
+    needs_serde::<KeyboardLayout>();
+    needs_serde::<KeyRepeatInfo>();
 }
 
 #[test]